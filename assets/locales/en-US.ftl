@@ -0,0 +1,249 @@
+# English, also standing in for whatever the other translations lack
+
+## Buttons used on several screens
+
+button-back = Back
+button-continue = Continue
+button-settings = Settings
+button-quit = Quit
+
+## Main menu
+
+game-title = Bullet Hell
+menu-play = Play
+menu-practice = Practice
+menu-high-scores = High scores
+menu-statistics = Statistics
+menu-save-slots = Save slots
+menu-credits = Credits
+
+## Pause menu
+
+paused = Paused
+pause-resume = Resume
+quit-title = Quit?
+quit-message = Progress will be lost
+dialog-yes = Yes
+dialog-no = No
+
+## Game over
+
+game-over = Game over
+time-up = Time up
+game-over-score = Score: { $score }
+game-over-high-score = High score: { $score }
+game-over-new-record = High score: { $score } (new record!)
+game-over-name-entry = New #{ $rank } high score! Name: { $name }
+game-over-kills = Enemies killed: { $kills }
+game-over-time = Time survived: { $time }
+game-over-retry = Retry
+game-over-main-menu = Main Menu
+game-over-save-replay = Save replay
+game-over-replay-saved = Replay saved
+
+## HUD
+
+hud-high-score = HI: { $score }
+hud-hp = HP { $hp }/{ $max }
+hud-combo = { $kills } COMBO x{ $multiplier }
+hud-time-left = TIME { $time }
+boss-warning = WARNING
+
+## Game modes and difficulty
+
+mode-select = Play
+mode-stages = Stage Mode
+mode-endless = Endless
+mode-time-attack = Time Attack
+mode-daily = Daily
+mode-caravan = Caravan
+mode-replays = Replays
+difficulty = Difficulty
+difficulty-easy = Easy
+difficulty-normal = Normal
+difficulty-hard = Hard
+difficulty-lunatic = Lunatic
+
+## Stage banners
+
+banner-stage = Stage { $number } — { $name }
+banner-endless = Endless
+banner-daily = Daily Challenge
+banner-caravan = Caravan
+
+## Practice
+
+practice = Practice
+practice-bombs = Bombs: { $bombs }
+practice-stage = { $number }. { $name }
+practice-boss = { $number }. { $name } Boss
+
+## Stage results
+
+stage-clear = Stage clear
+results-kills = Kills: { $kills }
+results-grazes = Grazes: { $grazes }
+results-damage = Damage taken: { $damage }
+results-time = Time: { $time }
+results-bonus = Bonus: +{ $bonus }
+results-grade = Grade: { $grade }
+results-saved = Progress saved to slot { $slot }
+
+## High scores
+
+high-scores = High scores
+high-scores-rank = { $rank }.
+high-scores-empty = No scores yet
+
+## Replays
+
+replays = Replays
+replays-empty = No replays saved yet
+replay-entry = { $mode }, { $difficulty }: { $score } ({ $date })
+replay-watching = Replay
+
+## Statistics
+
+statistics = Statistics
+statistics-last-run = Last run
+statistics-lifetime = All time
+statistics-runs = Runs
+statistics-time = Time played
+statistics-shots = Shots fired
+statistics-accuracy = Accuracy
+statistics-kills = Enemies killed
+statistics-kills-kind = { $kind ->
+    [grunt] - Grunts
+    [mid_boss] - Mid-bosses
+    [boss] - Bosses
+   *[other] - { $kind }
+}
+statistics-grazes = Bullets grazed
+statistics-bombs = Bombs used
+
+## Save slots
+
+save-slots = Save slots
+slot-empty = Slot { $slot }: empty
+slot-progress = Slot { $slot }: stage { $stage }, { $grades }
+slot-current = { $slot } (current)
+
+## Tutorial
+
+tutorial-move-standard = WASD/arrows to move
+tutorial-move-classic = Arrows to move
+tutorial-shoot-standard = Space to shoot
+tutorial-shoot-classic = Z to shoot
+tutorial-focus = Shift to focus
+tutorial-bomb-standard = B to bomb
+tutorial-bomb-classic = C to bomb
+tutorial-move-custom = { $keys } to move
+tutorial-shoot-custom = { $keys } to shoot
+tutorial-focus-custom = { $keys } to focus
+tutorial-bomb-custom = { $keys } to bomb
+
+## Settings
+
+settings = Settings
+settings-row = { $name }: { $value }
+settings-percent = { $percent }%
+settings-muted = { $volume } (muted)
+settings-on = On
+settings-off = Off
+settings-volume = Volume
+settings-music = Music
+settings-sfx = Sound effects
+settings-fire-mode = Fire
+fire-mode-hold = Hold
+fire-mode-toggle = Toggle
+fire-mode-tap = Tap
+settings-screen-shake = Screen shake
+settings-rumble = Rumble
+settings-bloom = Bloom
+settings-lighting = Lighting
+settings-low-hp-vignette = Low HP vignette
+settings-palette = Palette
+settings-colorblind = Colorblind
+settings-damage-numbers = Damage numbers
+settings-fps-counter = FPS counter
+settings-controls = Controls
+settings-language = Language
+palette-stage = Stage
+palette-classic = Classic
+palette-neon = Neon
+palette-ember = Ember
+colorblind-deuteranopia = Deuteranopia
+colorblind-protanopia = Protanopia
+colorblind-tritanopia = Tritanopia
+controls-standard = Standard
+controls-classic = Classic
+controls-custom = Custom
+
+## Accessibility
+
+accessibility = Accessibility
+accessibility-text-scale = Text size
+accessibility-high-contrast = High contrast
+accessibility-reduced-flashing = Reduced flashing
+accessibility-game-speed = Game speed
+
+## Controls
+
+controls = Controls
+controls-scheme = Preset
+controls-reset = Reset bindings
+controls-keys = Keys
+controls-gamepad = Gamepad
+settings-pointer = Mouse/touch
+pointer-follow = Follow
+pointer-drag = Drag
+reset-bindings-title = Reset?
+reset-bindings-message = Every key and button goes back to its default
+controls-press = Press a key
+controls-press-button = Press a button
+controls-hint = Pick a slot to bind a key or button to it
+controls-waiting = Press a key for { $action }. Esc cancels, Backspace clears.
+controls-waiting-button = Press a button for { $action }. Select or Esc cancels, Backspace clears.
+controls-swapped = { $input } was bound to { $action }, which gets { $replacement } instead
+controls-taken = { $input } was bound to { $action }, which no longer has it
+controls-reserved = { $input } is taken by { $action }
+action-move-up = Move up
+action-move-down = Move down
+action-move-left = Move left
+action-move-right = Move right
+action-fire = Fire
+action-focus = Focus
+action-bomb = Bomb
+action-pause = Pause
+action-menu-up = Menu up
+action-menu-down = Menu down
+action-menu-confirm = Menu confirm
+action-menu-back = Menu back
+action-toggle-mute = Mute toggle
+action-toggle-fps-counter = FPS counter toggle
+action-toggle-performance-overlay = Performance overlay toggle
+
+## Loading
+
+loading = Loading
+missing-assets = Missing assets
+missing-asset = Could not load { $asset }, { $fallback }
+asset-font = the UI font { $name }
+asset-translations = the { $name } translation
+asset-stage-timeline = the timeline of stage { $name }
+asset-caravan-timeline = the caravan timeline
+asset-enemy = the { $name } enemy definition
+asset-balance-table = the balance table
+asset-pattern = the { $name } bullet pattern
+asset-sound-effect = the { $name } sound effect
+asset-music = the music track { $name }
+asset-sprite-sheet = the sprite sheet
+fallback-no-text = text will not render
+fallback-message-ids = text will show as message ids
+fallback-english = showing English instead
+fallback-empty-stage = the stage will be empty
+fallback-empty-caravan = caravan runs will be empty
+fallback-built-in-enemy = using the built-in one
+fallback-unbalanced = enemies are as defined
+fallback-single-bullets = firing single bullets instead
+fallback-silent = it will be silent
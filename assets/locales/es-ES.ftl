@@ -0,0 +1,249 @@
+# Spanish
+
+## Buttons used on several screens
+
+button-back = Volver
+button-continue = Continuar
+button-settings = Opciones
+button-quit = Salir
+
+## Main menu
+
+game-title = Bullet Hell
+menu-play = Jugar
+menu-practice = Práctica
+menu-high-scores = Récords
+menu-statistics = Estadísticas
+menu-save-slots = Partidas guardadas
+menu-credits = Créditos
+
+## Pause menu
+
+paused = Pausa
+pause-resume = Reanudar
+quit-title = ¿Salir?
+quit-message = Se perderá el progreso
+dialog-yes = Sí
+dialog-no = No
+
+## Game over
+
+game-over = Fin de la partida
+time-up = Se acabó el tiempo
+game-over-score = Puntuación: { $score }
+game-over-high-score = Récord: { $score }
+game-over-new-record = Récord: { $score } (¡nuevo récord!)
+game-over-name-entry = ¡Nuevo récord, puesto { $rank }! Nombre: { $name }
+game-over-kills = Enemigos derribados: { $kills }
+game-over-time = Tiempo sobrevivido: { $time }
+game-over-retry = Reintentar
+game-over-main-menu = Menú principal
+game-over-save-replay = Guardar repetición
+game-over-replay-saved = Repetición guardada
+
+## HUD
+
+hud-high-score = RÉC: { $score }
+hud-hp = PV { $hp }/{ $max }
+hud-combo = { $kills } COMBO x{ $multiplier }
+hud-time-left = TIEMPO { $time }
+boss-warning = PELIGRO
+
+## Game modes and difficulty
+
+mode-select = Jugar
+mode-stages = Fases
+mode-endless = Sin fin
+mode-time-attack = Contrarreloj
+mode-daily = Diario
+mode-caravan = Caravana
+mode-replays = Repeticiones
+difficulty = Dificultad
+difficulty-easy = Fácil
+difficulty-normal = Normal
+difficulty-hard = Difícil
+difficulty-lunatic = Lunático
+
+## Stage banners
+
+banner-stage = Fase { $number } — { $name }
+banner-endless = Sin fin
+banner-daily = Desafío diario
+banner-caravan = Caravana
+
+## Practice
+
+practice = Práctica
+practice-bombs = Bombas: { $bombs }
+practice-stage = { $number }. { $name }
+practice-boss = { $number }. Jefe de { $name }
+
+## Stage results
+
+stage-clear = Fase superada
+results-kills = Derribos: { $kills }
+results-grazes = Roces: { $grazes }
+results-damage = Daño recibido: { $damage }
+results-time = Tiempo: { $time }
+results-bonus = Bonificación: +{ $bonus }
+results-grade = Nota: { $grade }
+results-saved = Progreso guardado en la ranura { $slot }
+
+## High scores
+
+high-scores = Récords
+high-scores-rank = { $rank }.
+high-scores-empty = Aún no hay récords
+
+## Replays
+
+replays = Repeticiones
+replays-empty = Aún no hay repeticiones guardadas
+replay-entry = { $mode }, { $difficulty }: { $score } ({ $date })
+replay-watching = Repetición
+
+## Statistics
+
+statistics = Estadísticas
+statistics-last-run = Última partida
+statistics-lifetime = En total
+statistics-runs = Partidas
+statistics-time = Tiempo jugado
+statistics-shots = Disparos
+statistics-accuracy = Precisión
+statistics-kills = Enemigos derribados
+statistics-kills-kind = { $kind ->
+    [grunt] - Soldados
+    [mid_boss] - Subjefes
+    [boss] - Jefes
+   *[other] - { $kind }
+}
+statistics-grazes = Balas rozadas
+statistics-bombs = Bombas usadas
+
+## Save slots
+
+save-slots = Partidas guardadas
+slot-empty = Ranura { $slot }: vacía
+slot-progress = Ranura { $slot }: fase { $stage }, { $grades }
+slot-current = { $slot } (actual)
+
+## Tutorial
+
+tutorial-move-standard = WASD/flechas para moverte
+tutorial-move-classic = Flechas para moverte
+tutorial-shoot-standard = Espacio para disparar
+tutorial-shoot-classic = Z para disparar
+tutorial-focus = Mayús para concentrarte
+tutorial-bomb-standard = B para lanzar una bomba
+tutorial-bomb-classic = C para lanzar una bomba
+tutorial-move-custom = { $keys } para moverte
+tutorial-shoot-custom = { $keys } para disparar
+tutorial-focus-custom = { $keys } para concentrarte
+tutorial-bomb-custom = { $keys } para lanzar una bomba
+
+## Settings
+
+settings = Opciones
+settings-row = { $name }: { $value }
+settings-percent = { $percent } %
+settings-muted = { $volume } (silenciado)
+settings-on = Sí
+settings-off = No
+settings-volume = Volumen
+settings-music = Música
+settings-sfx = Efectos de sonido
+settings-fire-mode = Disparo
+fire-mode-hold = Mantener
+fire-mode-toggle = Alternar
+fire-mode-tap = Pulsar
+settings-screen-shake = Temblor de pantalla
+settings-rumble = Vibración
+settings-bloom = Resplandor
+settings-lighting = Iluminación
+settings-low-hp-vignette = Aviso de poca vida
+settings-palette = Paleta
+settings-colorblind = Daltonismo
+settings-damage-numbers = Números de daño
+settings-fps-counter = Contador de FPS
+settings-controls = Controles
+settings-language = Idioma
+palette-stage = Fase
+palette-classic = Clásica
+palette-neon = Neón
+palette-ember = Brasa
+colorblind-deuteranopia = Deuteranopía
+colorblind-protanopia = Protanopía
+colorblind-tritanopia = Tritanopía
+controls-standard = Estándar
+controls-classic = Clásicos
+controls-custom = Personalizados
+
+## Accessibility
+
+accessibility = Accesibilidad
+accessibility-text-scale = Tamaño del texto
+accessibility-high-contrast = Alto contraste
+accessibility-reduced-flashing = Menos destellos
+accessibility-game-speed = Velocidad del juego
+
+## Controls
+
+controls = Controles
+controls-scheme = Predefinidos
+controls-reset = Restablecer controles
+controls-keys = Teclas
+controls-gamepad = Mando
+settings-pointer = Ratón/táctil
+pointer-follow = Seguir
+pointer-drag = Arrastrar
+reset-bindings-title = ¿Restablecer?
+reset-bindings-message = Todas las teclas y botones volverán a los de siempre
+controls-press = Pulsa una tecla
+controls-press-button = Pulsa un botón
+controls-hint = Elige un hueco para asignarle una tecla o un botón
+controls-waiting = Pulsa una tecla para { $action }. Esc cancela, Retroceso borra.
+controls-waiting-button = Pulsa un botón para { $action }. Select o Esc cancela, Retroceso borra.
+controls-swapped = { $input } era de { $action }, que pasa a tener { $replacement }
+controls-taken = { $input } era de { $action }, que se queda sin él
+controls-reserved = { $input } ya es de { $action }
+action-move-up = Subir
+action-move-down = Bajar
+action-move-left = Izquierda
+action-move-right = Derecha
+action-fire = Disparar
+action-focus = Concentrarse
+action-bomb = Bomba
+action-pause = Pausa
+action-menu-up = Subir en los menús
+action-menu-down = Bajar en los menús
+action-menu-confirm = Aceptar en los menús
+action-menu-back = Volver en los menús
+action-toggle-mute = Silenciar
+action-toggle-fps-counter = Mostrar los FPS
+action-toggle-performance-overlay = Mostrar el rendimiento
+
+## Loading
+
+loading = Cargando
+missing-assets = Faltan archivos
+missing-asset = No se pudo cargar { $asset }: { $fallback }
+asset-font = la fuente { $name }
+asset-translations = la traducción { $name }
+asset-stage-timeline = el guion de la fase { $name }
+asset-caravan-timeline = el guion de la caravana
+asset-enemy = la definición del enemigo { $name }
+asset-balance-table = la tabla de equilibrio
+asset-pattern = el patrón de balas { $name }
+asset-sound-effect = el efecto de sonido { $name }
+asset-music = la pista { $name }
+asset-sprite-sheet = la hoja de sprites
+fallback-no-text = el texto no se verá
+fallback-message-ids = el texto se verá como identificadores
+fallback-english = se mostrará en inglés
+fallback-empty-stage = la fase estará vacía
+fallback-empty-caravan = la caravana estará vacía
+fallback-built-in-enemy = se usará el integrado
+fallback-unbalanced = los enemigos quedan como están definidos
+fallback-single-bullets = disparará balas sueltas
+fallback-silent = no sonará
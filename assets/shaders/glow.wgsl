@@ -0,0 +1,37 @@
+#import bevy_sprite::{
+    mesh2d_vertex_output::VertexOutput,
+    mesh2d_view_bindings::view,
+}
+
+#ifdef TONEMAP_IN_SHADER
+#import bevy_core_pipeline::tonemapping
+#endif
+
+@group(1) @binding(0) var<uniform> color: vec4<f32>;
+@group(1) @binding(1) var<uniform> outline: vec4<f32>;
+
+// Width of the ring, as a fraction of the radius
+const RING_WIDTH: f32 = 0.3;
+
+@fragment
+fn fragment(
+    mesh: VertexOutput,
+) -> @location(0) vec4<f32> {
+    var output_color: vec4<f32> = color;
+    // From the center of the mesh, 1 on the edge of the circle it fits in
+    let offset = (mesh.uv - vec2<f32>(0.5)) * 2.0;
+#ifdef DIAMOND
+    if abs(offset.x) + abs(offset.y) > 1.0 {
+        discard;
+    }
+#endif
+#ifdef RINGED
+    if length(offset) > 1.0 - RING_WIDTH {
+        output_color = outline;
+    }
+#endif
+#ifdef TONEMAP_IN_SHADER
+    output_color = tonemapping::tone_mapping(output_color, view.color_grading);
+#endif
+    return output_color;
+}
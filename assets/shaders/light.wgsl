@@ -0,0 +1,20 @@
+// Lighting is drawn over the playfield by blending against what is already
+// there, see `lighting.rs`. The ambient pass multiplies the scene by `color`,
+// point lights add `color` times the scene back in, fading from the center of
+// the quad out to its edge.
+
+#import bevy_sprite::mesh2d_vertex_output::VertexOutput
+
+@group(1) @binding(0) var<uniform> color: vec4<f32>;
+
+@fragment
+fn fragment(
+    mesh: VertexOutput,
+) -> @location(0) vec4<f32> {
+#ifdef POINT_LIGHT
+    let falloff = clamp(1.0 - length(mesh.uv * 2.0 - 1.0), 0.0, 1.0);
+    return vec4<f32>(color.rgb * falloff * falloff, 1.0);
+#else
+    return color;
+#endif
+}
@@ -0,0 +1,36 @@
+//! Runs the game headless with a saturated playfield and prints per-frame
+//! timings for the movement and collision systems.
+//!
+//! `cargo run --release --example stress -- [--bullets N] [--enemies N] [--frames N]`
+
+use bevy::input::InputPlugin;
+use bevy::prelude::*;
+use bevy_game::stress::StressTestPlugin;
+use bevy_game::GamePlugin;
+
+fn main() {
+    let mut stress = StressTestPlugin::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_else(|| panic!("{flag} expects a number"));
+        match flag.as_str() {
+            "--bullets" => stress.bullets = value,
+            "--enemies" => stress.enemies = value,
+            "--frames" => stress.frames = value,
+            _ => panic!("Unknown argument {flag}"),
+        }
+    }
+
+    App::new()
+        .add_plugins((MinimalPlugins, AssetPlugin::default(), InputPlugin))
+        .init_asset::<Mesh>()
+        .init_asset::<ColorMaterial>()
+        .init_asset::<Font>()
+        .init_asset::<Image>()
+        .init_asset::<TextureAtlas>()
+        .add_plugins((GamePlugin::default(), stress))
+        .run();
+}
@@ -14,7 +14,7 @@ fn main() {
                 }),
                 ..default()
             }),
-            GamePlugin,
+            GamePlugin::default(),
         ))
         .run()
 }
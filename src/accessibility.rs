@@ -0,0 +1,239 @@
+//! Accessibility screen, opened from the settings screen in its place. It
+//! holds the options making the game easier to see and read: the text scale
+//! and high contrast, see `UiTheme`, the colorblind mode, see `palette`,
+//! reduced flashing, which every effect that flashes or shakes checks, and the
+//! game speed, see `game_speed`.
+
+use bevy::prelude::*;
+use bevy::ui::FocusPolicy;
+use fluent::fluent_args;
+
+use crate::bindings::{Action, ActionState};
+use crate::game_speed::GAME_SPEEDS;
+use crate::locale::{update_locale, Locale};
+use crate::modal::no_modal_open;
+use crate::settings::{Settings, SettingsMenuState};
+use crate::theme::UiTheme;
+
+const ROW_WIDTH: f32 = 360.;
+const ROW_HEIGHT: f32 = 36.;
+/// The text scales to pick from, in order
+const TEXT_SCALES: [f32; 3] = [1., 1.25, 1.5];
+
+#[derive(Component)]
+struct AccessibilityMenu;
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+enum AccessibilityButton {
+    TextScale,
+    HighContrast,
+    Colorblind,
+    ReducedFlashing,
+    GameSpeed,
+    Back,
+}
+
+impl AccessibilityButton {
+    fn label(self, settings: &Settings, locale: &Locale) -> String {
+        let percent = |value: f32| {
+            let percent = (value * 100.).round() as u32;
+            locale.format("settings-percent", &fluent_args!["percent" => percent])
+        };
+        let on_off = |on: bool| locale.get(if on { "settings-on" } else { "settings-off" });
+        let (name, value) = match self {
+            AccessibilityButton::TextScale => {
+                ("accessibility-text-scale", percent(settings.text_scale))
+            }
+            AccessibilityButton::HighContrast => (
+                "accessibility-high-contrast",
+                on_off(settings.high_contrast),
+            ),
+            AccessibilityButton::Colorblind => (
+                "settings-colorblind",
+                locale.get(settings.colorblind.label()),
+            ),
+            AccessibilityButton::ReducedFlashing => (
+                "accessibility-reduced-flashing",
+                on_off(settings.reduced_flashing),
+            ),
+            AccessibilityButton::GameSpeed => {
+                ("accessibility-game-speed", percent(settings.game_speed))
+            }
+            AccessibilityButton::Back => return locale.get("button-back"),
+        };
+        locale.format(
+            "settings-row",
+            &fluent_args!["name" => locale.get(name), "value" => value],
+        )
+    }
+
+    /// Steps the option to its next value, wrapping around at the end
+    fn cycle(self, settings: &mut Settings) {
+        // Any value not in the list, from an edited file, goes back to the
+        // first
+        let next = |values: &[f32], value: f32| {
+            let next = values
+                .iter()
+                .position(|other| *other == value)
+                .map_or(0, |index| (index + 1) % values.len());
+            values[next]
+        };
+        match self {
+            AccessibilityButton::TextScale => {
+                settings.text_scale = next(&TEXT_SCALES, settings.text_scale)
+            }
+            AccessibilityButton::HighContrast => settings.high_contrast = !settings.high_contrast,
+            AccessibilityButton::Colorblind => settings.colorblind = settings.colorblind.next(),
+            AccessibilityButton::ReducedFlashing => {
+                settings.reduced_flashing = !settings.reduced_flashing
+            }
+            AccessibilityButton::GameSpeed => {
+                settings.game_speed = next(&GAME_SPEEDS, settings.game_speed)
+            }
+            AccessibilityButton::Back => {}
+        }
+    }
+}
+
+/// Text showing the current value of an accessibility row
+#[derive(Component)]
+struct AccessibilityLabel(AccessibilityButton);
+
+pub struct AccessibilityPlugin;
+
+impl Plugin for AccessibilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(SettingsMenuState::Accessibility),
+            spawn_accessibility_menu,
+        )
+        .add_systems(
+            OnExit(SettingsMenuState::Accessibility),
+            despawn_accessibility_menu,
+        )
+        .add_systems(
+            Update,
+            (
+                accessibility_buttons,
+                back_on_escape.run_if(no_modal_open),
+                update_accessibility_labels.after(update_locale),
+            )
+                .chain()
+                .run_if(in_state(SettingsMenuState::Accessibility)),
+        );
+    }
+}
+
+fn spawn_accessibility_menu(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    theme: Res<UiTheme>,
+    locale: Res<Locale>,
+) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(4.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.9).into(),
+                // Keeps the mouse off the menu underneath
+                focus_policy: FocusPolicy::Block,
+                // Above the pause or main menu the settings were opened from
+                z_index: ZIndex::Global(1),
+                ..default()
+            },
+            AccessibilityMenu,
+        ))
+        .with_children(|parent| {
+            parent.spawn(locale.text("accessibility", theme.title.clone()));
+            for button in [
+                AccessibilityButton::TextScale,
+                AccessibilityButton::HighContrast,
+                AccessibilityButton::Colorblind,
+                AccessibilityButton::ReducedFlashing,
+                AccessibilityButton::GameSpeed,
+                AccessibilityButton::Back,
+            ] {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                min_width: Val::Px(ROW_WIDTH),
+                                height: Val::Px(ROW_HEIGHT),
+                                border: UiRect::all(Val::Px(3.)),
+                                padding: UiRect::horizontal(Val::Px(12.)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            border_color: BorderColor(Color::BLACK),
+                            background_color: Color::WHITE.into(),
+                            ..default()
+                        },
+                        button,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            TextBundle::from_section(
+                                button.label(&settings, &locale),
+                                theme.label.clone(),
+                            ),
+                            AccessibilityLabel(button),
+                        ));
+                    });
+            }
+        });
+}
+
+fn despawn_accessibility_menu(
+    mut commands: Commands,
+    query: Query<Entity, With<AccessibilityMenu>>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn accessibility_buttons(
+    interaction_query: Query<(&Interaction, &AccessibilityButton), Changed<Interaction>>,
+    mut settings: ResMut<Settings>,
+    mut next_state: ResMut<NextState<SettingsMenuState>>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            AccessibilityButton::Back => next_state.set(SettingsMenuState::Open),
+            _ => button.cycle(&mut settings),
+        }
+    }
+}
+
+/// Back to the settings screen
+fn back_on_escape(actions: Res<ActionState>, mut next_state: ResMut<NextState<SettingsMenuState>>) {
+    if actions.just_pressed(Action::MenuBack) {
+        next_state.set(SettingsMenuState::Open);
+    }
+}
+
+fn update_accessibility_labels(
+    settings: Res<Settings>,
+    locale: Res<Locale>,
+    mut labels: Query<(&AccessibilityLabel, &mut Text)>,
+) {
+    if !settings.is_changed() && !locale.is_changed() {
+        return;
+    }
+    for (label, mut text) in labels.iter_mut() {
+        text.sections[0].value = label.0.label(&settings, &locale);
+    }
+}
@@ -0,0 +1,327 @@
+//! Music and sound effects, each on their own channel. The channel volumes
+//! follow the volume settings, so changing them applies to whatever is already
+//! playing too.
+//!
+//! Music goes through `MusicController`: systems say which track should be
+//! playing and the controller crossfades to it, so the menu, the stages and
+//! whatever comes between never cut in or out abruptly. The track can also be
+//! ducked under something more important, like the boss siren.
+//!
+//! Sound effects are asked for with `PlaySfx` events, which a single system
+//! plays at the end of the frame. Every effect has its own mix level, under
+//! the effects volume, and a cap on how many copies of it can start in the
+//! same frame, so a volley or a dense collision doesn't stack dozens of them.
+//! Effects coming from somewhere on the playfield are panned to match, so an
+//! explosion at the left edge is heard on the left. The ones that repeat all
+//! the time are also played slightly off pitch at random, so a stream of them
+//! doesn't sound like a machine gun.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_kira_audio::{
+    AudioApp, AudioChannel, AudioControl, AudioInstance, AudioSource, AudioSystemSet, AudioTween,
+};
+use rand::Rng;
+
+use crate::settings::Settings;
+use crate::state::AppState;
+use crate::Playfield;
+
+pub(crate) const MENU_MUSIC: &str = "audio/menu.wav";
+const CROSSFADE_SECONDS: f32 = 1.5;
+const DUCK_SECONDS: f32 = 0.5;
+/// Fraction of the music volume a ducked track plays at
+const DUCKED_LEVEL: f64 = 0.3;
+/// How far from the center effects at the very edge of the playfield are
+/// panned, 0.5 being hard left or right
+const MAX_PAN: f32 = 0.4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SfxId {
+    PlayerShot,
+    EnemyShot,
+    /// A player bullet struck an enemy
+    Impact,
+    Explosion,
+    PlayerHit,
+    /// Repeats while the player is low on HP
+    LowHp,
+    /// The boss warning
+    Siren,
+}
+
+impl SfxId {
+    const ALL: [SfxId; 7] = [
+        SfxId::PlayerShot,
+        SfxId::EnemyShot,
+        SfxId::Impact,
+        SfxId::Explosion,
+        SfxId::PlayerHit,
+        SfxId::LowHp,
+        SfxId::Siren,
+    ];
+
+    fn path(self) -> &'static str {
+        match self {
+            SfxId::PlayerShot => "audio/sfx/player_shot.wav",
+            SfxId::EnemyShot => "audio/sfx/enemy_shot.wav",
+            SfxId::Impact => "audio/sfx/impact.wav",
+            SfxId::Explosion => "audio/sfx/explosion.wav",
+            SfxId::PlayerHit => "audio/sfx/player_hit.wav",
+            SfxId::LowHp => "audio/sfx/low_hp.wav",
+            SfxId::Siren => "audio/siren.wav",
+        }
+    }
+
+    /// Mix level, from 0 to 1. Effects that fire constantly sit lower so the
+    /// rare ones stand out.
+    fn volume(self) -> f32 {
+        match self {
+            SfxId::PlayerShot => 0.25,
+            SfxId::EnemyShot => 0.3,
+            SfxId::Impact => 0.4,
+            SfxId::Explosion => 0.8,
+            SfxId::PlayerHit => 1.,
+            SfxId::LowHp => 0.6,
+            SfxId::Siren => 1.,
+        }
+    }
+
+    /// How far off the regular pitch each play can land, as a fraction of it
+    fn pitch_variation(self) -> f32 {
+        match self {
+            SfxId::PlayerShot | SfxId::EnemyShot => 0.05,
+            SfxId::Impact => 0.1,
+            SfxId::Explosion => 0.08,
+            _ => 0.,
+        }
+    }
+
+    /// Copies that can start in the same frame, past which more requests are
+    /// dropped
+    fn max_per_frame(self) -> usize {
+        match self {
+            SfxId::EnemyShot | SfxId::Explosion => 2,
+            SfxId::Impact => 3,
+            _ => 1,
+        }
+    }
+}
+
+/// Asks for a sound effect to be played
+#[derive(Event, Clone, Copy, Debug)]
+pub struct PlaySfx {
+    pub sfx: SfxId,
+    /// Where on the playfield the sound comes from, `None` to play it centered
+    pub position: Option<Vec2>,
+}
+
+impl PlaySfx {
+    pub fn new(sfx: SfxId) -> Self {
+        Self {
+            sfx,
+            position: None,
+        }
+    }
+
+    pub fn at(sfx: SfxId, position: Vec2) -> Self {
+        Self {
+            sfx,
+            position: Some(position),
+        }
+    }
+}
+
+/// Handles to every sound effect, loaded up front so the first play of each
+/// doesn't wait on the disk
+#[derive(Resource)]
+pub(crate) struct SoundEffects(pub(crate) HashMap<SfxId, Handle<AudioSource>>);
+
+impl FromWorld for SoundEffects {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        Self(
+            SfxId::ALL
+                .into_iter()
+                .map(|sfx| (sfx, asset_server.load(sfx.path())))
+                .collect(),
+        )
+    }
+}
+
+/// Audio channel for the sound effects, apart from the music
+#[derive(Resource)]
+struct SfxChannel;
+
+/// Audio channel for the music
+#[derive(Resource)]
+pub(crate) struct MusicChannel;
+
+/// The looping track that should be playing, and the one that is. Whenever
+/// they differ, the old track fades out while the new one fades in.
+#[derive(Resource, Default)]
+pub struct MusicController {
+    wanted: Option<String>,
+    playing: Option<(String, Handle<AudioInstance>)>,
+    ducked: bool,
+    /// Whether the playing track has been turned down to match `ducked`
+    ducked_playing: bool,
+}
+
+impl MusicController {
+    /// Crossfades to `track`, or fades out to silence on `None`. Asking for
+    /// the track already playing leaves it be.
+    pub fn play(&mut self, track: Option<&str>) {
+        self.wanted = track.map(str::to_string);
+    }
+
+    /// The track playing, or fading in, and its instance
+    pub fn playing(&self) -> Option<(&str, &Handle<AudioInstance>)> {
+        self.playing
+            .as_ref()
+            .map(|(track, instance)| (track.as_str(), instance))
+    }
+
+    /// Turns the playing track down, or back up. Only lasts until the next
+    /// track starts, which always comes in at full volume.
+    pub fn duck(&mut self, ducked: bool) {
+        self.ducked = ducked;
+    }
+}
+
+pub struct GameAudioPlugin;
+
+impl Plugin for GameAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_audio_channel::<SfxChannel>()
+            .add_audio_channel::<MusicChannel>()
+            .add_event::<PlaySfx>()
+            .init_resource::<SoundEffects>()
+            .init_resource::<MusicController>()
+            .add_systems(
+                Update,
+                apply_volume_settings.run_if(resource_changed::<Settings>()),
+            )
+            .add_systems(OnEnter(AppState::MainMenu), play_menu_music)
+            .add_systems(OnExit(AppState::Running), fade_out_music)
+            .add_systems(Update, crossfade_music)
+            // Once the channel's volume is set, which sets the track's too
+            .add_systems(
+                PostUpdate,
+                duck_music.after(AudioSystemSet::PlayTypedChannels),
+            )
+            .add_systems(PostUpdate, play_sfx);
+    }
+}
+
+/// Turning the music channel up or down brings a ducked track back to the full
+/// level, so it gets ducked again
+fn apply_volume_settings(
+    settings: Res<Settings>,
+    music: Res<AudioChannel<MusicChannel>>,
+    sfx: Res<AudioChannel<SfxChannel>>,
+    mut music_controller: ResMut<MusicController>,
+) {
+    music.set_volume(settings.music_level());
+    sfx.set_volume(settings.sfx_level());
+    music_controller.ducked_playing = false;
+}
+
+fn play_menu_music(mut music: ResMut<MusicController>) {
+    music.play(Some(MENU_MUSIC));
+}
+
+/// Leaving a run to the main menu goes straight to the menu music, since its
+/// `OnEnter` runs after this
+fn fade_out_music(mut music: ResMut<MusicController>) {
+    music.play(None);
+}
+
+fn crossfade_music(
+    mut music: ResMut<MusicController>,
+    asset_server: Res<AssetServer>,
+    channel: Res<AudioChannel<MusicChannel>>,
+    mut instances: ResMut<Assets<AudioInstance>>,
+) {
+    let playing = music.playing.as_ref().map(|(track, _)| track);
+    if playing == music.wanted.as_ref() {
+        return;
+    }
+    let fade = AudioTween::linear(Duration::from_secs_f32(CROSSFADE_SECONDS));
+    if let Some((_, instance)) = music.playing.take() {
+        if let Some(instance) = instances.get_mut(&instance) {
+            instance.stop(fade.clone());
+        }
+    }
+    if let Some(track) = music.wanted.clone() {
+        let instance = channel
+            .play(asset_server.load(&track))
+            .looped()
+            .fade_in(fade)
+            .handle();
+        music.playing = Some((track, instance));
+    }
+    music.ducked = false;
+    music.ducked_playing = false;
+}
+
+fn duck_music(
+    mut music: ResMut<MusicController>,
+    settings: Res<Settings>,
+    mut instances: ResMut<Assets<AudioInstance>>,
+) {
+    if music.ducked == music.ducked_playing {
+        return;
+    }
+    let level = if music.ducked {
+        settings.music_level() * DUCKED_LEVEL
+    } else {
+        settings.music_level()
+    };
+    if let Some((_, instance)) = &music.playing {
+        if let Some(instance) = instances.get_mut(instance) {
+            instance.set_volume(
+                level,
+                AudioTween::linear(Duration::from_secs_f32(DUCK_SECONDS)),
+            );
+        }
+    }
+    music.ducked_playing = music.ducked;
+}
+
+fn play_sfx(
+    mut events: EventReader<PlaySfx>,
+    sound_effects: Res<SoundEffects>,
+    playfield: Res<Playfield>,
+    channel: Res<AudioChannel<SfxChannel>>,
+) {
+    // Not the game's RNG, which has to stay in step with the fixed timestep
+    // for seeded runs
+    let mut rng = rand::thread_rng();
+    let mut started = HashMap::<SfxId, usize>::new();
+    for PlaySfx { sfx, position } in events.read() {
+        let count = started.entry(*sfx).or_default();
+        if *count >= sfx.max_per_frame() {
+            continue;
+        }
+        *count += 1;
+        // From 0 for hard left to 1 for hard right
+        let panning = position.map_or(0.5, |position| {
+            let offset = (position.x - playfield.rect.center().x) / playfield.rect.half_size().x;
+            0.5 + offset.clamp(-1., 1.) * MAX_PAN
+        });
+        let variation = sfx.pitch_variation();
+        let playback_rate = if variation > 0. {
+            1. + rng.gen_range(-variation..variation)
+        } else {
+            1.
+        };
+        channel
+            .play(sound_effects.0[sfx].clone())
+            .with_volume(sfx.volume() as f64)
+            .with_panning(panning as f64)
+            .with_playback_rate(playback_rate as f64);
+    }
+}
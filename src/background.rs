@@ -0,0 +1,170 @@
+//! Scrolling background behind the playfield. Layers of stars, clouds or
+//! ground drift down at different speeds for a bit of depth, the nearer the
+//! faster. Every stage picks its own layers with `BackgroundLayers`. How fast
+//! they go is up to `BackgroundScroll`, which the stage timeline sets: faster
+//! through transitions, stopped for bosses.
+
+use bevy::prelude::*;
+use bevy::sprite::MaterialMesh2dBundle;
+
+use crate::state::{gameplay_active, AppState};
+use crate::Playfield;
+
+/// Scroll speed of the near layer at a `BackgroundScroll` speed of 1
+const BASE_SPEED: f32 = 120.;
+/// How quickly the actual speed catches up with a new `BackgroundScroll`
+const SPEED_EASING: f32 = 2.;
+/// The 2D camera sees down to z = -0.1, so this keeps the background behind
+/// everything else while still in view
+const BACKGROUND_Z: f32 = -0.08;
+/// How much nearer every layer is drawn than the one before
+const LAYER_Z_STEP: f32 = 0.005;
+
+/// A layer of identical pieces, like stars or clouds, spread over the
+/// playfield and scrolling down with it
+#[derive(Clone, Debug)]
+pub struct BackgroundLayer {
+    pub count: usize,
+    pub size: Vec2,
+    pub color: Color,
+    /// Fraction of the scroll speed this layer moves at
+    pub parallax: f32,
+}
+
+pub const DISTANT_STARS: BackgroundLayer = BackgroundLayer {
+    count: 60,
+    size: Vec2::splat(2.),
+    color: Color::rgba(1., 1., 1., 0.25),
+    parallax: 0.5,
+};
+
+pub const NEAR_STARS: BackgroundLayer = BackgroundLayer {
+    count: 30,
+    size: Vec2::splat(4.),
+    color: Color::rgba(1., 1., 1., 0.5),
+    parallax: 1.,
+};
+
+pub const GROUND: BackgroundLayer = BackgroundLayer {
+    count: 8,
+    size: Vec2::new(240., 140.),
+    color: Color::rgba(0.1, 0.05, 0.05, 0.5),
+    parallax: 0.4,
+};
+
+pub const CLOUDS: BackgroundLayer = BackgroundLayer {
+    count: 5,
+    size: Vec2::new(200., 60.),
+    color: Color::rgba(1., 1., 1., 0.08),
+    parallax: 1.6,
+};
+
+/// The layers behind the playfield, from the farthest to the nearest
+#[derive(Resource, Clone, Debug)]
+pub struct BackgroundLayers(pub Vec<BackgroundLayer>);
+
+impl Default for BackgroundLayers {
+    fn default() -> Self {
+        Self(vec![DISTANT_STARS, NEAR_STARS])
+    }
+}
+
+/// How fast the background scrolls, 1 being the normal speed
+#[derive(Resource, Debug)]
+pub struct BackgroundScroll {
+    pub speed: f32,
+}
+
+impl Default for BackgroundScroll {
+    fn default() -> Self {
+        Self { speed: 1. }
+    }
+}
+
+#[derive(Component)]
+struct BackgroundPiece {
+    height: f32,
+    parallax: f32,
+}
+
+pub struct BackgroundPlugin;
+
+impl Plugin for BackgroundPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BackgroundScroll>()
+            .init_resource::<BackgroundLayers>()
+            .add_systems(
+                Update,
+                (
+                    spawn_background.run_if(
+                        in_state(AppState::Running)
+                            .and_then(resource_changed::<BackgroundLayers>()),
+                    ),
+                    scroll_background.run_if(gameplay_active),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Replaces the background whenever a stage brings its own layers
+fn spawn_background(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    playfield: Res<Playfield>,
+    layers: Res<BackgroundLayers>,
+    pieces: Query<Entity, With<BackgroundPiece>>,
+) {
+    for entity in pieces.iter() {
+        commands.entity(entity).despawn();
+    }
+    let rect = playfield.rect;
+    for (depth, layer) in layers.0.iter().enumerate() {
+        let mesh = meshes.add(shape::Quad::new(layer.size).into());
+        let material = materials.add(ColorMaterial::from(layer.color));
+        // Pieces wrap around once fully off screen, so spread them over the
+        // playfield plus their own height
+        let span = rect.height() + layer.size.y;
+        for i in 0..layer.count {
+            // Spread the pieces out evenly without touching the gameplay RNG
+            let x = rect.min.x + rect.width() * (i as f32 * 0.618_034).fract();
+            let y = rect.min.y - layer.size.y / 2. + span * ((i as f32 + 0.5) / layer.count as f32);
+            commands.spawn((
+                MaterialMesh2dBundle {
+                    mesh: mesh.clone().into(),
+                    material: material.clone(),
+                    transform: Transform::from_xyz(
+                        x,
+                        y,
+                        BACKGROUND_Z + depth as f32 * LAYER_Z_STEP,
+                    ),
+                    ..default()
+                },
+                BackgroundPiece {
+                    height: layer.size.y,
+                    parallax: layer.parallax,
+                },
+            ));
+        }
+    }
+}
+
+fn scroll_background(
+    time: Res<Time>,
+    scroll: Res<BackgroundScroll>,
+    playfield: Res<Playfield>,
+    mut speed: Local<f32>,
+    mut pieces: Query<(&mut Transform, &BackgroundPiece)>,
+) {
+    let easing = (SPEED_EASING * time.delta_seconds()).min(1.);
+    *speed += (scroll.speed - *speed) * easing;
+
+    let rect = playfield.rect;
+    for (mut transform, piece) in pieces.iter_mut() {
+        transform.translation.y -= BASE_SPEED * *speed * piece.parallax * time.delta_seconds();
+        if transform.translation.y < rect.min.y - piece.height / 2. {
+            transform.translation.y += rect.height() + piece.height;
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! Balance tables: per-difficulty tweaks to enemies, kept in a CSV file at
+//! `assets/balance/enemies.balance.csv` so they can be tuned in a spreadsheet.
+//! Every row tweaks one enemy on one difficulty:
+//!
+//! ```csv
+//! enemy,difficulty,hp_multiplier,score
+//! grunt,lunatic,1.2,80
+//! ```
+//!
+//! `hp_multiplier` applies on top of the difficulty's own, and `score` takes
+//! the place of the definition's. Either can be left empty to keep what the
+//! definition has, and enemies without a row are left as they are.
+//! `EnemyBook::balanced` looks definitions up with the table applied. With the
+//! `dev` feature the file is watched, so edits apply to enemies spawned from
+//! then on.
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::log;
+use bevy::prelude::*;
+use bevy::utils::{BoxedFuture, HashMap};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::difficulty::Difficulty;
+use crate::enemies::EnemyDefinitions;
+use crate::state::AppState;
+
+const ENEMY_BALANCE_PATH: &str = "balance/enemies.balance.csv";
+
+/// What a row of the table changes about an enemy
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EnemyBalance {
+    /// Multiplier on its HP, on top of the difficulty's
+    pub hp_multiplier: Option<f32>,
+    /// Points for destroying it
+    pub score: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct BalanceRow {
+    enemy: String,
+    difficulty: Difficulty,
+    hp_multiplier: Option<f32>,
+    score: Option<u32>,
+}
+
+/// Every row of a balance table, by enemy name and difficulty
+#[derive(Asset, TypePath, Debug, Default)]
+pub struct BalanceTable(pub HashMap<(String, Difficulty), EnemyBalance>);
+
+impl BalanceTable {
+    /// Parses a table, or says what is wrong with every row that doesn't make
+    /// sense
+    pub fn from_csv(bytes: &[u8]) -> Result<Self, BalanceTableLoaderError> {
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .comment(Some(b'#'))
+            .from_reader(bytes);
+        let mut table = HashMap::new();
+        let mut problems = Vec::new();
+        for row in reader.deserialize() {
+            let row: BalanceRow = row?;
+            let name = format!("{} on {:?}", row.enemy, row.difficulty);
+            if let Some(multiplier) = row.hp_multiplier {
+                if !(multiplier > 0. && multiplier.is_finite()) {
+                    problems.push(format!(
+                        "`hp_multiplier` of {name} must be above 0, got {multiplier}"
+                    ));
+                }
+            }
+            let balance = EnemyBalance {
+                hp_multiplier: row.hp_multiplier,
+                score: row.score,
+            };
+            if table.insert((row.enemy, row.difficulty), balance).is_some() {
+                problems.push(format!("{name} has more than one row"));
+            }
+        }
+        if !problems.is_empty() {
+            return Err(BalanceTableLoaderError::Invalid(problems));
+        }
+        Ok(Self(table))
+    }
+
+    pub fn get(&self, enemy: &str, difficulty: Difficulty) -> Option<&EnemyBalance> {
+        self.0.get(&(enemy.to_string(), difficulty))
+    }
+}
+
+/// The balance table applied to enemies
+#[derive(Resource, Debug)]
+pub struct EnemyBalanceTable(pub Handle<BalanceTable>);
+
+impl FromWorld for EnemyBalanceTable {
+    fn from_world(world: &mut World) -> Self {
+        Self(world.resource::<AssetServer>().load(ENEMY_BALANCE_PATH))
+    }
+}
+
+#[derive(Default)]
+pub struct BalanceTableLoader;
+
+#[derive(Debug, Error)]
+pub enum BalanceTableLoaderError {
+    #[error("could not read the balance table: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse the balance table: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("invalid balance table: {}", .0.join("; "))]
+    Invalid(Vec<String>),
+}
+
+impl AssetLoader for BalanceTableLoader {
+    type Asset = BalanceTable;
+    type Settings = ();
+    type Error = BalanceTableLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<BalanceTable, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            BalanceTable::from_csv(&bytes)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["balance.csv"]
+    }
+}
+
+pub struct BalancePlugin;
+
+impl Plugin for BalancePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<BalanceTable>()
+            .init_asset_loader::<BalanceTableLoader>()
+            .init_resource::<EnemyBalanceTable>()
+            .add_systems(OnExit(AppState::Loading), check_enemy_names)
+            .add_systems(Update, log_balance_reloads);
+    }
+}
+
+/// Warns about rows for enemies that don't exist, which would otherwise
+/// quietly change nothing
+fn check_enemy_names(
+    handle: Res<EnemyBalanceTable>,
+    tables: Res<Assets<BalanceTable>>,
+    enemies: Res<EnemyDefinitions>,
+) {
+    let Some(table) = tables.get(&handle.0) else {
+        return;
+    };
+    for (enemy, difficulty) in table.0.keys() {
+        if !enemies.0.contains_key(enemy) {
+            log::warn!(
+                "{ENEMY_BALANCE_PATH} has a row for an unknown enemy {enemy:?} on {difficulty:?}"
+            );
+        }
+    }
+}
+
+fn log_balance_reloads(
+    mut events: EventReader<AssetEvent<BalanceTable>>,
+    asset_server: Res<AssetServer>,
+) {
+    for event in events.read() {
+        if let AssetEvent::Modified { id } = event {
+            let path = asset_server.get_path(*id);
+            log::info!("Reloaded balance table {path:?}");
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! Stage intro banner that fades in and out at the start of each stage.
+//!
+//! Stages announce themselves by writing the `StageBanner` resource: the banner
+//! plays whenever it is written to, even with the same text as before.
+
+use bevy::prelude::*;
+use fluent::fluent_args;
+
+use crate::locale::Locale;
+use crate::state::gameplay_active;
+use crate::theme::UiTheme;
+
+const FADE_IN_SECONDS: f32 = 0.5;
+const HOLD_SECONDS: f32 = 1.5;
+const FADE_OUT_SECONDS: f32 = 0.5;
+
+/// Text shown by the intro banner of the current stage
+#[derive(Resource, Clone, Debug, Default, PartialEq)]
+pub struct StageBanner {
+    pub text: String,
+}
+
+impl StageBanner {
+    /// The standard "Stage N — Name" banner
+    pub fn for_stage(locale: &Locale, number: u32, name: &str) -> Self {
+        Self {
+            text: locale.format(
+                "banner-stage",
+                &fluent_args!["number" => number, "name" => name],
+            ),
+        }
+    }
+}
+
+pub struct BannerPlugin;
+
+impl Plugin for BannerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StageBanner>().add_systems(
+            Update,
+            (
+                show_stage_banner.run_if(resource_changed::<StageBanner>()),
+                fade_banners,
+            )
+                .chain()
+                .run_if(gameplay_active),
+        );
+    }
+}
+
+#[derive(Component)]
+struct Banner {
+    elapsed: f32,
+}
+
+fn show_stage_banner(
+    mut commands: Commands,
+    stage_banner: Res<StageBanner>,
+    theme: Res<UiTheme>,
+    banners: Query<Entity, With<Banner>>,
+) {
+    for entity in banners.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let mut style = theme.title.clone();
+    style.font_size = 56.;
+    style.color.set_a(0.);
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    top: Val::Percent(35.),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            Banner { elapsed: 0. },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(stage_banner.text.clone(), style));
+        });
+}
+
+fn fade_banners(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut banners: Query<(Entity, &mut Banner, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    for (entity, mut banner, children) in banners.iter_mut() {
+        banner.elapsed += time.delta_seconds();
+        let fade_out_start = FADE_IN_SECONDS + HOLD_SECONDS;
+        if banner.elapsed >= fade_out_start + FADE_OUT_SECONDS {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let alpha = if banner.elapsed < FADE_IN_SECONDS {
+            banner.elapsed / FADE_IN_SECONDS
+        } else if banner.elapsed < fade_out_start {
+            1.
+        } else {
+            1. - (banner.elapsed - fade_out_start) / FADE_OUT_SECONDS
+        };
+        let mut texts = texts.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            for section in text.sections.iter_mut() {
+                section.style.color.set_a(alpha);
+            }
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! Beats of the music, for patterns that follow the soundtrack. While a track
+//! with a known tempo plays, a `BeatEvent` goes out on every beat, and enemies
+//! with `FireOnBeat` shoot on those instead of on their gun's cooldown. The
+//! boss does, to fire in time with its theme. Without a tempo to follow, or
+//! without this plugin, those guns go back to their cooldown.
+//!
+//! Beats come from the playback position rather than the fixed timestep, so
+//! they are left out in deterministic mode to keep runs reproducible. Replays
+//! record them instead, and play them back in place of the track's. They are
+//! also left out below full game speed, where the music would run ahead of
+//! the action.
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_kira_audio::{AudioInstance, PlaybackState};
+
+use crate::audio::{MusicController, PlaySfx};
+use crate::boss_music::BOSS_MUSIC;
+use crate::bullet::Gun;
+use crate::collision::not_in_hitstop;
+use crate::config::GameConfig;
+use crate::difficulty::Difficulty;
+use crate::enemy::{enemy_shots, fire_enemy_bullet, Enemy};
+use crate::game_speed::full_game_speed;
+use crate::glow::GlowMaterial;
+use crate::palette::Palette;
+use crate::patterns::PatternBook;
+use crate::rank::Rank;
+use crate::replay::watching_replay;
+use crate::state::AppState;
+use crate::{DeterministicMode, GameSet};
+
+#[derive(Clone, Copy, Debug)]
+pub struct Tempo {
+    pub bpm: f32,
+    /// Seconds into the track of its first beat
+    pub offset: f32,
+}
+
+/// Tempo of every track that has one, by asset path
+#[derive(Resource, Debug)]
+pub struct TrackTempos(pub HashMap<String, Tempo>);
+
+impl Default for TrackTempos {
+    fn default() -> Self {
+        Self(
+            [(
+                BOSS_MUSIC.to_string(),
+                Tempo {
+                    bpm: 150.,
+                    offset: 0.,
+                },
+            )]
+            .into_iter()
+            .collect(),
+        )
+    }
+}
+
+/// A beat of the playing track, counted from when it started
+#[derive(Event, Clone, Copy, Debug)]
+pub struct BeatEvent {
+    pub beat: u64,
+}
+
+/// Shoots on every `every`th beat, counting from the first
+#[derive(Component, Clone, Copy, Debug)]
+pub struct FireOnBeat {
+    pub every: u64,
+}
+
+/// Follows the playing track, beat by beat
+#[derive(Resource, Default, Debug)]
+pub struct BeatClock {
+    following: Option<Handle<AudioInstance>>,
+    /// Last beat seen, counted from the start of the loop
+    last_beat: Option<i64>,
+    /// Beats sent since the track started
+    beats: u64,
+    /// Set while a replay sends the beats, standing in for the track
+    pub(crate) replayed: bool,
+}
+
+impl BeatClock {
+    /// Whether a track with a tempo is playing
+    pub fn running(&self) -> bool {
+        self.following.is_some() || self.replayed
+    }
+}
+
+pub struct BeatPlugin;
+
+impl Plugin for BeatPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TrackTempos>()
+            .init_resource::<BeatClock>()
+            .add_event::<BeatEvent>()
+            .add_systems(OnEnter(AppState::Running), reset_beat_clock)
+            .add_systems(
+                FixedUpdate,
+                (
+                    emit_beats.run_if(
+                        not(resource_exists::<DeterministicMode>())
+                            .and_then(not(watching_replay))
+                            .and_then(full_game_speed),
+                    ),
+                    fire_on_beat,
+                )
+                    .chain()
+                    .after(enemy_shots)
+                    .run_if(not_in_hitstop)
+                    .in_set(GameSet::Movement),
+            );
+    }
+}
+
+/// Every run starts with the clock stopped, whatever the last one left it
+/// following, and picks the track up on its first tick
+fn reset_beat_clock(mut clock: ResMut<BeatClock>) {
+    *clock = BeatClock::default();
+}
+
+fn emit_beats(
+    music: Res<MusicController>,
+    tempos: Res<TrackTempos>,
+    instances: Res<Assets<AudioInstance>>,
+    mut clock: ResMut<BeatClock>,
+    mut beat_events: EventWriter<BeatEvent>,
+) {
+    let playing = music.playing().and_then(|(track, instance)| {
+        let tempo = tempos.0.get(track)?;
+        let PlaybackState::Playing { position } = instances.get(instance)?.state() else {
+            return None;
+        };
+        let beat = ((position as f32 - tempo.offset) * tempo.bpm / 60.).floor() as i64;
+        Some((instance, beat))
+    });
+    let Some((instance, beat)) = playing else {
+        *clock = BeatClock::default();
+        return;
+    };
+
+    if clock.following.as_ref() != Some(instance) {
+        *clock = BeatClock {
+            following: Some(instance.clone()),
+            ..default()
+        };
+    }
+    // Looping back to the start counts as a new beat too
+    if beat < 0 || clock.last_beat == Some(beat) {
+        return;
+    }
+    clock.last_beat = Some(beat);
+    beat_events.send(BeatEvent { beat: clock.beats });
+    clock.beats += 1;
+}
+
+pub(crate) fn fire_on_beat(
+    mut commands: Commands,
+    mut beat_events: EventReader<BeatEvent>,
+    query: Query<(Entity, &Transform, &Gun, &FireOnBeat), With<Enemy>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<GlowMaterial>>,
+    palette: Res<Palette>,
+    difficulty: Res<Difficulty>,
+    rank: Res<Rank>,
+    patterns: PatternBook,
+    config: Res<GameConfig>,
+    mut sfx_events: EventWriter<PlaySfx>,
+) {
+    for BeatEvent { beat } in beat_events.read() {
+        for (entity, transform, gun, fire_on_beat) in query.iter() {
+            if beat % fire_on_beat.every == 0 {
+                fire_enemy_bullet(
+                    &mut commands,
+                    &mut meshes,
+                    &mut materials,
+                    &palette,
+                    &config,
+                    &mut sfx_events,
+                    entity,
+                    transform,
+                    gun,
+                    patterns.get(gun.pattern.as_deref()),
+                    difficulty.bullet_speed() * rank.bullet_speed(),
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,516 @@
+//! Input bindings, and the actions every input the game reads goes through.
+//! Keys and gamepad buttons are bound to actions, and `ActionState` says
+//! which actions are held every frame, so runs and menus alike ask for
+//! actions rather than keys or buttons. The gamepads' left sticks move the
+//! ship too, along with the movement actions, and move through menus. The
+//! on-screen controls of touch screens add to it as well, see
+//! `touch_controls`.
+//!
+//! The actions played with can be rebound. Their keys start out as one of
+//! the control schemes, and keys and buttons alike are kept in the settings
+//! file, where they can be changed one by one from the controls screen. No
+//! input is ever bound to two of them: rebinding one takes it away from the
+//! action it was bound to. The rest of the actions, for the menus and the
+//! shortcuts working anywhere, keep the inputs they are given here, and
+//! those working during runs can't be bound to anything else.
+//!
+//! The file holds actions and inputs by name, so bindings outlive changes to
+//! either: actions the game no longer has and inputs it no longer knows are
+//! dropped with a warning, and actions added since get their defaults.
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+use bevy::input::InputSystem;
+use bevy::log;
+use bevy::prelude::*;
+use bevy::ui::UiSystem;
+use bevy::utils::HashSet;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::{ControlScheme, Settings};
+
+/// How far a stick has to be pushed to move through menus
+const STICK_MENU_THRESHOLD: f32 = 0.5;
+
+/// Something an input can be bound to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Fire,
+    /// Slows the ship down for precise dodging
+    Focus,
+    Bomb,
+    /// Pauses runs, and resumes them
+    Pause,
+    /// Moves the focus between menu buttons
+    MenuUp,
+    MenuDown,
+    /// Presses the focused menu button
+    MenuConfirm,
+    /// Leaves the screen, or closes the dialog on top of it
+    MenuBack,
+    /// Turns all audio off and back on, anywhere in the game
+    ToggleMute,
+    ToggleFpsCounter,
+    TogglePerformanceOverlay,
+}
+
+impl Action {
+    /// The actions played with, bound from the settings
+    pub const REBINDABLE: [Action; 7] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Fire,
+        Action::Focus,
+        Action::Bomb,
+    ];
+
+    pub const ALL: [Action; 15] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Fire,
+        Action::Focus,
+        Action::Bomb,
+        Action::Pause,
+        Action::MenuUp,
+        Action::MenuDown,
+        Action::MenuConfirm,
+        Action::MenuBack,
+        Action::ToggleMute,
+        Action::ToggleFpsCounter,
+        Action::TogglePerformanceOverlay,
+    ];
+
+    pub fn rebindable(self) -> bool {
+        Self::REBINDABLE.contains(&self)
+    }
+
+    /// Whether the action works during runs, so its inputs can't be bound to
+    /// the ones played with
+    fn reserves_inputs(self) -> bool {
+        matches!(
+            self,
+            Action::Pause
+                | Action::ToggleMute
+                | Action::ToggleFpsCounter
+                | Action::TogglePerformanceOverlay
+        )
+    }
+
+    /// Name of the action in the settings file
+    fn name(self) -> &'static str {
+        match self {
+            Action::MoveUp => "move_up",
+            Action::MoveDown => "move_down",
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::Fire => "fire",
+            Action::Focus => "focus",
+            Action::Bomb => "bomb",
+            Action::Pause => "pause",
+            Action::MenuUp => "menu_up",
+            Action::MenuDown => "menu_down",
+            Action::MenuConfirm => "menu_confirm",
+            Action::MenuBack => "menu_back",
+            Action::ToggleMute => "toggle_mute",
+            Action::ToggleFpsCounter => "toggle_fps_counter",
+            Action::TogglePerformanceOverlay => "toggle_performance_overlay",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::REBINDABLE
+            .into_iter()
+            .find(|action| action.name() == name)
+    }
+
+    /// Message naming the action
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::MoveUp => "action-move-up",
+            Action::MoveDown => "action-move-down",
+            Action::MoveLeft => "action-move-left",
+            Action::MoveRight => "action-move-right",
+            Action::Fire => "action-fire",
+            Action::Focus => "action-focus",
+            Action::Bomb => "action-bomb",
+            Action::Pause => "action-pause",
+            Action::MenuUp => "action-menu-up",
+            Action::MenuDown => "action-menu-down",
+            Action::MenuConfirm => "action-menu-confirm",
+            Action::MenuBack => "action-menu-back",
+            Action::ToggleMute => "action-toggle-mute",
+            Action::ToggleFpsCounter => "action-toggle-fps-counter",
+            Action::TogglePerformanceOverlay => "action-toggle-performance-overlay",
+        }
+    }
+
+    /// Which way the action moves the ship, if it does
+    pub fn direction(self) -> Option<Vec2> {
+        match self {
+            Action::MoveUp => Some(Vec2::Y),
+            Action::MoveDown => Some(Vec2::NEG_Y),
+            Action::MoveLeft => Some(Vec2::NEG_X),
+            Action::MoveRight => Some(Vec2::X),
+            _ => None,
+        }
+    }
+}
+
+/// Keys or gamepad buttons
+pub trait Bindable:
+    Copy + Eq + Debug + Send + Sync + Serialize + DeserializeOwned + 'static
+{
+    /// What the inputs are called in warnings
+    const KIND: &'static str;
+
+    /// The inputs of an action that can't be rebound
+    fn fixed(action: Action) -> &'static [Self];
+}
+
+impl Bindable for KeyCode {
+    const KIND: &'static str = "key";
+
+    fn fixed(action: Action) -> &'static [Self] {
+        match action {
+            Action::Pause | Action::MenuBack => &[KeyCode::Escape],
+            Action::MenuUp => &[KeyCode::Up],
+            Action::MenuDown => &[KeyCode::Down],
+            Action::MenuConfirm => &[KeyCode::Return],
+            Action::ToggleMute => &[KeyCode::M],
+            Action::ToggleFpsCounter => &[KeyCode::F2],
+            Action::TogglePerformanceOverlay => &[KeyCode::F3],
+            _ => &[],
+        }
+    }
+}
+
+impl Bindable for GamepadButtonType {
+    const KIND: &'static str = "gamepad button";
+
+    fn fixed(action: Action) -> &'static [Self] {
+        match action {
+            Action::Pause => &[GamepadButtonType::Start],
+            Action::MenuUp => &[GamepadButtonType::DPadUp],
+            Action::MenuDown => &[GamepadButtonType::DPadDown],
+            Action::MenuConfirm => &[GamepadButtonType::South],
+            Action::MenuBack => &[GamepadButtonType::East],
+            _ => &[],
+        }
+    }
+}
+
+/// The action `input` works for during runs already, if any, see
+/// `Action::reserves_inputs`
+pub fn reserved_by<T: Bindable>(input: T) -> Option<Action> {
+    Action::ALL
+        .into_iter()
+        .find(|action| action.reserves_inputs() && T::fixed(*action).contains(&input))
+}
+
+/// An input taken away from one action to bind it to another
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rebound<T> {
+    pub from: Action,
+    /// The input it got in its place, the one the other action let go of
+    pub replacement: Option<T>,
+}
+
+/// The inputs bound to every action played with
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    from = "BTreeMap<String, Vec<String>>",
+    into = "BTreeMap<String, Vec<String>>",
+    bound = "Self: Default"
+)]
+pub struct Bindings<T: Bindable>(BTreeMap<Action, Vec<T>>);
+
+pub type KeyBindings = Bindings<KeyCode>;
+pub type GamepadBindings = Bindings<GamepadButtonType>;
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self::for_scheme(ControlScheme::default())
+    }
+}
+
+impl Default for GamepadBindings {
+    fn default() -> Self {
+        let buttons = |action| -> &[GamepadButtonType] {
+            match action {
+                Action::MoveUp => &[GamepadButtonType::DPadUp],
+                Action::MoveDown => &[GamepadButtonType::DPadDown],
+                Action::MoveLeft => &[GamepadButtonType::DPadLeft],
+                Action::MoveRight => &[GamepadButtonType::DPadRight],
+                Action::Fire => &[GamepadButtonType::South],
+                Action::Focus => &[GamepadButtonType::RightTrigger],
+                Action::Bomb => &[GamepadButtonType::East],
+                _ => &[],
+            }
+        };
+        Self::from_fn(buttons)
+    }
+}
+
+impl KeyBindings {
+    /// The keys a control scheme binds
+    pub fn for_scheme(scheme: ControlScheme) -> Self {
+        Self::from_fn(|action| match (action, scheme) {
+            (Action::MoveUp, ControlScheme::Standard) => &[KeyCode::Up, KeyCode::W],
+            (Action::MoveDown, ControlScheme::Standard) => &[KeyCode::Down, KeyCode::S],
+            (Action::MoveLeft, ControlScheme::Standard) => &[KeyCode::Left, KeyCode::A],
+            (Action::MoveRight, ControlScheme::Standard) => &[KeyCode::Right, KeyCode::D],
+            (Action::MoveUp, ControlScheme::Classic) => &[KeyCode::Up],
+            (Action::MoveDown, ControlScheme::Classic) => &[KeyCode::Down],
+            (Action::MoveLeft, ControlScheme::Classic) => &[KeyCode::Left],
+            (Action::MoveRight, ControlScheme::Classic) => &[KeyCode::Right],
+            (Action::Fire, ControlScheme::Standard) => &[KeyCode::Space],
+            (Action::Fire, ControlScheme::Classic) => &[KeyCode::Z],
+            (Action::Focus, _) => &[KeyCode::ShiftLeft, KeyCode::ShiftRight],
+            (Action::Bomb, ControlScheme::Standard) => &[KeyCode::B],
+            (Action::Bomb, ControlScheme::Classic) => &[KeyCode::C],
+            _ => &[],
+        })
+    }
+
+    /// The control scheme binding exactly these keys, if any does
+    pub fn scheme(&self) -> Option<ControlScheme> {
+        ControlScheme::ALL
+            .into_iter()
+            .find(|scheme| *self == Self::for_scheme(*scheme))
+    }
+}
+
+impl<T: Bindable> Bindings<T> {
+    fn from_fn(inputs: impl Fn(Action) -> &'static [T]) -> Self {
+        Self(
+            Action::REBINDABLE
+                .into_iter()
+                .map(|action| (action, inputs(action).to_vec()))
+                .collect(),
+        )
+    }
+
+    /// What the action is bound to, here or, for those that can't be
+    /// rebound, by the game
+    pub fn inputs(&self, action: Action) -> &[T] {
+        if !action.rebindable() {
+            return T::fixed(action);
+        }
+        self.0.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Binds `input` to `action` in place of its input at `slot`, or after
+    /// its last one. An input bound to another action is swapped with the
+    /// one it replaces, or taken away if it replaces none.
+    pub fn rebind(&mut self, action: Action, slot: usize, input: T) -> Option<Rebound<T>> {
+        let inputs = self.0.entry(action).or_default();
+        if let Some(index) = inputs.iter().position(|bound| *bound == input) {
+            if slot < inputs.len() {
+                inputs.swap(index, slot);
+            }
+            return None;
+        }
+        let replaced = inputs.get(slot).copied();
+        if slot < inputs.len() {
+            inputs[slot] = input;
+        } else {
+            inputs.push(input);
+        }
+        let (from, inputs) = self
+            .0
+            .iter_mut()
+            .find(|(other, inputs)| **other != action && inputs.contains(&input))?;
+        let replacement = replaced.filter(|replaced| !inputs.contains(replaced));
+        match replacement {
+            Some(replaced) => {
+                for bound in inputs.iter_mut().filter(|bound| **bound == input) {
+                    *bound = replaced;
+                }
+            }
+            None => inputs.retain(|bound| *bound != input),
+        }
+        Some(Rebound {
+            from: *from,
+            replacement,
+        })
+    }
+
+    /// Drops the input of `action` at `slot`, if it has one
+    pub fn unbind(&mut self, action: Action, slot: usize) {
+        if let Some(inputs) = self.0.get_mut(&action) {
+            if slot < inputs.len() {
+                inputs.remove(slot);
+            }
+        }
+    }
+}
+
+impl<T: Bindable> From<BTreeMap<String, Vec<String>>> for Bindings<T>
+where
+    Self: Default,
+{
+    fn from(saved: BTreeMap<String, Vec<String>>) -> Self {
+        let mut bindings = Self::default();
+        for (name, inputs) in saved {
+            let Some(action) = Action::from_name(&name) else {
+                log::warn!("Dropping the bindings of {name:?}, which is no longer an action");
+                continue;
+            };
+            let known = inputs
+                .iter()
+                .filter_map(|input| match ron::from_str(input) {
+                    Ok(input) => Some(input),
+                    Err(_) => {
+                        log::warn!(
+                            "Dropping the unknown {} {input:?} bound to {name:?}",
+                            T::KIND
+                        );
+                        None
+                    }
+                })
+                .collect::<Vec<_>>();
+            // Keeping the defaults rather than leaving the action with none
+            if known.is_empty() && !inputs.is_empty() {
+                continue;
+            }
+            bindings.0.insert(action, known);
+        }
+        bindings
+    }
+}
+
+impl<T: Bindable> From<Bindings<T>> for BTreeMap<String, Vec<String>> {
+    fn from(bindings: Bindings<T>) -> Self {
+        bindings
+            .0
+            .into_iter()
+            .map(|(action, inputs)| {
+                let inputs = inputs.iter().map(|input| format!("{input:?}")).collect();
+                (action.name().to_string(), inputs)
+            })
+            .collect()
+    }
+}
+
+/// The actions held this frame, on the keyboard or any gamepad
+#[derive(Resource, Default, Debug)]
+pub struct ActionState {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+    /// Where the left sticks are pushed, together
+    stick: Vec2,
+}
+
+impl ActionState {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    /// Holds `action` down for the frame, for inputs read elsewhere
+    pub(crate) fn press(&mut self, action: Action, just_pressed: bool) {
+        self.pressed.insert(action);
+        if just_pressed {
+            self.just_pressed.insert(action);
+        }
+    }
+
+    /// Moves the ship for the frame, like another stick
+    pub(crate) fn push_stick(&mut self, by: Vec2) {
+        self.stick += by;
+    }
+
+    /// Which way the movement actions and the sticks point, together
+    pub fn movement(&self) -> Vec2 {
+        Action::REBINDABLE
+            .into_iter()
+            .filter(|action| self.pressed(*action))
+            .filter_map(Action::direction)
+            .fold(self.stick, |movement, direction| movement + direction)
+    }
+}
+
+pub struct BindingsPlugin;
+
+impl Plugin for BindingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActionState>().add_systems(
+            PreUpdate,
+            // Ahead of menu navigation, which goes after the UI's focus
+            update_actions.after(InputSystem).before(UiSystem::Focus),
+        );
+    }
+}
+
+pub(crate) fn update_actions(
+    keyboard: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    gamepads: Res<Gamepads>,
+    settings: Res<Settings>,
+    mut actions: ResMut<ActionState>,
+) {
+    let ActionState {
+        pressed,
+        just_pressed,
+        stick,
+    } = &mut *actions;
+    pressed.clear();
+    just_pressed.clear();
+    for action in Action::ALL {
+        let keys = settings.bindings.inputs(action).iter().copied();
+        let buttons = settings.gamepad_bindings.inputs(action);
+        let gamepad_buttons_of = || {
+            gamepads.iter().flat_map(|gamepad| {
+                buttons
+                    .iter()
+                    .map(move |button| GamepadButton::new(gamepad, *button))
+            })
+        };
+        if keyboard.any_pressed(keys.clone()) || gamepad_buttons.any_pressed(gamepad_buttons_of()) {
+            pressed.insert(action);
+        }
+        if keyboard.any_just_pressed(keys) || gamepad_buttons.any_just_pressed(gamepad_buttons_of())
+        {
+            just_pressed.insert(action);
+        }
+    }
+    let previous_stick = *stick;
+    let axis = |gamepad, axis_type| {
+        gamepad_axes
+            .get(GamepadAxis::new(gamepad, axis_type))
+            .unwrap_or_default()
+    };
+    *stick = gamepads
+        .iter()
+        .map(|gamepad| {
+            Vec2::new(
+                axis(gamepad, GamepadAxisType::LeftStickX),
+                axis(gamepad, GamepadAxisType::LeftStickY),
+            )
+        })
+        .sum();
+    // Pushing a stick up or down moves through menus too, once per push
+    for (action, sign) in [(Action::MenuUp, 1.), (Action::MenuDown, -1.)] {
+        let pushed = |stick: Vec2| stick.y * sign > STICK_MENU_THRESHOLD;
+        if pushed(*stick) {
+            pressed.insert(action);
+            if !pushed(previous_stick) {
+                just_pressed.insert(action);
+            }
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! Bombs. Pressing the bomb key spends one and sends a shockwave out from the
+//! player: a ring that keeps growing until it has swept the whole playfield,
+//! wiping out enemy bullets exactly as it passes over them. The ring drawn is
+//! the one doing the clearing, so what the player sees is what happens.
+
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::sprite::MaterialMesh2dBundle;
+
+use crate::bullet::{Bullet, Hostility};
+use crate::collision::not_in_hitstop;
+use crate::player::{Bombs, Player, PlayerControllerSet, TickInput};
+use crate::score::RunStats;
+use crate::screen_shake::ScreenShake;
+use crate::{GameSet, Playfield};
+
+/// How fast the ring grows, in units per second
+const SHOCKWAVE_SPEED: f32 = 1200.;
+/// Ring thickness, as a fraction of its radius
+const RING_THICKNESS: f32 = 0.06;
+const RING_SEGMENTS: usize = 64;
+const SHOCKWAVE_COLOR: Color = Color::rgba(0.6, 0.9, 1., 0.8);
+/// Above the ships and bullets
+const SHOCKWAVE_Z: f32 = 4.;
+const BOMB_TRAUMA: f32 = 0.6;
+
+/// The player set off a bomb
+#[derive(Event, Clone, Copy, Debug)]
+pub struct BombUsedEvent {
+    /// Where the shockwave starts from
+    pub position: Vec2,
+}
+
+#[derive(Component)]
+struct Shockwave {
+    center: Vec2,
+    radius: f32,
+    /// Radius at which the whole playfield has been swept
+    max_radius: f32,
+}
+
+pub struct BombPlugin;
+
+impl Plugin for BombPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BombUsedEvent>()
+            .add_systems(
+                FixedUpdate,
+                use_bomb.after(PlayerControllerSet).in_set(GameSet::Input),
+            )
+            .add_systems(
+                FixedUpdate,
+                expand_shockwaves
+                    .run_if(not_in_hitstop)
+                    .in_set(GameSet::Movement),
+            );
+    }
+}
+
+/// A flat ring of radius 1, to be scaled up to the shockwave's radius
+fn ring_mesh() -> Mesh {
+    let inner = 1. - RING_THICKNESS;
+    let mut positions = Vec::with_capacity(RING_SEGMENTS * 2);
+    let mut indices = Vec::with_capacity(RING_SEGMENTS * 6);
+    for i in 0..RING_SEGMENTS {
+        let direction = Vec2::from_angle(i as f32 / RING_SEGMENTS as f32 * std::f32::consts::TAU);
+        positions.push(direction.extend(0.).to_array());
+        positions.push((direction * inner).extend(0.).to_array());
+        let (outer_a, inner_a) = (i as u32 * 2, i as u32 * 2 + 1);
+        let next = (i + 1) % RING_SEGMENTS;
+        let (outer_b, inner_b) = (next as u32 * 2, next as u32 * 2 + 1);
+        indices.extend([outer_a, inner_a, outer_b, outer_b, inner_a, inner_b]);
+    }
+    let vertices = positions.len();
+    Mesh::new(PrimitiveTopology::TriangleList)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0., 0., 1.]; vertices])
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0., 0.]; vertices])
+        .with_indices(Some(Indices::U32(indices)))
+}
+
+/// Goes off on the tick the bomb key goes down, as long as there are bombs left
+fn use_bomb(
+    mut commands: Commands,
+    tick_input: Res<TickInput>,
+    mut was_pressed: Local<bool>,
+    mut bombs: ResMut<Bombs>,
+    players: Query<&Transform, With<Player>>,
+    playfield: Res<Playfield>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut shake: ResMut<ScreenShake>,
+    mut run_stats: ResMut<RunStats>,
+    mut bomb_events: EventWriter<BombUsedEvent>,
+) {
+    let pressed = tick_input.bomb && !*was_pressed;
+    *was_pressed = tick_input.bomb;
+    if !pressed || bombs.0 == 0 {
+        return;
+    }
+    let Ok(transform) = players.get_single() else {
+        return;
+    };
+    bombs.0 -= 1;
+    run_stats.bombs_used += 1;
+    let center = transform.translation.truncate();
+    // Far enough to reach the farthest corner
+    let max_radius = [
+        playfield.rect.min,
+        playfield.rect.max,
+        Vec2::new(playfield.rect.min.x, playfield.rect.max.y),
+        Vec2::new(playfield.rect.max.x, playfield.rect.min.y),
+    ]
+    .into_iter()
+    .map(|corner| corner.distance(center))
+    .fold(0., f32::max);
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes.add(ring_mesh()).into(),
+            material: materials.add(ColorMaterial::from(SHOCKWAVE_COLOR)),
+            transform: Transform::from_translation(center.extend(SHOCKWAVE_Z))
+                .with_scale(Vec3::ZERO),
+            ..default()
+        },
+        Shockwave {
+            center,
+            radius: 0.,
+            max_radius,
+        },
+    ));
+    shake.add_trauma(BOMB_TRAUMA);
+    bomb_events.send(BombUsedEvent { position: center });
+}
+
+fn expand_shockwaves(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut shockwaves: Query<(
+        Entity,
+        &mut Shockwave,
+        &mut Transform,
+        &Handle<ColorMaterial>,
+    )>,
+    bullets: Query<(Entity, &Transform, &Hostility), (With<Bullet>, Without<Shockwave>)>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (entity, mut shockwave, mut transform, material) in shockwaves.iter_mut() {
+        shockwave.radius += SHOCKWAVE_SPEED * time.delta_seconds();
+        for (bullet, bullet_transform, hostility) in bullets.iter() {
+            if let Hostility::Hostile = hostility {
+                let distance = bullet_transform
+                    .translation
+                    .truncate()
+                    .distance(shockwave.center);
+                if distance <= shockwave.radius {
+                    commands.entity(bullet).despawn();
+                }
+            }
+        }
+        if shockwave.radius >= shockwave.max_radius {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        transform.scale = Vec3::new(shockwave.radius, shockwave.radius, 1.);
+        // Fades out as it reaches the far edge
+        if let Some(material) = materials.get_mut(material) {
+            material
+                .color
+                .set_a(SHOCKWAVE_COLOR.a() * (1. - shockwave.radius / shockwave.max_radius));
+        }
+    }
+}
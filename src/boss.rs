@@ -0,0 +1,301 @@
+//! Boss encounters. When the stage timeline calls for it, the boss approaches:
+//! a flashing "WARNING" banner and a siren announce it and the small enemies
+//! retreat. Once the boss is destroyed it goes down in a string of
+//! explosions, and the stage is cleared when they are over.
+
+use bevy::log;
+use bevy::prelude::*;
+use bevy::sprite::MaterialMesh2dBundle;
+
+use crate::audio::{PlaySfx, SfxId};
+use crate::beat::FireOnBeat;
+use crate::bullet::{DespawnOutOfBounds, Gun, Hostility};
+use crate::collision::{Collider, ColliderShape, HitFlash};
+use crate::difficulty::Difficulty;
+use crate::enemy::{Direction, Enemy, EnemyKind, HoverBehaviour, KnockbackVelocity, Mass};
+use crate::locale::Locale;
+use crate::muzzle_flash::BOSS_GUN_FLASH;
+use crate::palette::OwnColor;
+use crate::settings::Settings;
+use crate::sprites::{SpriteArt, SpriteId};
+use crate::stage::{BossSpec, StageProgress, Stages};
+use crate::state::{gameplay_active, AppState};
+use crate::theme::UiTheme;
+use crate::{HitPoints, Playfield};
+
+const BOSS_WARNING_SECONDS: f32 = 4.;
+const BOSS_MASS: f32 = 10.;
+const BOSS_HIT_COLOR: Color = Color::WHITE;
+/// From the boss going down to the stage being cleared
+const BOSS_DEATH_SECONDS: f32 = 2.;
+const BOSS_BEATS_PER_SHOT: u64 = 2;
+const WARNING_COLOR: Color = Color::RED;
+const WARNING_FLASHES_PER_SECOND: f32 = 2.;
+/// Kinds of the boss and mid-bosses in the statistics, see `EnemyKind`
+pub(crate) const BOSS_KIND: &str = "boss";
+pub(crate) const MID_BOSS_KIND: &str = "mid_boss";
+
+#[derive(Component)]
+pub(crate) struct Boss;
+
+/// Small enemies flying off screen to make room for the boss
+#[derive(Component)]
+pub(crate) struct Retreating;
+
+#[derive(Resource, Default, Debug)]
+pub(crate) enum BossEncounter {
+    /// Waiting for the stage to start the boss
+    #[default]
+    Approaching,
+    Warning(Timer),
+    Fighting,
+    /// The boss is down, and the stage clears once its death plays out
+    Dying(Timer),
+}
+
+/// Sent by the stage timeline to bring the boss in
+#[derive(Event, Default)]
+pub(crate) struct StartBossEvent;
+
+#[derive(Event, Default)]
+pub(crate) struct BossWarningEvent;
+
+/// The boss took its last hit
+#[derive(Event)]
+pub(crate) struct BossDefeatedEvent {
+    /// What the boss covered when it went down
+    pub area: Rect,
+}
+
+/// A boss script moved its boss on to another phase
+#[derive(Event, Clone, Copy, Debug)]
+pub struct BossPhaseEvent {
+    pub boss: Entity,
+    /// What the script set `boss.phase` to
+    pub phase: i64,
+}
+
+/// The boss went down, which clears the current stage
+#[derive(Event, Default)]
+pub(crate) struct StageClearedEvent;
+
+#[derive(Component)]
+struct WarningBanner {
+    elapsed: f32,
+}
+
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BossEncounter>()
+            .add_event::<BossWarningEvent>()
+            .add_event::<BossDefeatedEvent>()
+            .add_event::<StageClearedEvent>()
+            .add_event::<StartBossEvent>()
+            .add_event::<BossPhaseEvent>()
+            .add_systems(OnExit(AppState::Running), reset_boss_encounter)
+            .add_systems(
+                Update,
+                (show_boss_warning, flash_warning_banner)
+                    .chain()
+                    .run_if(gameplay_active),
+            );
+    }
+}
+
+/// Runs ending mid-encounter must not leave the next one waiting on a boss,
+/// least of all in modes without stages
+fn reset_boss_encounter(mut encounter: ResMut<BossEncounter>) {
+    *encounter = BossEncounter::default();
+}
+
+/// Runs on the fixed timestep alongside the enemy spawner
+pub(crate) fn advance_boss_encounter(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut encounter: ResMut<BossEncounter>,
+    mut start_events: EventReader<StartBossEvent>,
+    mut warning_events: EventWriter<BossWarningEvent>,
+    mut cleared_events: EventWriter<StageClearedEvent>,
+    mut enemy_query: Query<(Entity, &mut Direction), (With<Enemy>, Without<Boss>)>,
+    boss_query: Query<(), With<Boss>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    playfield: Res<Playfield>,
+    stages: Res<Stages>,
+    progress: Res<StageProgress>,
+    difficulty: Res<Difficulty>,
+) {
+    match &mut *encounter {
+        BossEncounter::Approaching => {
+            if start_events.read().count() > 0 {
+                log::info!("Boss approaching!");
+                warning_events.send_default();
+                for (entity, mut direction) in enemy_query.iter_mut() {
+                    commands.entity(entity).insert(Retreating);
+                    direction.0 = Vec3::Y;
+                }
+                *encounter = BossEncounter::Warning(Timer::from_seconds(
+                    BOSS_WARNING_SECONDS,
+                    TimerMode::Once,
+                ));
+            }
+        }
+        BossEncounter::Warning(timer) => {
+            if timer.tick(time.delta()).just_finished() {
+                let boss = &progress.stage(&stages).boss;
+                let entity = spawn_boss(
+                    &mut commands,
+                    &mut meshes,
+                    &mut materials,
+                    &playfield,
+                    *difficulty,
+                    boss,
+                );
+                // Fires in time with the boss theme
+                commands.entity(entity).insert((
+                    Boss,
+                    EnemyKind(BOSS_KIND.to_string()),
+                    FireOnBeat {
+                        every: BOSS_BEATS_PER_SHOT,
+                    },
+                ));
+                *encounter = BossEncounter::Fighting;
+            }
+        }
+        BossEncounter::Fighting => {
+            if boss_query.is_empty() {
+                log::info!("Boss defeated");
+                *encounter =
+                    BossEncounter::Dying(Timer::from_seconds(BOSS_DEATH_SECONDS, TimerMode::Once));
+            }
+        }
+        BossEncounter::Dying(timer) => {
+            if timer.tick(time.delta()).just_finished() {
+                cleared_events.send_default();
+                *encounter = BossEncounter::default();
+            }
+        }
+    }
+}
+
+/// Spawns a big enemy. Only the one tagged `Boss` ends the stage, others
+/// are mid-bosses.
+pub(crate) fn spawn_boss(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    playfield: &Playfield,
+    difficulty: Difficulty,
+    spec: &BossSpec,
+) -> Entity {
+    let top = playfield.rect.max.y;
+    let boss = commands
+        .spawn((
+            MaterialMesh2dBundle {
+                mesh: meshes.add(shape::Quad::new(spec.size).into()).into(),
+                material: materials.add(ColorMaterial::from(spec.color)),
+                transform: Transform::from_translation(Vec3::new(
+                    playfield.rect.center().x,
+                    top,
+                    0.,
+                )),
+                ..default()
+            },
+            Enemy,
+            OwnColor,
+            SpriteArt(SpriteId::Boss),
+            HitFlash::new(spec.color, BOSS_HIT_COLOR),
+            Collider,
+            ColliderShape::Aabb {
+                half_extents: spec.size / 2.,
+            },
+            Gun {
+                cooldown_timer: Timer::from_seconds(0.5, TimerMode::Once),
+                interval: 1.,
+                pattern: None,
+                damage: 10,
+                knockback: None,
+                flash: BOSS_GUN_FLASH,
+            },
+            Mass(BOSS_MASS),
+            KnockbackVelocity::default(),
+            DespawnOutOfBounds {
+                margin: spec.size.y * 2.,
+            },
+            HitPoints(difficulty.scale_hp(spec.hp)),
+            Hostility::Hostile,
+            Direction(Vec3::ZERO),
+            HoverBehaviour {
+                upper_limit_base: top - 100.,
+                upper_limit_margin: 20.,
+                lower_limit_base: top - 150.,
+                lower_limit_margin: 20.,
+            },
+        ))
+        .id();
+    #[cfg(feature = "scripting")]
+    if let Some(script) = &spec.script {
+        commands
+            .entity(boss)
+            .insert(crate::scripting::BossBrain::new(script));
+    }
+    boss
+}
+
+fn show_boss_warning(
+    mut commands: Commands,
+    mut events: EventReader<BossWarningEvent>,
+    theme: Res<UiTheme>,
+    locale: Res<Locale>,
+    mut sfx_events: EventWriter<PlaySfx>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+    sfx_events.send(PlaySfx::new(SfxId::Siren));
+
+    let mut style = theme.title.clone();
+    style.color = WARNING_COLOR;
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    top: Val::Percent(35.),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            WarningBanner { elapsed: 0. },
+        ))
+        .with_children(|parent| {
+            parent.spawn(locale.text("boss-warning", style));
+        });
+}
+
+/// Stays up without flashing with reduced flashing
+fn flash_warning_banner(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut banners: Query<(Entity, &mut WarningBanner, &mut Visibility)>,
+) {
+    for (entity, mut banner, mut visibility) in banners.iter_mut() {
+        banner.elapsed += time.delta_seconds();
+        if banner.elapsed >= BOSS_WARNING_SECONDS {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let shown = settings.reduced_flashing
+            || (banner.elapsed * WARNING_FLASHES_PER_SECOND).fract() < 0.5;
+        *visibility = if shown {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
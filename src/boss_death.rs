@@ -0,0 +1,202 @@
+//! The boss going down. Instead of just vanishing, it blows up in a chain of
+//! explosions spread across where it was. The screen flashes, or only lights
+//! up softly with reduced flashing, the action slows down for a beat, and
+//! every enemy bullet is cleared so the player can take it in. The stage
+//! clears once it is all over, see `BossEncounter`.
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::audio::{PlaySfx, SfxId};
+use crate::boss::BossDefeatedEvent;
+use crate::bullet::{Bullet, Hostility};
+use crate::game_speed::GameSpeed;
+use crate::particles::ExplosionEvent;
+use crate::settings::Settings;
+use crate::state::{gameplay_active, AppState};
+use crate::{GameSet, Playfield};
+
+const EXPLOSIONS: usize = 8;
+const SECONDS_BETWEEN_EXPLOSIONS: f32 = 0.15;
+const FLASH_COLOR: Color = Color::rgba(1., 1., 1., 0.8);
+const FLASH_SECONDS: f32 = 0.4;
+/// Dimmer and fading out slower, with reduced flashing
+const SOFT_FLASH_COLOR: Color = Color::rgba(1., 1., 1., 0.2);
+const SOFT_FLASH_SECONDS: f32 = 1.2;
+/// In front of everything on the playfield
+const FLASH_Z: f32 = 50.;
+/// How fast the game runs during the slow motion, at full game speed
+const SLOW_MOTION_SPEED: f32 = 0.3;
+/// How long the slow motion lasts, in real time
+const SLOW_MOTION_SECONDS: f32 = 1.;
+
+/// The explosions still to go off
+#[derive(Resource, Default)]
+struct BossDeath {
+    area: Rect,
+    explosions_left: usize,
+    next_explosion: Timer,
+}
+
+/// Time until the game is back to full speed
+#[derive(Resource, Default)]
+struct SlowMotion(Option<Timer>);
+
+#[derive(Component)]
+struct ScreenFlash {
+    /// Opacity it starts at
+    alpha: f32,
+    seconds: f32,
+    elapsed: f32,
+}
+
+pub struct BossDeathPlugin;
+
+impl Plugin for BossDeathPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BossDeath>()
+            .init_resource::<SlowMotion>()
+            .add_systems(OnExit(AppState::Running), reset_boss_death)
+            .add_systems(
+                FixedUpdate,
+                clear_hostile_bullets.in_set(GameSet::Resolution),
+            )
+            .add_systems(
+                Update,
+                (
+                    start_boss_death,
+                    chain_explosions,
+                    fade_screen_flash,
+                    end_slow_motion,
+                )
+                    .chain()
+                    .run_if(gameplay_active),
+            );
+    }
+}
+
+/// On the tick the boss goes down, so replays clear the same bullets
+fn clear_hostile_bullets(
+    mut commands: Commands,
+    mut events: EventReader<BossDefeatedEvent>,
+    bullets: Query<(Entity, &Hostility), With<Bullet>>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+    for (entity, hostility) in bullets.iter() {
+        if let Hostility::Hostile = hostility {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn start_boss_death(
+    mut commands: Commands,
+    mut events: EventReader<BossDefeatedEvent>,
+    mut boss_death: ResMut<BossDeath>,
+    mut slow_motion: ResMut<SlowMotion>,
+    mut time: ResMut<Time<Virtual>>,
+    speed: Res<GameSpeed>,
+    playfield: Res<Playfield>,
+    settings: Res<Settings>,
+) {
+    let Some(event) = events.read().last() else {
+        return;
+    };
+    *boss_death = BossDeath {
+        area: event.area,
+        explosions_left: EXPLOSIONS,
+        next_explosion: Timer::from_seconds(SECONDS_BETWEEN_EXPLOSIONS, TimerMode::Repeating),
+    };
+    let (color, seconds) = if settings.reduced_flashing {
+        (SOFT_FLASH_COLOR, SOFT_FLASH_SECONDS)
+    } else {
+        (FLASH_COLOR, FLASH_SECONDS)
+    };
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color,
+                custom_size: Some(playfield.rect.size()),
+                ..default()
+            },
+            transform: Transform::from_translation(playfield.rect.center().extend(FLASH_Z)),
+            ..default()
+        },
+        ScreenFlash {
+            alpha: color.a(),
+            seconds,
+            elapsed: 0.,
+        },
+    ));
+    time.set_relative_speed(SLOW_MOTION_SPEED * speed.0);
+    slow_motion.0 = Some(Timer::from_seconds(SLOW_MOTION_SECONDS, TimerMode::Once));
+}
+
+fn chain_explosions(
+    time: Res<Time>,
+    mut boss_death: ResMut<BossDeath>,
+    mut explosion_events: EventWriter<ExplosionEvent>,
+    mut sfx_events: EventWriter<PlaySfx>,
+) {
+    if boss_death.explosions_left == 0 {
+        return;
+    }
+    if !boss_death.next_explosion.tick(time.delta()).just_finished() {
+        return;
+    }
+    let mut rng = rand::thread_rng();
+    let area = boss_death.area;
+    let position = Vec2::new(
+        rng.gen_range(area.min.x..=area.max.x),
+        rng.gen_range(area.min.y..=area.max.y),
+    );
+    explosion_events.send(ExplosionEvent { position });
+    sfx_events.send(PlaySfx::at(SfxId::Explosion, position));
+    boss_death.explosions_left -= 1;
+}
+
+fn fade_screen_flash(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut ScreenFlash, &mut Sprite)>,
+) {
+    for (entity, mut flash, mut sprite) in query.iter_mut() {
+        flash.elapsed += time.delta_seconds();
+        if flash.elapsed >= flash.seconds {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        sprite
+            .color
+            .set_a(flash.alpha * (1. - flash.elapsed / flash.seconds));
+    }
+}
+
+fn end_slow_motion(
+    real_time: Res<Time<Real>>,
+    speed: Res<GameSpeed>,
+    mut slow_motion: ResMut<SlowMotion>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    let Some(timer) = &mut slow_motion.0 else {
+        return;
+    };
+    if timer.tick(real_time.delta()).finished() {
+        time.set_relative_speed(speed.0);
+        slow_motion.0 = None;
+    }
+}
+
+/// Leaving mid-sequence must not leave the game in slow motion
+fn reset_boss_death(
+    mut boss_death: ResMut<BossDeath>,
+    mut slow_motion: ResMut<SlowMotion>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    *boss_death = BossDeath::default();
+    if slow_motion.0.take().is_some() {
+        time.set_relative_speed(1.);
+    }
+}
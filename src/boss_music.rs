@@ -0,0 +1,93 @@
+//! Music around boss fights. The stage theme ducks under the siren when the
+//! warning goes off, and the boss theme takes over once the boss arrives.
+//! Bringing the boss down plays a victory sting, after which the stage theme
+//! comes back.
+
+use bevy::prelude::*;
+use bevy_kira_audio::{AudioChannel, AudioControl};
+
+use crate::audio::{MusicChannel, MusicController};
+use crate::boss::{BossEncounter, BossWarningEvent, StageClearedEvent};
+use crate::stage::{StageProgress, Stages};
+use crate::state::AppState;
+
+pub(crate) const BOSS_MUSIC: &str = "audio/boss.wav";
+pub(crate) const VICTORY_STING: &str = "audio/victory.wav";
+/// How long the sting plays before the stage theme comes back
+const VICTORY_STING_SECONDS: f32 = 2.2;
+
+#[derive(Resource, Default, Debug)]
+enum BossMusic {
+    /// The stage theme, as usual
+    #[default]
+    Stage,
+    /// The stage theme, ducked under the siren
+    Warning,
+    Fight,
+    /// The sting, with the stage theme still to come back
+    Victory(Timer),
+}
+
+pub struct BossMusicPlugin;
+
+impl Plugin for BossMusicPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BossMusic>()
+            .add_systems(OnEnter(AppState::Running), reset_boss_music)
+            .add_systems(
+                Update,
+                advance_boss_music.run_if(in_state(AppState::Running)),
+            );
+    }
+}
+
+/// A run restarted during the warning may pick up the same, still ducked,
+/// stage theme
+fn reset_boss_music(mut boss_music: ResMut<BossMusic>, mut music: ResMut<MusicController>) {
+    *boss_music = BossMusic::default();
+    music.duck(false);
+}
+
+fn advance_boss_music(
+    time: Res<Time>,
+    mut boss_music: ResMut<BossMusic>,
+    mut warning_events: EventReader<BossWarningEvent>,
+    mut cleared_events: EventReader<StageClearedEvent>,
+    encounter: Res<BossEncounter>,
+    stages: Res<Stages>,
+    progress: Res<StageProgress>,
+    asset_server: Res<AssetServer>,
+    mut music: ResMut<MusicController>,
+    channel: Res<AudioChannel<MusicChannel>>,
+) {
+    let warned = warning_events.read().count() > 0;
+    let cleared = cleared_events.read().count() > 0;
+    match &mut *boss_music {
+        BossMusic::Stage => {
+            if warned {
+                music.duck(true);
+                *boss_music = BossMusic::Warning;
+            }
+        }
+        BossMusic::Warning => {
+            if matches!(*encounter, BossEncounter::Fighting) {
+                music.play(Some(BOSS_MUSIC));
+                *boss_music = BossMusic::Fight;
+            }
+        }
+        BossMusic::Fight => {
+            if cleared {
+                music.play(None);
+                channel.play(asset_server.load(VICTORY_STING));
+                *boss_music =
+                    BossMusic::Victory(Timer::from_seconds(VICTORY_STING_SECONDS, TimerMode::Once));
+            }
+        }
+        BossMusic::Victory(timer) => {
+            if timer.tick(time.delta()).just_finished() {
+                music.play(progress.stage(&stages).music.as_deref());
+                *boss_music = BossMusic::Stage;
+            }
+        }
+    }
+}
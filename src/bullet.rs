@@ -0,0 +1,195 @@
+//! Bullets, and the guns that fire them. Every bullet flies in a straight
+//! line until it hits something or leaves the playfield.
+
+use bevy::log;
+use bevy::prelude::*;
+use bevy::sprite::MaterialMesh2dBundle;
+
+use crate::collision::{not_in_hitstop, Collider, ColliderShape};
+use crate::enemy::enemy_shots;
+use crate::glow::{BlendMode, GlowMaterial, GlowShape};
+use crate::muzzle_flash::MuzzleFlash;
+use crate::palette::Palette;
+use crate::player::shoot;
+use crate::sprites::{SpriteArt, SpriteId};
+use crate::{GameSet, Playfield};
+
+const PLAYER_BULLET_STYLE: BulletStyle = BulletStyle {
+    emissive: 2.,
+    blend: BlendMode::Alpha,
+};
+
+/// Additive, so dense patterns brighten where they overlap
+const ENEMY_BULLET_STYLE: BulletStyle = BulletStyle {
+    emissive: 4.,
+    blend: BlendMode::Additive,
+};
+
+#[derive(Component)]
+pub(crate) struct Gun {
+    pub(crate) cooldown_timer: Timer,
+    /// Seconds between enemy shots, before difficulty and rank. Every shot
+    /// waits up to twice as long, at random.
+    pub(crate) interval: f32,
+    /// Name of the bullet pattern an enemy gun fires, a single bullet when
+    /// `None`
+    pub(crate) pattern: Option<String>,
+    pub(crate) damage: u32,
+    pub(crate) knockback: Option<f32>,
+    pub(crate) flash: MuzzleFlash,
+}
+
+#[derive(Component)]
+pub struct Bullet;
+
+/// How a kind of bullet is drawn, on top of its palette color
+struct BulletStyle {
+    /// Multiplier on the color's brightness. Anything past full brightness is
+    /// clipped without bloom, and glows with it.
+    emissive: f32,
+    blend: BlendMode,
+}
+
+impl BulletStyle {
+    fn of(is_hostile: bool) -> &'static BulletStyle {
+        if is_hostile {
+            &ENEMY_BULLET_STYLE
+        } else {
+            &PLAYER_BULLET_STYLE
+        }
+    }
+}
+
+#[derive(Component, Debug)]
+pub enum Hostility {
+    Hostile,
+    Friendly,
+}
+
+#[derive(Component)]
+pub(crate) struct Velocity(pub(crate) Vec2);
+
+#[derive(Component)]
+pub(crate) struct Damage(pub(crate) u32);
+
+/// Impulse a bullet applies to whatever it hits, along its travel direction.
+#[derive(Component)]
+pub(crate) struct Knockback(pub(crate) f32);
+
+/// Despawns the entity once it wanders further than `margin` outside the playfield.
+#[derive(Component)]
+pub(crate) struct DespawnOutOfBounds {
+    pub(crate) margin: f32,
+}
+
+pub struct BulletPlugin;
+
+impl Plugin for BulletPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                move_bullets.after(shoot).run_if(not_in_hitstop),
+                // Enemies spawn just past the top edge, so only once they moved
+                despawn_out_of_bounds.after(enemy_shots),
+            )
+                .in_set(GameSet::Movement),
+        );
+    }
+}
+
+/// Bullets are drawn brighter than their palette color, to glow under bloom.
+/// Palettes telling them apart by shape too ring hostile bullets in white
+/// and cut friendly ones down to diamonds.
+pub(crate) fn bullet_material(palette: &Palette, is_hostile: bool) -> GlowMaterial {
+    let style = BulletStyle::of(is_hostile);
+    let color = if is_hostile {
+        palette.hostile_bullet
+    } else {
+        palette.friendly_bullet
+    };
+    let mut material = GlowMaterial::new(color * style.emissive, style.blend);
+    if palette.distinct_bullets {
+        material.outline = Color::WHITE * style.emissive;
+        material.shape = if is_hostile {
+            GlowShape::Ringed
+        } else {
+            GlowShape::Diamond
+        };
+    }
+    material
+}
+
+pub(crate) fn create_bullet(
+    position: Vec3,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<GlowMaterial>>,
+    palette: &Palette,
+    radius: f32,
+    velocity: Vec2,
+    damage: u32,
+    is_hostile: bool,
+) -> (
+    MaterialMesh2dBundle<GlowMaterial>,
+    Bullet,
+    Velocity,
+    Damage,
+    Hostility,
+    Collider,
+    ColliderShape,
+    DespawnOutOfBounds,
+    SpriteArt,
+) {
+    (
+        MaterialMesh2dBundle {
+            mesh: meshes.add(shape::Circle::new(radius).into()).into(),
+            material: materials.add(bullet_material(palette, is_hostile)),
+            transform: Transform::from_translation(position),
+            ..default()
+        },
+        Bullet,
+        Velocity(velocity),
+        Damage(damage),
+        if is_hostile {
+            Hostility::Hostile
+        } else {
+            Hostility::Friendly
+        },
+        Collider,
+        ColliderShape::Circle { radius },
+        DespawnOutOfBounds { margin: 0. },
+        SpriteArt(if is_hostile {
+            SpriteId::EnemyBullet
+        } else {
+            SpriteId::PlayerBullet
+        }),
+    )
+}
+
+pub(crate) fn move_bullets(
+    time: Res<Time>,
+    mut query: Query<(&Velocity, &mut Transform), With<Bullet>>,
+) {
+    for (velocity, mut transform) in query.iter_mut() {
+        transform.translation += (velocity.0 * time.delta_seconds()).extend(0.);
+    }
+}
+
+fn despawn_out_of_bounds(
+    mut commands: Commands,
+    playfield: Res<Playfield>,
+    query: Query<(Entity, &Transform, &DespawnOutOfBounds)>,
+) {
+    for (entity, transform, bounds) in query.iter() {
+        let limit = playfield.rect.inset(bounds.margin);
+        if !limit.contains(transform.translation.truncate()) {
+            log::info!(
+                "Entity {:?} out of bounds at {:?}. Despawning.",
+                entity,
+                transform.translation
+            );
+            // Along with any muzzle flash still on it
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
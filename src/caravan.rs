@@ -0,0 +1,122 @@
+//! Caravan. Two minutes of dense waves on a fixed timeline, with a fixed RNG
+//! seed, so every run sees the same enemies at the same moments and scores
+//! come down to how well they were played.
+
+use bevy::log;
+use bevy::prelude::*;
+
+use crate::background::BackgroundScroll;
+use crate::collision::not_in_hitstop;
+use crate::enemies::GRUNT;
+use crate::enemy::{spawn_requested_enemies, SpawnEnemy};
+use crate::mode::caravan_mode;
+use crate::stage::spawn_wave;
+use crate::state::AppState;
+use crate::timeline::{report_changes, StageTimeline, TimelineAction, TimelineReloadedEvent};
+use crate::{GameSet, Playfield};
+
+const CARAVAN_TIMELINE_PATH: &str = "stages/caravan.timeline.ron";
+
+/// The waves of a caravan run, which only uses `SpawnWave` and
+/// `SetScrollSpeed`
+#[derive(Resource)]
+pub struct CaravanTimeline(pub Handle<StageTimeline>);
+
+impl FromWorld for CaravanTimeline {
+    fn from_world(world: &mut World) -> Self {
+        Self(world.resource::<AssetServer>().load(CARAVAN_TIMELINE_PATH))
+    }
+}
+
+/// Seconds into the current caravan run, not counting pauses
+#[derive(Resource, Default, Debug)]
+struct CaravanProgress {
+    elapsed: f32,
+}
+
+pub struct CaravanPlugin;
+
+impl Plugin for CaravanPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CaravanTimeline>()
+            .init_resource::<CaravanProgress>()
+            .add_systems(
+                OnEnter(AppState::Running),
+                reset_caravan_progress.run_if(caravan_mode),
+            )
+            .add_systems(
+                FixedUpdate,
+                run_caravan_timeline
+                    .run_if(caravan_mode.and_then(not_in_hitstop))
+                    .before(spawn_requested_enemies)
+                    .in_set(GameSet::Movement),
+            )
+            .add_systems(
+                Update,
+                follow_timeline_reloads.run_if(caravan_mode.and_then(in_state(AppState::Running))),
+            );
+    }
+}
+
+fn reset_caravan_progress(mut progress: ResMut<CaravanProgress>) {
+    *progress = CaravanProgress::default();
+}
+
+/// Carries on with the run on the edited caravan timeline
+fn follow_timeline_reloads(
+    mut events: EventReader<TimelineReloadedEvent>,
+    caravan: Res<CaravanTimeline>,
+    timelines: Res<Assets<StageTimeline>>,
+    progress: Res<CaravanProgress>,
+    mut scroll: ResMut<BackgroundScroll>,
+) {
+    for event in events.read() {
+        if event.id != caravan.0.id() {
+            continue;
+        }
+        let Some(timeline) = timelines.get(event.id) else {
+            continue;
+        };
+        report_changes(
+            "the caravan timeline",
+            &event.previous,
+            timeline,
+            progress.elapsed,
+        );
+        scroll.speed = timeline.scroll_speed_at(progress.elapsed).unwrap_or(1.);
+    }
+}
+
+fn run_caravan_timeline(
+    time: Res<Time>,
+    caravan: Res<CaravanTimeline>,
+    timelines: Res<Assets<StageTimeline>>,
+    mut progress: ResMut<CaravanProgress>,
+    playfield: Res<Playfield>,
+    mut spawn_events: EventWriter<SpawnEnemy>,
+    mut scroll: ResMut<BackgroundScroll>,
+) {
+    let from = progress.elapsed;
+    progress.elapsed += time.delta_seconds();
+    let Some(timeline) = timelines.get(&caravan.0) else {
+        return;
+    };
+
+    for event in timeline.events_between(from, progress.elapsed) {
+        match &event.action {
+            TimelineAction::SpawnWave {
+                enemies: count,
+                enemy,
+                pattern,
+            } => spawn_wave(
+                &mut spawn_events,
+                &playfield,
+                enemy.as_deref().unwrap_or(GRUNT),
+                *count,
+                pattern.as_deref(),
+            ),
+            TimelineAction::SetScrollSpeed(speed) => scroll.speed = *speed,
+            action => log::warn!("Caravan runs have no use for {action:?}"),
+        }
+    }
+}
@@ -0,0 +1,537 @@
+//! Collisions between bullets and ships, and what comes of them right away:
+//! damage, knockback, hit flashes and the hitstop on boss kills and phase
+//! breaks. Enemies are bucketed into a uniform grid every tick, and bullets
+//! are tested against the grid in parallel.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use bevy::log;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::audio::{PlaySfx, SfxId};
+use crate::boss::{Boss, BossDefeatedEvent, BossPhaseEvent, MID_BOSS_KIND};
+use crate::bullet::{Bullet, Damage, Hostility, Knockback, Velocity};
+use crate::enemy::{Enemy, EnemyKilledEvent, EnemyKind, KnockbackVelocity, Mass, ScoreValue};
+use crate::player::{player_hit, Player};
+use crate::settings::Settings;
+use crate::state::{gameplay_active, AppState};
+use crate::{DeathEvent, DeathKind, GameSet, HitPoints};
+
+const HIT_FEEDBACK_SECONDS: f32 = 0.05;
+/// How far hit flashes go towards their color with reduced flashing
+const SOFT_HIT_FLASH: f32 = 0.4;
+
+/// For the heavy impacts only: bosses and mid-bosses going down, and bosses
+/// breaking into their next phase
+const HEAVY_IMPACT_HITSTOP_FRAMES: u32 = 2;
+
+#[derive(Component)]
+pub struct Collider;
+
+/// Collision shape, centered on the entity's translation. Rotation and scale
+/// are ignored: capsules always run vertically, which is all lasers need.
+#[derive(Component, Clone, Copy, Debug)]
+pub enum ColliderShape {
+    Point,
+    Circle { radius: f32 },
+    Aabb { half_extents: Vec2 },
+    Capsule { half_height: f32, radius: f32 },
+}
+
+impl ColliderShape {
+    /// Half extents of the shape's bounding box
+    pub(crate) fn half_extents(&self) -> Vec2 {
+        match *self {
+            ColliderShape::Point => Vec2::ZERO,
+            ColliderShape::Circle { radius } => Vec2::splat(radius),
+            ColliderShape::Aabb { half_extents } => half_extents,
+            ColliderShape::Capsule {
+                half_height,
+                radius,
+            } => Vec2::new(radius, half_height + radius),
+        }
+    }
+
+    /// Every shape other than a box is a vertical segment swept by a radius:
+    /// a circle is a zero-length segment and a point also has a zero radius.
+    fn as_swept_segment(&self) -> Option<(f32, f32)> {
+        match *self {
+            ColliderShape::Point => Some((0., 0.)),
+            ColliderShape::Circle { radius } => Some((0., radius)),
+            ColliderShape::Aabb { .. } => None,
+            ColliderShape::Capsule {
+                half_height,
+                radius,
+            } => Some((half_height, radius)),
+        }
+    }
+
+    pub(crate) fn intersects(
+        &self,
+        position: Vec2,
+        other: &ColliderShape,
+        other_position: Vec2,
+    ) -> bool {
+        let offset = (other_position - position).abs();
+        match (self.as_swept_segment(), other.as_swept_segment()) {
+            (None, None) => {
+                let reach = self.half_extents() + other.half_extents();
+                offset.x <= reach.x && offset.y <= reach.y
+            }
+            (Some((half_height, radius)), None) => {
+                segment_intersects_box(offset, half_height, radius, other.half_extents())
+            }
+            (None, Some((half_height, radius))) => {
+                segment_intersects_box(offset, half_height, radius, self.half_extents())
+            }
+            (Some((half_height, radius)), Some((other_half_height, other_radius))) => {
+                // Distance between two vertical segments, compared to the summed radii
+                let gap = Vec2::new(
+                    offset.x,
+                    (offset.y - half_height - other_half_height).max(0.),
+                );
+                let reach = radius + other_radius;
+                gap.length_squared() <= reach * reach
+            }
+        }
+    }
+}
+
+pub struct CollisionPlugin;
+
+impl Plugin for CollisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Hitstop>()
+            .init_resource::<CollisionStats>()
+            .add_event::<CollisionEvent>()
+            .add_event::<HitEvent>()
+            // The hit ending the last run may have left a freeze behind
+            .add_systems(OnEnter(AppState::Running), reset_hitstop)
+            .add_systems(
+                FixedUpdate,
+                (
+                    reset_collision_stats,
+                    check_for_collisions,
+                    check_for_collisions_player,
+                )
+                    .chain()
+                    .in_set(GameSet::Collision),
+            )
+            .add_systems(
+                FixedUpdate,
+                (freeze_on_boss_phase, tick_hitstop)
+                    .chain()
+                    .after(player_hit)
+                    .in_set(GameSet::Resolution),
+            )
+            .add_systems(Update, update_hit_flashes.run_if(gameplay_active));
+    }
+}
+
+/// Whether a vertical segment swept by `radius` touches a box, given the
+/// absolute offset between their centers.
+fn segment_intersects_box(offset: Vec2, half_height: f32, radius: f32, half_extents: Vec2) -> bool {
+    let gap = Vec2::new(
+        (offset.x - half_extents.x).max(0.),
+        (offset.y - half_extents.y - half_height).max(0.),
+    );
+    gap.length_squared() <= radius * radius
+}
+
+/// A player bullet hit an enemy
+#[derive(Event, Clone, Copy, Debug)]
+pub struct CollisionEvent {
+    pub enemy: Entity,
+    /// Where the bullet struck
+    pub position: Vec2,
+    pub damage: u32,
+}
+
+/// A hostile bullet hit the player
+#[derive(Event, Default, Clone, Copy, Debug)]
+pub struct HitEvent {
+    pub damage: u32,
+    /// Where the bullet struck
+    pub position: Vec2,
+}
+
+/// Flashes the entity's material in another color for a moment whenever it
+/// gets hit
+#[derive(Component)]
+pub(crate) struct HitFlash {
+    pub(crate) timer: Timer,
+    /// The material's color between flashes
+    pub(crate) color: Color,
+    pub(crate) flash_color: Color,
+}
+
+impl HitFlash {
+    pub(crate) fn new(color: Color, flash_color: Color) -> Self {
+        let mut timer = Timer::from_seconds(HIT_FEEDBACK_SECONDS, TimerMode::Once);
+        // Not flashing until the first hit
+        timer.tick(timer.duration());
+        Self {
+            timer,
+            color,
+            flash_color,
+        }
+    }
+
+    /// Starts a flash, or starts the current one over
+    pub(crate) fn start(&mut self) {
+        self.timer.reset();
+    }
+}
+
+/// Global hitstop. While frames remain, enemies and bullets stay frozen in
+/// place to sell the weight of a heavy impact.
+#[derive(Resource, Default)]
+pub(crate) struct Hitstop {
+    frames_remaining: u32,
+}
+
+impl Hitstop {
+    /// Freezes for at least `frames` frames, never shortening an ongoing hitstop.
+    fn freeze(&mut self, frames: u32) {
+        self.frames_remaining = self.frames_remaining.max(frames);
+    }
+
+    fn is_active(&self) -> bool {
+        self.frames_remaining > 0
+    }
+}
+
+/// Narrow-phase work done during the last fixed tick
+#[derive(Resource, Default)]
+pub(crate) struct CollisionStats {
+    pub(crate) pairs_tested: usize,
+    pub(crate) hits: usize,
+}
+
+/// Uniform grid used as the collision broad phase. Colliders are bucketed into
+/// every cell their bounding box overlaps, so a query only needs to look at the
+/// cells its own bounding box overlaps.
+struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<(Entity, Vec2, ColliderShape)>>,
+}
+
+impl Default for SpatialGrid {
+    fn default() -> Self {
+        Self {
+            cell_size: 64.,
+            cells: HashMap::default(),
+        }
+    }
+}
+
+impl SpatialGrid {
+    fn cell(&self, position: Vec2) -> IVec2 {
+        (position / self.cell_size).floor().as_ivec2()
+    }
+
+    fn covered_cells(&self, position: Vec2, shape: &ColliderShape) -> impl Iterator<Item = IVec2> {
+        let min = self.cell(position - shape.half_extents());
+        let max = self.cell(position + shape.half_extents());
+        (min.x..=max.x).flat_map(move |x| (min.y..=max.y).map(move |y| IVec2::new(x, y)))
+    }
+
+    /// Empties every cell while keeping their allocations around for the next tick.
+    fn clear(&mut self) {
+        for entries in self.cells.values_mut() {
+            entries.clear();
+        }
+    }
+
+    fn insert(&mut self, entity: Entity, position: Vec2, shape: ColliderShape) {
+        for cell in self.covered_cells(position, &shape).collect::<Vec<_>>() {
+            self.cells
+                .entry(cell)
+                .or_default()
+                .push((entity, position, shape));
+        }
+    }
+
+    /// First collider in the grid that intersects the given shape. The number of
+    /// narrow-phase tests it took is added to `pairs_tested`.
+    fn find_intersecting(
+        &self,
+        position: Vec2,
+        shape: &ColliderShape,
+        pairs_tested: &AtomicUsize,
+    ) -> Option<Entity> {
+        let mut tested = 0;
+        let hit = self
+            .covered_cells(position, shape)
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .inspect(|_| tested += 1)
+            .find(|(_, other_position, other_shape)| {
+                shape.intersects(position, other_shape, *other_position)
+            })
+            .map(|(entity, ..)| *entity);
+        pairs_tested.fetch_add(tested, Ordering::Relaxed);
+        hit
+    }
+}
+
+fn reset_collision_stats(mut collision_stats: ResMut<CollisionStats>) {
+    *collision_stats = CollisionStats::default();
+}
+
+fn check_for_collisions(
+    mut commands: Commands,
+    mut grid: Local<SpatialGrid>,
+    bullet_query: Query<
+        (
+            Entity,
+            &Transform,
+            &Damage,
+            &Hostility,
+            &Velocity,
+            &ColliderShape,
+            Option<&Knockback>,
+        ),
+        With<Bullet>,
+    >,
+    mut enemy_query: Query<
+        (
+            Entity,
+            &Transform,
+            &ColliderShape,
+            &mut HitPoints,
+            &Mass,
+            &mut KnockbackVelocity,
+            Option<&mut HitFlash>,
+            Option<&ScoreValue>,
+            Option<&EnemyKind>,
+            Has<Boss>,
+        ),
+        (With<Enemy>, With<Collider>),
+    >,
+    mut collision_events: EventWriter<CollisionEvent>,
+    mut kill_events: EventWriter<EnemyKilledEvent>,
+    mut death_events: EventWriter<DeathEvent>,
+    mut boss_defeated_events: EventWriter<BossDefeatedEvent>,
+    mut hitstop: ResMut<Hitstop>,
+    mut collision_stats: ResMut<CollisionStats>,
+    mut sfx_events: EventWriter<PlaySfx>,
+) {
+    grid.clear();
+    for (enemy_entity, enemy_transform, enemy_shape, ..) in enemy_query.iter() {
+        grid.insert(
+            enemy_entity,
+            enemy_transform.translation.truncate(),
+            *enemy_shape,
+        );
+    }
+
+    // Narrow phase runs in parallel, only recording what happened. The hits are
+    // applied afterwards, since that needs mutable access to the enemies.
+    let hits = Mutex::new(Vec::new());
+    let pairs_tested = AtomicUsize::new(0);
+    bullet_query.par_iter().for_each(
+        |(
+            bullet_entity,
+            bullet_transform,
+            bullet_damage,
+            hostility,
+            velocity,
+            bullet_shape,
+            knockback,
+        )| {
+            // No enemy friendly fire
+            if let Hostility::Hostile = hostility {
+                return;
+            }
+            let hit = grid.find_intersecting(
+                bullet_transform.translation.truncate(),
+                bullet_shape,
+                &pairs_tested,
+            );
+            if let Some(enemy_entity) = hit {
+                let impulse = knockback.map_or(Vec3::ZERO, |knockback| {
+                    velocity.0.normalize_or_zero().extend(0.) * knockback.0
+                });
+                hits.lock().unwrap().push((
+                    bullet_entity,
+                    enemy_entity,
+                    bullet_transform.translation.truncate(),
+                    bullet_damage.0,
+                    impulse,
+                ));
+            }
+        },
+    );
+    let mut hits = hits.into_inner().unwrap();
+    // Keep the outcome independent of how the work was split between threads
+    hits.sort_unstable_by_key(|(bullet_entity, ..)| *bullet_entity);
+    collision_stats.pairs_tested += pairs_tested.into_inner();
+    collision_stats.hits += hits.len();
+
+    for (bullet_entity, enemy_entity, position, damage, impulse) in hits {
+        let Ok((
+            _,
+            enemy_transform,
+            enemy_shape,
+            mut enemy_hp,
+            mass,
+            mut knockback_velocity,
+            flash,
+            score_value,
+            kind,
+            is_boss,
+        )) = enemy_query.get_mut(enemy_entity)
+        else {
+            continue;
+        };
+        // Already destroyed by an earlier bullet this tick
+        if enemy_hp.0 == 0 {
+            continue;
+        }
+        log::info!(
+            "Found collision! Bullet {:?} and enemy at {:?}",
+            bullet_entity,
+            enemy_transform.translation
+        );
+        collision_events.send(CollisionEvent {
+            enemy: enemy_entity,
+            position,
+            damage,
+        });
+        sfx_events.send(PlaySfx::at(SfxId::Impact, position));
+        commands.entity(bullet_entity).despawn();
+        enemy_hp.0 = enemy_hp.0.saturating_sub(damage);
+        knockback_velocity.0 += impulse / mass.0;
+        if let Some(mut flash) = flash {
+            flash.start();
+        }
+        if enemy_hp.0 == 0 {
+            commands.entity(enemy_entity).despawn_recursive();
+            kill_events.send(EnemyKilledEvent {
+                position: enemy_transform.translation.truncate(),
+                score: score_value.map_or(0, |score| score.0),
+                kind: kind.map(|kind| kind.0.clone()),
+            });
+            death_events.send(DeathEvent {
+                entity: enemy_entity,
+                position: enemy_transform.translation.truncate(),
+                kind: if is_boss {
+                    DeathKind::Boss
+                } else {
+                    DeathKind::Enemy
+                },
+            });
+            if is_boss {
+                boss_defeated_events.send(BossDefeatedEvent {
+                    area: Rect::from_center_half_size(
+                        enemy_transform.translation.truncate(),
+                        enemy_shape.half_extents(),
+                    ),
+                });
+            }
+            sfx_events.send(PlaySfx::at(
+                SfxId::Explosion,
+                enemy_transform.translation.truncate(),
+            ));
+            let mid_boss = kind.is_some_and(|kind| kind.0 == MID_BOSS_KIND);
+            if is_boss || mid_boss {
+                hitstop.freeze(HEAVY_IMPACT_HITSTOP_FRAMES);
+            }
+        }
+    }
+}
+
+fn check_for_collisions_player(
+    mut commands: Commands,
+    bullet_query: Query<(Entity, &Transform, &Damage, &Hostility, &ColliderShape), With<Bullet>>,
+    player_query: Query<(&Transform, &ColliderShape), (With<Player>, With<Collider>)>,
+    mut hit_events: EventWriter<HitEvent>,
+    mut sfx_events: EventWriter<PlaySfx>,
+    mut collision_stats: ResMut<CollisionStats>,
+) {
+    let players = player_query
+        .iter()
+        .map(|(transform, shape)| (transform.translation.truncate(), *shape))
+        .collect::<Vec<_>>();
+
+    let hits = Mutex::new(Vec::new());
+    let pairs_tested = AtomicUsize::new(0);
+    bullet_query.par_iter().for_each(
+        |(bullet_entity, bullet_transform, bullet_damage, hostility, bullet_shape)| {
+            // No friendly fire. Unused right now, but maybe in coop?
+            if let Hostility::Friendly = hostility {
+                return;
+            }
+            pairs_tested.fetch_add(players.len(), Ordering::Relaxed);
+            let bullet_position = bullet_transform.translation.truncate();
+            let is_hit = players.iter().any(|(player_position, player_shape)| {
+                bullet_shape.intersects(bullet_position, player_shape, *player_position)
+            });
+            if is_hit {
+                hits.lock()
+                    .unwrap()
+                    .push((bullet_entity, bullet_position, bullet_damage.0));
+            }
+        },
+    );
+    let mut hits = hits.into_inner().unwrap();
+    hits.sort_unstable_by_key(|(bullet_entity, ..)| *bullet_entity);
+    collision_stats.pairs_tested += pairs_tested.into_inner();
+    collision_stats.hits += hits.len();
+
+    for (bullet_entity, position, damage) in hits {
+        commands.entity(bullet_entity).despawn();
+        hit_events.send(HitEvent { damage, position });
+        sfx_events.send(PlaySfx::at(SfxId::PlayerHit, position));
+    }
+}
+
+pub(crate) fn not_in_hitstop(hitstop: Res<Hitstop>) -> bool {
+    !hitstop.is_active()
+}
+
+fn freeze_on_boss_phase(
+    mut phase_events: EventReader<BossPhaseEvent>,
+    mut hitstop: ResMut<Hitstop>,
+) {
+    if phase_events.read().count() > 0 {
+        hitstop.freeze(HEAVY_IMPACT_HITSTOP_FRAMES);
+    }
+}
+
+fn reset_hitstop(mut hitstop: ResMut<Hitstop>) {
+    *hitstop = Hitstop::default();
+}
+
+fn tick_hitstop(mut hitstop: ResMut<Hitstop>) {
+    hitstop.frames_remaining = hitstop.frames_remaining.saturating_sub(1);
+}
+
+/// With reduced flashing, flashes only go partway to their color, so a
+/// stream of hits doesn't strobe
+fn update_hit_flashes(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut query: Query<(&mut HitFlash, &Handle<ColorMaterial>)>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (mut flash, handle) in query.iter_mut() {
+        if flash.timer.finished() && !flash.timer.just_finished() {
+            continue;
+        }
+        let color = if flash.timer.tick(time.delta()).finished() {
+            flash.color
+        } else if settings.reduced_flashing {
+            let (from, to) = (Vec4::from(flash.color), Vec4::from(flash.flash_color));
+            Color::from(from.lerp(to, SOFT_HIT_FLASH))
+        } else {
+            flash.flash_color
+        };
+        if let Some(material) = materials.get_mut(handle) {
+            material.color = color;
+        }
+    }
+}
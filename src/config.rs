@@ -0,0 +1,164 @@
+//! Game configuration: sizes, speeds and other balance numbers, and the
+//! colors of the classic palette, read from `config.ron` in the working
+//! directory at startup. Anything the file leaves out keeps its default, so it
+//! only needs what it changes, for example
+//!
+//! ```ron
+//! (
+//!     player: (max_hp: 150, speed: 500.0),
+//!     enemy_bullet_speed: 400.0,
+//! )
+//! ```
+//!
+//! A file that can't be parsed is reported and ignored, and so is any size,
+//! speed or cooldown in it that isn't above 0, which keeps its default. That
+//! way the game always starts. Apps embedding the game can override parts of
+//! it through `GamePluginConfig`.
+
+use bevy::log;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::palette::Palette;
+
+const CONFIG_PATH: &str = "config.ron";
+
+#[derive(Resource, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct GameConfig {
+    /// Width and height of the playfield
+    pub screen: (f32, f32),
+    pub player: PlayerConfig,
+    pub bullet_radius: f32,
+    /// Speed of enemy bullets, before difficulty, rank and patterns
+    pub enemy_bullet_speed: f32,
+    /// Drawn outside of runs through the stages, in the first stage and
+    /// whenever the settings pick it
+    pub classic_palette: Palette,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlayerConfig {
+    pub max_hp: u32,
+    /// Width and height
+    pub size: (f32, f32),
+    pub speed: f32,
+    /// Speed while focusing
+    pub focused_speed: f32,
+    /// Extra lives at the start of a run
+    pub lives: u32,
+    /// Bombs at the start of a run
+    pub bombs: u32,
+    /// Seconds between shots
+    pub shot_cooldown: f32,
+    pub shot_damage: u32,
+    pub shot_speed: f32,
+    /// How hard shots push enemies back
+    pub shot_knockback: f32,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            screen: (600., 800.),
+            player: PlayerConfig::default(),
+            bullet_radius: 10.,
+            enemy_bullet_speed: 500.,
+            classic_palette: Palette::CLASSIC,
+        }
+    }
+}
+
+impl Default for PlayerConfig {
+    fn default() -> Self {
+        Self {
+            max_hp: 100,
+            size: (50., 50.),
+            speed: 600.,
+            focused_speed: 250.,
+            lives: 2,
+            bombs: 3,
+            shot_cooldown: 0.25,
+            shot_damage: 10,
+            shot_speed: 1000.,
+            shot_knockback: 150.,
+        }
+    }
+}
+
+impl GameConfig {
+    /// Falls back to the defaults without a readable config file
+    pub fn load() -> Self {
+        let Ok(contents) = std::fs::read_to_string(CONFIG_PATH) else {
+            return Self::default();
+        };
+        match ron::de::from_str(&contents) {
+            Ok(config) => {
+                log::info!("Loaded the game configuration from {CONFIG_PATH}");
+                Self::in_range(config)
+            }
+            Err(error) => {
+                log::warn!("Could not parse {CONFIG_PATH}, using the defaults: {error}");
+                Self::default()
+            }
+        }
+    }
+
+    /// `config` with the values out of range put back to their defaults
+    fn in_range(mut config: Self) -> Self {
+        let defaults = Self::default();
+        let player = &mut config.player;
+        let default_player = &defaults.player;
+        for (name, value, default) in [
+            ("screen.0", &mut config.screen.0, defaults.screen.0),
+            ("screen.1", &mut config.screen.1, defaults.screen.1),
+            (
+                "bullet_radius",
+                &mut config.bullet_radius,
+                defaults.bullet_radius,
+            ),
+            (
+                "enemy_bullet_speed",
+                &mut config.enemy_bullet_speed,
+                defaults.enemy_bullet_speed,
+            ),
+            ("player.size.0", &mut player.size.0, default_player.size.0),
+            ("player.size.1", &mut player.size.1, default_player.size.1),
+            ("player.speed", &mut player.speed, default_player.speed),
+            (
+                "player.focused_speed",
+                &mut player.focused_speed,
+                default_player.focused_speed,
+            ),
+            (
+                "player.shot_cooldown",
+                &mut player.shot_cooldown,
+                default_player.shot_cooldown,
+            ),
+            (
+                "player.shot_speed",
+                &mut player.shot_speed,
+                default_player.shot_speed,
+            ),
+        ] {
+            if !(*value > 0. && value.is_finite()) {
+                log::warn!(
+                    "`{name}` in {CONFIG_PATH} must be above 0, got {value}, using {default}"
+                );
+                *value = default;
+            }
+        }
+        config
+    }
+
+    pub fn screen_size(&self) -> Vec2 {
+        Vec2::new(self.screen.0, self.screen.1)
+    }
+}
+
+impl PlayerConfig {
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.size.0, self.size.1)
+    }
+}
@@ -0,0 +1,469 @@
+//! Controls screen, opened from the settings screen in its place. Every
+//! action played with has a row of key slots and a gamepad button slot:
+//! picking one waits for the next key or button pressed and binds it there,
+//! taking effect right away. Esc, or Select on a gamepad, cancels the wait
+//! and Backspace empties the slot instead. An input bound to another action
+//! is taken from it, see `Bindings::rebind`, and the screen says which.
+//!
+//! The inputs the game keeps for itself during runs, like the mute toggle,
+//! can't be bound, see `reserved_by`.
+
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::ui::{FocusPolicy, UiSystem};
+use fluent::fluent_args;
+
+use crate::bindings::{
+    reserved_by, update_actions, Action, ActionState, Bindable, Bindings, GamepadBindings,
+    KeyBindings,
+};
+use crate::locale::{update_locale, Locale};
+use crate::modal::{no_modal_open, spawn_confirm_dialog, ConfirmDialog};
+use crate::settings::{ControlScheme, Settings, SettingsMenuState};
+use crate::theme::UiTheme;
+
+/// Keys every action can have
+const SLOTS: usize = 2;
+const NAME_WIDTH: f32 = 200.;
+const SLOT_WIDTH: f32 = 150.;
+const COLUMN_GAP: f32 = 4.;
+const ROW_WIDTH: f32 = 360.;
+const ROW_HEIGHT: f32 = 36.;
+/// Stops waiting for a key or button
+const CANCEL_KEY: KeyCode = KeyCode::Escape;
+const CANCEL_BUTTON: GamepadButtonType = GamepadButtonType::Select;
+/// Empties the slot waiting for a key or button
+const CLEAR_KEY: KeyCode = KeyCode::Back;
+
+/// What the line under the slots says. Inputs are kept by name, keys and
+/// buttons alike.
+#[derive(Clone, Debug, Default)]
+enum Notice {
+    #[default]
+    Hint,
+    /// The input bound was taken from `from`, which got `replacement` back
+    Taken {
+        input: String,
+        from: Action,
+        replacement: Option<String>,
+    },
+    /// The input is kept for `action`
+    Reserved { input: String, action: Action },
+}
+
+/// The slot waiting for a key or button, if any, and what the last one bound
+/// did
+#[derive(Resource, Default, Debug)]
+struct Rebinding {
+    waiting: Option<ControlsButton>,
+    notice: Notice,
+}
+
+impl Rebinding {
+    fn notice(&self, locale: &Locale) -> String {
+        let waiting = |id, action: Action| {
+            locale.format(id, &fluent_args!["action" => locale.get(action.label())])
+        };
+        match self.waiting {
+            Some(ControlsButton::Key(action, _)) => return waiting("controls-waiting", action),
+            Some(ControlsButton::Button(action)) => {
+                return waiting("controls-waiting-button", action)
+            }
+            _ => {}
+        }
+        match &self.notice {
+            Notice::Hint => locale.get("controls-hint"),
+            Notice::Taken {
+                input,
+                from,
+                replacement: Some(replacement),
+            } => locale.format(
+                "controls-swapped",
+                &fluent_args![
+                    "input" => input.as_str(),
+                    "action" => locale.get(from.label()),
+                    "replacement" => replacement.as_str(),
+                ],
+            ),
+            Notice::Taken {
+                input,
+                from,
+                replacement: None,
+            } => locale.format(
+                "controls-taken",
+                &fluent_args![
+                    "input" => input.as_str(),
+                    "action" => locale.get(from.label()),
+                ],
+            ),
+            Notice::Reserved { input, action } => locale.format(
+                "controls-reserved",
+                &fluent_args![
+                    "input" => input.as_str(),
+                    "action" => locale.get(action.label()),
+                ],
+            ),
+        }
+    }
+}
+
+#[derive(Component)]
+struct ControlsMenu;
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+enum ControlsButton {
+    /// One of the keys of an action
+    Key(Action, usize),
+    /// The gamepad button of an action
+    Button(Action),
+    /// Cycles through the control schemes
+    Scheme,
+    /// Cycles through the ways the mouse or touch screen moves the ship
+    Pointer,
+    /// Asks before putting the default key bindings back
+    Reset,
+    Back,
+}
+
+impl ControlsButton {
+    fn label(self, settings: &Settings, rebinding: &Rebinding, locale: &Locale) -> String {
+        let bindings = &settings.bindings;
+        let input_name = |input: Option<&dyn std::fmt::Debug>| {
+            input.map_or("-".to_string(), |input| format!("{input:?}"))
+        };
+        let row = |name, value| {
+            locale.format(
+                "settings-row",
+                &fluent_args!["name" => locale.get(name), "value" => locale.get(value)],
+            )
+        };
+        match self {
+            ControlsButton::Key(..) if rebinding.waiting == Some(self) => {
+                locale.get("controls-press")
+            }
+            ControlsButton::Button(_) if rebinding.waiting == Some(self) => {
+                locale.get("controls-press-button")
+            }
+            ControlsButton::Key(action, slot) => {
+                input_name(bindings.inputs(action).get(slot).map(|key| key as _))
+            }
+            ControlsButton::Button(action) => input_name(
+                settings
+                    .gamepad_bindings
+                    .inputs(action)
+                    .first()
+                    .map(|button| button as _),
+            ),
+            ControlsButton::Scheme => {
+                let scheme = bindings.scheme();
+                row(
+                    "controls-scheme",
+                    scheme.map_or("controls-custom", ControlScheme::label),
+                )
+            }
+            ControlsButton::Pointer => row("settings-pointer", settings.pointer_movement.label()),
+            ControlsButton::Reset => locale.get("controls-reset"),
+            ControlsButton::Back => locale.get("button-back"),
+        }
+    }
+}
+
+/// Text showing a button's current key or value
+#[derive(Component)]
+struct ControlsLabel(ControlsButton);
+
+#[derive(Component)]
+struct NoticeText;
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Rebinding>()
+            .add_systems(OnEnter(SettingsMenuState::Controls), spawn_controls_menu)
+            .add_systems(OnExit(SettingsMenuState::Controls), despawn_controls_menu)
+            // Ahead of the actions and everything else reading keys and
+            // buttons, so the one bound does nothing else
+            .add_systems(
+                PreUpdate,
+                capture_input
+                    .after(InputSystem)
+                    .before(update_actions)
+                    .before(UiSystem::Focus)
+                    .run_if(in_state(SettingsMenuState::Controls)),
+            )
+            .add_systems(
+                Update,
+                (
+                    controls_buttons,
+                    back_on_escape.run_if(no_modal_open),
+                    update_controls_labels.after(update_locale),
+                )
+                    .chain()
+                    .run_if(in_state(SettingsMenuState::Controls)),
+            );
+    }
+}
+
+fn spawn_controls_menu(
+    mut commands: Commands,
+    mut rebinding: ResMut<Rebinding>,
+    settings: Res<Settings>,
+    theme: Res<UiTheme>,
+    locale: Res<Locale>,
+) {
+    *rebinding = Rebinding::default();
+    let spawn_button = |parent: &mut ChildBuilder, button: ControlsButton, width: Val| {
+        parent
+            .spawn((
+                ButtonBundle {
+                    style: Style {
+                        min_width: width,
+                        height: Val::Px(ROW_HEIGHT),
+                        border: UiRect::all(Val::Px(3.)),
+                        padding: UiRect::horizontal(Val::Px(12.)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    border_color: BorderColor(Color::BLACK),
+                    background_color: Color::WHITE.into(),
+                    ..default()
+                },
+                button,
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    TextBundle::from_section(
+                        button.label(&settings, &rebinding, &locale),
+                        theme.label.clone(),
+                    ),
+                    ControlsLabel(button),
+                ));
+            });
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(4.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.9).into(),
+                // Keeps the mouse off the menu underneath
+                focus_policy: FocusPolicy::Block,
+                // Above the pause or main menu the settings were opened from
+                z_index: ZIndex::Global(1),
+                ..default()
+            },
+            ControlsMenu,
+        ))
+        .with_children(|parent| {
+            parent.spawn(locale.text("controls", theme.title.clone()));
+            let row = NodeBundle {
+                style: Style {
+                    align_items: AlignItems::Center,
+                    column_gap: Val::Px(COLUMN_GAP),
+                    ..default()
+                },
+                ..default()
+            };
+            let cell = |parent: &mut ChildBuilder, id: &'static str, width: f32| {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Px(width),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(locale.text(id, theme.hud.clone()));
+                    });
+            };
+            parent.spawn(row.clone()).with_children(|parent| {
+                parent.spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(NAME_WIDTH),
+                        ..default()
+                    },
+                    ..default()
+                });
+                let keys_width = SLOT_WIDTH * SLOTS as f32 + COLUMN_GAP * (SLOTS - 1) as f32;
+                cell(parent, "controls-keys", keys_width);
+                cell(parent, "controls-gamepad", SLOT_WIDTH);
+            });
+            for action in Action::REBINDABLE {
+                parent.spawn(row.clone()).with_children(|parent| {
+                    cell(parent, action.label(), NAME_WIDTH);
+                    for slot in 0..SLOTS {
+                        spawn_button(
+                            parent,
+                            ControlsButton::Key(action, slot),
+                            Val::Px(SLOT_WIDTH),
+                        );
+                    }
+                    spawn_button(parent, ControlsButton::Button(action), Val::Px(SLOT_WIDTH));
+                });
+            }
+            parent.spawn((
+                TextBundle::from_section(rebinding.notice(&locale), theme.hud.clone()),
+                NoticeText,
+            ));
+            for button in [
+                ControlsButton::Scheme,
+                ControlsButton::Pointer,
+                ControlsButton::Reset,
+                ControlsButton::Back,
+            ] {
+                spawn_button(parent, button, Val::Px(ROW_WIDTH));
+            }
+        });
+}
+
+fn despawn_controls_menu(mut commands: Commands, query: Query<Entity, With<ControlsMenu>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Binds `input` to the slot, unless the game keeps it for itself
+fn bind<T: Bindable>(bindings: &mut Bindings<T>, action: Action, slot: usize, input: T) -> Notice {
+    let name = |input: T| format!("{input:?}");
+    if let Some(reserved) = reserved_by(input) {
+        return Notice::Reserved {
+            input: name(input),
+            action: reserved,
+        };
+    }
+    match bindings.rebind(action, slot, input) {
+        Some(rebound) => Notice::Taken {
+            input: name(input),
+            from: rebound.from,
+            replacement: rebound.replacement.map(name),
+        },
+        None => Notice::Hint,
+    }
+}
+
+fn capture_input(
+    mut keyboard: ResMut<Input<KeyCode>>,
+    mut gamepad_buttons: ResMut<Input<GamepadButton>>,
+    mut rebinding: ResMut<Rebinding>,
+    mut settings: ResMut<Settings>,
+) {
+    let Some(waiting) = rebinding.waiting else {
+        return;
+    };
+    let key = keyboard.get_just_pressed().next().copied();
+    let button = gamepad_buttons.get_just_pressed().next().copied();
+    // Whatever else it is, it isn't pressed for anything else while waiting
+    if let Some(key) = key {
+        keyboard.clear_just_pressed(key);
+    }
+    if let Some(button) = button {
+        gamepad_buttons.clear_just_pressed(button);
+    }
+    let button_type = button.map(|button| button.button_type);
+    // Settings are only touched by the arms that change them, as any mutable
+    // access has them saved again
+    rebinding.notice = match (waiting, key, button_type) {
+        (_, Some(CANCEL_KEY), _) | (_, _, Some(CANCEL_BUTTON)) => Notice::Hint,
+        (ControlsButton::Key(action, slot), Some(CLEAR_KEY), _) => {
+            settings.bindings.unbind(action, slot);
+            Notice::Hint
+        }
+        (ControlsButton::Button(action), Some(CLEAR_KEY), _) => {
+            settings.gamepad_bindings.unbind(action, 0);
+            Notice::Hint
+        }
+        (ControlsButton::Key(action, slot), Some(key), _) => {
+            bind(&mut settings.bindings, action, slot, key)
+        }
+        (ControlsButton::Button(action), _, Some(button_type)) => {
+            bind(&mut settings.gamepad_bindings, action, 0, button_type)
+        }
+        _ => return,
+    };
+    rebinding.waiting = None;
+}
+
+fn controls_buttons(
+    mut commands: Commands,
+    theme: Res<UiTheme>,
+    locale: Res<Locale>,
+    interaction_query: Query<(&Interaction, &ControlsButton), Changed<Interaction>>,
+    mut rebinding: ResMut<Rebinding>,
+    mut settings: ResMut<Settings>,
+    mut next_state: ResMut<NextState<SettingsMenuState>>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        *rebinding = Rebinding::default();
+        match *button {
+            ControlsButton::Key(..) | ControlsButton::Button(_) => {
+                rebinding.waiting = Some(*button)
+            }
+            // Custom bindings go back to the first scheme
+            ControlsButton::Scheme => {
+                let scheme = settings
+                    .bindings
+                    .scheme()
+                    .map_or(ControlScheme::default(), ControlScheme::next);
+                settings.bindings = KeyBindings::for_scheme(scheme);
+            }
+            ControlsButton::Pointer => {
+                settings.pointer_movement = settings.pointer_movement.next();
+            }
+            ControlsButton::Reset => spawn_confirm_dialog(
+                &mut commands,
+                &theme,
+                ConfirmDialog::new(
+                    &locale,
+                    locale.get("reset-bindings-title"),
+                    locale.get("reset-bindings-message"),
+                ),
+                |world| {
+                    let mut settings = world.resource_mut::<Settings>();
+                    settings.bindings = KeyBindings::default();
+                    settings.gamepad_bindings = GamepadBindings::default();
+                },
+            ),
+            ControlsButton::Back => next_state.set(SettingsMenuState::Open),
+        }
+    }
+}
+
+/// Back to the settings screen. Never while waiting for a key or button,
+/// which takes Esc for itself.
+fn back_on_escape(actions: Res<ActionState>, mut next_state: ResMut<NextState<SettingsMenuState>>) {
+    if actions.just_pressed(Action::MenuBack) {
+        next_state.set(SettingsMenuState::Open);
+    }
+}
+
+fn update_controls_labels(
+    settings: Res<Settings>,
+    rebinding: Res<Rebinding>,
+    locale: Res<Locale>,
+    mut labels: Query<(&ControlsLabel, &mut Text), Without<NoticeText>>,
+    mut notices: Query<&mut Text, With<NoticeText>>,
+) {
+    if !settings.is_changed() && !rebinding.is_changed() && !locale.is_changed() {
+        return;
+    }
+    for (label, mut text) in labels.iter_mut() {
+        text.sections[0].value = label.0.label(&settings, &rebinding, &locale);
+    }
+    for mut text in notices.iter_mut() {
+        text.sections[0].value = rebinding.notice(&locale);
+    }
+}
@@ -0,0 +1,185 @@
+//! Credits roll, reached from the main menu. The text comes straight from
+//! `credits/CREDITS.md`, so the screen never drifts from what ships with the
+//! builds.
+
+use bevy::prelude::*;
+
+use crate::bindings::{Action, ActionState};
+use crate::locale::Locale;
+use crate::state::AppState;
+use crate::theme::UiTheme;
+
+const CREDITS: &str = include_str!("../credits/CREDITS.md");
+const SCROLL_SPEED: f32 = 60.;
+/// Where the roll starts, just below the bottom of the playfield
+const SCROLL_START: f32 = 800.;
+
+pub struct CreditsPlugin;
+
+impl Plugin for CreditsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::Credits), spawn_credits)
+            .add_systems(OnExit(AppState::Credits), despawn_credits)
+            .add_systems(
+                Update,
+                (scroll_credits, skip_credits).run_if(in_state(AppState::Credits)),
+            );
+    }
+}
+
+#[derive(Component)]
+struct CreditsScreen;
+
+#[derive(Component)]
+struct CreditsRoll;
+
+#[derive(Component)]
+struct CreditsBackButton;
+
+/// One line of the credits file, stripped of its markdown
+enum CreditsLine {
+    Heading(String),
+    Entry(String),
+}
+
+fn parse_credits(markdown: &str) -> Vec<CreditsLine> {
+    markdown
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let heading = line.trim_start_matches('#');
+            if heading.len() < line.len() {
+                CreditsLine::Heading(heading.trim().to_string())
+            } else {
+                let entry = line.trim_start_matches('*').trim().trim_end_matches(';');
+                CreditsLine::Entry(strip_links(entry))
+            }
+        })
+        .collect()
+}
+
+/// Turns `[text](url)` into `text`
+fn strip_links(line: &str) -> String {
+    let mut stripped = String::new();
+    let mut rest = line;
+    while let Some(start) = rest.find('[') {
+        let Some(end) = rest[start..].find("](").map(|end| start + end) else {
+            break;
+        };
+        let Some(close) = rest[end..].find(')').map(|close| end + close) else {
+            break;
+        };
+        stripped.push_str(&rest[..start]);
+        stripped.push_str(&rest[start + 1..end]);
+        rest = &rest[close + 1..];
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+fn spawn_credits(mut commands: Commands, theme: Res<UiTheme>, locale: Res<Locale>) {
+    let mut heading_style = theme.title.clone();
+    heading_style.font_size = 48.;
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    overflow: Overflow::clip(),
+                    ..default()
+                },
+                ..default()
+            },
+            CreditsScreen,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            width: Val::Percent(100.),
+                            top: Val::Px(SCROLL_START),
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            row_gap: Val::Px(12.),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    CreditsRoll,
+                ))
+                .with_children(|parent| {
+                    for line in parse_credits(CREDITS) {
+                        let (text, style) = match line {
+                            CreditsLine::Heading(text) => (text, heading_style.clone()),
+                            CreditsLine::Entry(text) => (text, theme.hud.clone()),
+                        };
+                        parent.spawn(TextBundle::from_section(text, style));
+                    }
+                });
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            bottom: Val::Px(20.),
+                            right: Val::Px(20.),
+                            min_width: Val::Px(160.),
+                            height: Val::Px(50.),
+                            border: UiRect::all(Val::Px(3.)),
+                            padding: UiRect::horizontal(Val::Px(12.)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        border_color: BorderColor(Color::BLACK),
+                        background_color: Color::WHITE.into(),
+                        ..default()
+                    },
+                    CreditsBackButton,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(locale.text("button-back", theme.label.clone()));
+                });
+        });
+}
+
+fn despawn_credits(mut commands: Commands, query: Query<Entity, With<CreditsScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Rolls the credits upwards and heads back to the menu once they are gone
+fn scroll_credits(
+    time: Res<Time>,
+    mut query: Query<(&mut Style, &Node), With<CreditsRoll>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    for (mut style, node) in query.iter_mut() {
+        let Val::Px(top) = style.top else {
+            continue;
+        };
+        let top = top - SCROLL_SPEED * time.delta_seconds();
+        style.top = Val::Px(top);
+        if top + node.size().y < 0. {
+            next_state.set(AppState::MainMenu);
+        }
+    }
+}
+
+fn skip_credits(
+    actions: Res<ActionState>,
+    back_button: Query<&Interaction, (Changed<Interaction>, With<CreditsBackButton>)>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let back_pressed = back_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed);
+    if back_pressed || actions.just_pressed(Action::MenuBack) {
+        next_state.set(AppState::MainMenu);
+    }
+}
@@ -0,0 +1,87 @@
+//! Small numbers rising from every impact point and fading out, showing how
+//! much damage each hit dealt. Can be turned off in the settings.
+
+use bevy::prelude::*;
+
+use crate::collision::{CollisionEvent, HitEvent};
+use crate::settings::Settings;
+use crate::state::gameplay_active;
+use crate::theme::UiTheme;
+
+const LIFETIME_SECONDS: f32 = 0.6;
+const RISE_SPEED: f32 = 60.;
+const FONT_SIZE: f32 = 18.;
+const ENEMY_DAMAGE_COLOR: Color = Color::WHITE;
+const PLAYER_DAMAGE_COLOR: Color = Color::RED;
+
+pub struct DamageNumbersPlugin;
+
+impl Plugin for DamageNumbersPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                spawn_damage_numbers.run_if(|settings: Res<Settings>| settings.damage_numbers),
+                animate_damage_numbers,
+            )
+                .chain()
+                .run_if(gameplay_active),
+        );
+    }
+}
+
+#[derive(Component)]
+struct DamageNumber {
+    elapsed: f32,
+}
+
+fn spawn_damage_numbers(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut hit_events: EventReader<HitEvent>,
+    theme: Res<UiTheme>,
+) {
+    let enemy_hits = collision_events
+        .read()
+        .map(|event| (event.position, event.damage, ENEMY_DAMAGE_COLOR));
+    let player_hits = hit_events
+        .read()
+        .map(|event| (event.position, event.damage, PLAYER_DAMAGE_COLOR));
+    for (position, damage, color) in enemy_hits.chain(player_hits) {
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    damage.to_string(),
+                    TextStyle {
+                        font: theme.font.clone(),
+                        font_size: FONT_SIZE,
+                        color,
+                    },
+                ),
+                // In front of the ships and bullets
+                transform: Transform::from_translation(position.extend(10.)),
+                ..default()
+            },
+            DamageNumber { elapsed: 0. },
+        ));
+    }
+}
+
+fn animate_damage_numbers(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut DamageNumber, &mut Transform, &mut Text)>,
+) {
+    for (entity, mut damage_number, mut transform, mut text) in query.iter_mut() {
+        damage_number.elapsed += time.delta_seconds();
+        if damage_number.elapsed >= LIFETIME_SECONDS {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        transform.translation.y += RISE_SPEED * time.delta_seconds();
+        let alpha = 1. - damage_number.elapsed / LIFETIME_SECONDS;
+        for section in text.sections.iter_mut() {
+            section.style.color.set_a(alpha);
+        }
+    }
+}
@@ -0,0 +1,206 @@
+//! Performance overlay toggled with F3: FPS, frame time, entity counts by type
+//! and how many collision pairs the narrow phase had to test.
+//!
+//! Also the small FPS counter players can turn on in the settings or with F2.
+
+use bevy::diagnostic::{
+    Diagnostic, DiagnosticId, Diagnostics, DiagnosticsStore, FrameTimeDiagnosticsPlugin,
+    RegisterDiagnostic,
+};
+use bevy::prelude::*;
+
+use crate::bindings::{Action, ActionState};
+use crate::bullet::Bullet;
+use crate::collision::CollisionStats;
+use crate::enemy::Enemy;
+use crate::hud::{HudAnchor, HudRegion};
+use crate::settings::Settings;
+use crate::state::AppState;
+use crate::theme::UiTheme;
+
+const BULLET_COUNT: DiagnosticId = DiagnosticId::from_u128(133059786320915730366312416359437813291);
+const ENEMY_COUNT: DiagnosticId = DiagnosticId::from_u128(92376283504926138572196812334860427810);
+const COLLISION_PAIRS: DiagnosticId =
+    DiagnosticId::from_u128(261727063580373011983522853735190938377);
+const COLLISION_HITS: DiagnosticId =
+    DiagnosticId::from_u128(47730154373542417151046208219651320644);
+
+const HISTORY_LENGTH: usize = 20;
+
+pub struct PerformanceOverlayPlugin;
+
+impl Plugin for PerformanceOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+            app.add_plugins(FrameTimeDiagnosticsPlugin);
+        }
+        app.register_diagnostic(Diagnostic::new(BULLET_COUNT, "bullets", HISTORY_LENGTH))
+            .register_diagnostic(Diagnostic::new(ENEMY_COUNT, "enemies", HISTORY_LENGTH))
+            .register_diagnostic(Diagnostic::new(
+                COLLISION_PAIRS,
+                "collision_pairs",
+                HISTORY_LENGTH,
+            ))
+            .register_diagnostic(Diagnostic::new(
+                COLLISION_HITS,
+                "collision_hits",
+                HISTORY_LENGTH,
+            ))
+            .init_resource::<OverlayVisibility>()
+            .add_systems(
+                OnEnter(AppState::Running),
+                (spawn_overlay, spawn_fps_counter),
+            )
+            .add_systems(
+                Update,
+                (
+                    measure,
+                    toggle_overlay,
+                    update_overlay.run_if(|visibility: Res<OverlayVisibility>| visibility.0),
+                    toggle_fps_counter,
+                    show_fps_counter.run_if(resource_changed::<Settings>()),
+                    update_fps_counter.run_if(|settings: Res<Settings>| settings.show_fps),
+                )
+                    // The overlay and counter only exist during a run
+                    .run_if(in_state(AppState::Running)),
+            );
+    }
+}
+
+/// Survives restarts, unlike the overlay entity itself
+#[derive(Resource, Default)]
+struct OverlayVisibility(bool);
+
+#[derive(Component)]
+struct PerformanceOverlayText;
+
+#[derive(Component)]
+struct FpsCounterText;
+
+fn visibility_from(shown: bool) -> Visibility {
+    if shown {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    }
+}
+
+fn spawn_overlay(mut commands: Commands, visibility: Res<OverlayVisibility>, theme: Res<UiTheme>) {
+    let style = TextStyle {
+        font_size: 16.,
+        color: Color::LIME_GREEN,
+        ..theme.hud.clone()
+    };
+    let sections = ["FPS", "Frame time", "Bullets", "Enemies", "Collisions"]
+        .into_iter()
+        .flat_map(|label| {
+            [
+                TextSection::new(format!("{label}: "), style.clone()),
+                TextSection::new("-\n", style.clone()),
+            ]
+        });
+    let mut overlay = TextBundle::from_sections(sections);
+    overlay.visibility = visibility_from(visibility.0);
+    commands.spawn((
+        overlay,
+        PerformanceOverlayText,
+        HudAnchor(HudRegion::TopRight),
+    ));
+}
+
+fn spawn_fps_counter(mut commands: Commands, settings: Res<Settings>, theme: Res<UiTheme>) {
+    let mut counter = TextBundle::from_section("", theme.hud.clone());
+    counter.visibility = visibility_from(settings.show_fps);
+    commands.spawn((counter, FpsCounterText, HudAnchor(HudRegion::BottomRight)));
+}
+
+fn measure(
+    mut diagnostics: Diagnostics,
+    bullet_query: Query<(), With<Bullet>>,
+    enemy_query: Query<(), With<Enemy>>,
+    collision_stats: Res<CollisionStats>,
+) {
+    diagnostics.add_measurement(BULLET_COUNT, || bullet_query.iter().len() as f64);
+    diagnostics.add_measurement(ENEMY_COUNT, || enemy_query.iter().len() as f64);
+    diagnostics.add_measurement(COLLISION_PAIRS, || collision_stats.pairs_tested as f64);
+    diagnostics.add_measurement(COLLISION_HITS, || collision_stats.hits as f64);
+}
+
+fn toggle_overlay(
+    actions: Res<ActionState>,
+    mut overlay_visibility: ResMut<OverlayVisibility>,
+    mut query: Query<&mut Visibility, With<PerformanceOverlayText>>,
+) {
+    if actions.just_pressed(Action::TogglePerformanceOverlay) {
+        overlay_visibility.0 = !overlay_visibility.0;
+        for mut visibility in query.iter_mut() {
+            *visibility = visibility_from(overlay_visibility.0);
+        }
+    }
+}
+
+fn update_overlay(
+    diagnostics: Res<DiagnosticsStore>,
+    mut query: Query<&mut Text, With<PerformanceOverlayText>>,
+) {
+    let smoothed = |id| {
+        diagnostics
+            .get(id)
+            .and_then(|diagnostic| diagnostic.smoothed())
+            .unwrap_or_default()
+    };
+    let values = [
+        format!("{:.0}\n", smoothed(FrameTimeDiagnosticsPlugin::FPS)),
+        format!(
+            "{:.2}ms\n",
+            smoothed(FrameTimeDiagnosticsPlugin::FRAME_TIME)
+        ),
+        format!("{:.0}\n", smoothed(BULLET_COUNT)),
+        format!("{:.0}\n", smoothed(ENEMY_COUNT)),
+        format!(
+            "{:.0} pairs, {:.0} hits\n",
+            smoothed(COLLISION_PAIRS),
+            smoothed(COLLISION_HITS)
+        ),
+    ];
+    for mut text in query.iter_mut() {
+        for (index, value) in values.iter().enumerate() {
+            text.sections[index * 2 + 1].value.clone_from(value);
+        }
+    }
+}
+
+fn toggle_fps_counter(actions: Res<ActionState>, mut settings: ResMut<Settings>) {
+    if actions.just_pressed(Action::ToggleFpsCounter) {
+        settings.show_fps = !settings.show_fps;
+    }
+}
+
+fn show_fps_counter(
+    settings: Res<Settings>,
+    mut query: Query<&mut Visibility, With<FpsCounterText>>,
+) {
+    for mut visibility in query.iter_mut() {
+        *visibility = visibility_from(settings.show_fps);
+    }
+}
+
+fn update_fps_counter(
+    diagnostics: Res<DiagnosticsStore>,
+    mut query: Query<&mut Text, With<FpsCounterText>>,
+) {
+    let smoothed = |id| {
+        diagnostics
+            .get(id)
+            .and_then(|diagnostic| diagnostic.smoothed())
+            .unwrap_or_default()
+    };
+    let value = format!(
+        "{:.0} FPS  {:.1}ms",
+        smoothed(FrameTimeDiagnosticsPlugin::FPS),
+        smoothed(FrameTimeDiagnosticsPlugin::FRAME_TIME)
+    );
+    for mut text in query.iter_mut() {
+        text.sections[0].value.clone_from(&value);
+    }
+}
@@ -0,0 +1,221 @@
+//! Difficulty, picked from the main menu before every run. It scales how
+//! tough enemies are, how often they spawn and how much they shoot.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::bindings::{Action, ActionState};
+use crate::locale::Locale;
+use crate::settings::Settings;
+use crate::state::AppState;
+use crate::theme::UiTheme;
+
+const BUTTON_WIDTH: f32 = 240.;
+const BUTTON_HEIGHT: f32 = 65.;
+
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    Lunatic,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Normal,
+        Difficulty::Hard,
+        Difficulty::Lunatic,
+    ];
+
+    /// Multiplier on the HP of enemies and bosses
+    pub fn enemy_hp(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.6,
+            Difficulty::Normal => 1.,
+            Difficulty::Hard => 1.5,
+            Difficulty::Lunatic => 2.,
+        }
+    }
+
+    /// Multiplier on how often enemies fire
+    pub fn fire_rate(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.6,
+            Difficulty::Normal => 1.,
+            Difficulty::Hard => 1.4,
+            Difficulty::Lunatic => 2.,
+        }
+    }
+
+    /// Multiplier on the speed of enemy bullets
+    pub fn bullet_speed(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.8,
+            Difficulty::Normal => 1.,
+            Difficulty::Hard => 1.2,
+            Difficulty::Lunatic => 1.5,
+        }
+    }
+
+    /// Multiplier on the time between regular enemy spawns
+    pub fn spawn_interval(self) -> f32 {
+        match self {
+            Difficulty::Easy => 1.3,
+            Difficulty::Normal => 1.,
+            Difficulty::Hard => 0.8,
+            Difficulty::Lunatic => 0.6,
+        }
+    }
+
+    /// Message naming the difficulty
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "difficulty-easy",
+            Difficulty::Normal => "difficulty-normal",
+            Difficulty::Hard => "difficulty-hard",
+            Difficulty::Lunatic => "difficulty-lunatic",
+        }
+    }
+
+    pub(crate) fn scale_hp(self, hp: u32) -> u32 {
+        ((hp as f32 * self.enemy_hp()).round() as u32).max(1)
+    }
+}
+
+pub struct DifficultyPlugin;
+
+impl Plugin for DifficultyPlugin {
+    fn build(&self, app: &mut App) {
+        // Starting on the one picked last time
+        let difficulty = app
+            .world
+            .get_resource::<Settings>()
+            .map(|settings| settings.difficulty)
+            .unwrap_or_default();
+        app.insert_resource(difficulty)
+            .add_systems(OnEnter(AppState::DifficultySelect), spawn_difficulty_select)
+            .add_systems(
+                OnExit(AppState::DifficultySelect),
+                despawn_difficulty_select,
+            )
+            .add_systems(
+                Update,
+                difficulty_buttons.run_if(in_state(AppState::DifficultySelect)),
+            );
+    }
+}
+
+#[derive(Component)]
+struct DifficultySelectScreen;
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+enum DifficultyButton {
+    Pick(Difficulty),
+    Back,
+}
+
+fn spawn_difficulty_select(mut commands: Commands, theme: Res<UiTheme>, locale: Res<Locale>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.),
+                    ..default()
+                },
+                ..default()
+            },
+            DifficultySelectScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(locale.text("difficulty", theme.title.clone()));
+            for option in Difficulty::ALL {
+                spawn_difficulty_button(
+                    parent,
+                    &theme,
+                    &locale,
+                    option.label(),
+                    DifficultyButton::Pick(option),
+                );
+            }
+            spawn_difficulty_button(
+                parent,
+                &theme,
+                &locale,
+                "button-back",
+                DifficultyButton::Back,
+            );
+        });
+}
+
+fn spawn_difficulty_button(
+    parent: &mut ChildBuilder,
+    theme: &UiTheme,
+    locale: &Locale,
+    label: &'static str,
+    button: DifficultyButton,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    min_width: Val::Px(BUTTON_WIDTH),
+                    height: Val::Px(BUTTON_HEIGHT),
+                    border: UiRect::all(Val::Px(5.)),
+                    padding: UiRect::horizontal(Val::Px(16.)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                border_color: BorderColor(Color::BLACK),
+                background_color: Color::WHITE.into(),
+                ..default()
+            },
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn(locale.text(label, theme.button.clone()));
+        });
+}
+
+fn despawn_difficulty_select(
+    mut commands: Commands,
+    query: Query<Entity, With<DifficultySelectScreen>>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn difficulty_buttons(
+    actions: Res<ActionState>,
+    interaction_query: Query<(&Interaction, &DifficultyButton), Changed<Interaction>>,
+    mut difficulty: ResMut<Difficulty>,
+    mut settings: ResMut<Settings>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if actions.just_pressed(Action::MenuBack) {
+        next_state.set(AppState::MainMenu);
+    }
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            DifficultyButton::Pick(option) => {
+                *difficulty = *option;
+                settings.difficulty = *option;
+                next_state.set(AppState::Running);
+            }
+            DifficultyButton::Back => next_state.set(AppState::MainMenu),
+        }
+    }
+}
@@ -0,0 +1,237 @@
+//! Enemy definitions: the stats of every kind of enemy, as RON assets under
+//! `assets/enemies/`. With the `dev` feature the files are watched, so edits
+//! apply to enemies spawned from then on without recompiling.
+//!
+//! Definitions are looked up by name through `EnemyBook`, which can apply the
+//! balance table for the difficulty along the way. One that is missing, failed
+//! to load or has values out of range falls back to the built-in grunt, so a
+//! broken file never stops enemies from spawning.
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::ecs::system::SystemParam;
+use bevy::log;
+use bevy::prelude::*;
+use bevy::utils::{BoxedFuture, HashMap};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::balance::{BalanceTable, EnemyBalanceTable};
+use crate::difficulty::Difficulty;
+use crate::sprites::SpriteId;
+
+/// The enemy the random spawner sends in, and stage waves unless they name another
+pub(crate) const GRUNT: &str = "grunt";
+/// Every enemy definition, by name
+const ENEMY_PATHS: [(&str, &str); 1] = [(GRUNT, "enemies/grunt.enemy.ron")];
+
+#[derive(Asset, TypePath, Deserialize, Clone, Debug)]
+pub struct EnemyDefinition {
+    pub hp: u32,
+    /// Width and height
+    pub size: (f32, f32),
+    /// Red, green and blue, from 0 to 1. `None` draws it in the palette's
+    /// enemy color.
+    #[serde(default)]
+    pub color: Option<(f32, f32, f32)>,
+    pub sprite: SpriteId,
+    /// How hard it is to knock back
+    pub mass: f32,
+    pub hover: HoverDefinition,
+    pub gun: GunDefinition,
+    /// Points for destroying it, on top of the points for every hit
+    pub score: u32,
+}
+
+/// Enemies fly down to `lower` and back up to `upper`, over and over, both
+/// measured down from the top of the playfield
+#[derive(Deserialize, Clone, Debug)]
+pub struct HoverDefinition {
+    /// Picked at random within the range, for every enemy
+    pub upper: (f32, f32),
+    pub lower: (f32, f32),
+    /// How far past its limits it may wander
+    pub margin: f32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct GunDefinition {
+    pub damage: u32,
+    /// Seconds between shots, before difficulty and rank. Every shot waits up
+    /// to twice as long, at random.
+    pub interval: f32,
+    /// Name of the bullet pattern it fires, a single bullet when left out
+    #[serde(default)]
+    pub pattern: Option<String>,
+}
+
+impl EnemyDefinition {
+    /// Stands in for a definition that is missing or failed to load
+    const FALLBACK: EnemyDefinition = EnemyDefinition {
+        hp: 10,
+        size: (50., 50.),
+        color: None,
+        sprite: SpriteId::Enemy,
+        mass: 1.,
+        hover: HoverDefinition {
+            upper: (0., 100.),
+            lower: (200., 300.),
+            margin: 50.,
+        },
+        gun: GunDefinition {
+            damage: 10,
+            interval: 1.,
+            pattern: None,
+        },
+        score: 50,
+    };
+
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.size.0, self.size.1)
+    }
+
+    /// Everything wrong with the definition, if anything
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let (width, height) = self.size;
+        if !(width > 0. && height > 0. && width.is_finite() && height.is_finite()) {
+            problems.push(format!(
+                "`size` must be above 0 both ways, got ({width}, {height})"
+            ));
+        }
+        if !(self.mass > 0. && self.mass.is_finite()) {
+            problems.push(format!("`mass` must be above 0, got {}", self.mass));
+        }
+        if !(self.gun.interval > 0. && self.gun.interval.is_finite()) {
+            problems.push(format!(
+                "`gun.interval` must be above 0, got {}",
+                self.gun.interval
+            ));
+        }
+        problems
+    }
+
+    /// Its own color, or `default` when it has none
+    pub fn color_or(&self, default: Color) -> Color {
+        self.color
+            .map_or(default, |(red, green, blue)| Color::rgb(red, green, blue))
+    }
+}
+
+/// Handles to every enemy definition, by name
+#[derive(Resource, Debug)]
+pub struct EnemyDefinitions(pub HashMap<String, Handle<EnemyDefinition>>);
+
+impl FromWorld for EnemyDefinitions {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        Self(
+            ENEMY_PATHS
+                .into_iter()
+                .map(|(name, path)| (name.to_string(), asset_server.load(path)))
+                .collect(),
+        )
+    }
+}
+
+/// Looks up enemy definitions by name
+#[derive(SystemParam)]
+pub struct EnemyBook<'w> {
+    definitions: Res<'w, EnemyDefinitions>,
+    assets: Res<'w, Assets<EnemyDefinition>>,
+    balance: Res<'w, EnemyBalanceTable>,
+    balance_tables: Res<'w, Assets<BalanceTable>>,
+}
+
+impl EnemyBook<'_> {
+    pub fn get(&self, name: &str) -> &EnemyDefinition {
+        self.definitions
+            .0
+            .get(name)
+            .and_then(|handle| self.assets.get(handle))
+            .unwrap_or(&EnemyDefinition::FALLBACK)
+    }
+
+    /// The definition called `name`, with its row of the balance table for
+    /// `difficulty` applied
+    pub fn balanced(&self, name: &str, difficulty: Difficulty) -> EnemyDefinition {
+        let mut definition = self.get(name).clone();
+        let balance = self
+            .balance_tables
+            .get(&self.balance.0)
+            .and_then(|table| table.get(name, difficulty));
+        if let Some(balance) = balance {
+            if let Some(multiplier) = balance.hp_multiplier {
+                definition.hp = ((definition.hp as f32 * multiplier).round() as u32).max(1);
+            }
+            if let Some(score) = balance.score {
+                definition.score = score;
+            }
+        }
+        definition
+    }
+}
+
+#[derive(Default)]
+pub struct EnemyDefinitionLoader;
+
+#[derive(Debug, Error)]
+pub enum EnemyDefinitionLoaderError {
+    #[error("could not read the enemy definition: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse the enemy definition: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("invalid enemy definition: {}", .0.join("; "))]
+    Invalid(Vec<String>),
+}
+
+impl AssetLoader for EnemyDefinitionLoader {
+    type Asset = EnemyDefinition;
+    type Settings = ();
+    type Error = EnemyDefinitionLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<EnemyDefinition, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let definition: EnemyDefinition = ron::de::from_bytes(&bytes)?;
+            let problems = definition.problems();
+            if !problems.is_empty() {
+                return Err(EnemyDefinitionLoaderError::Invalid(problems));
+            }
+            Ok(definition)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["enemy.ron"]
+    }
+}
+
+pub struct EnemiesPlugin;
+
+impl Plugin for EnemiesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<EnemyDefinition>()
+            .init_asset_loader::<EnemyDefinitionLoader>()
+            .init_resource::<EnemyDefinitions>()
+            .add_systems(Update, log_enemy_reloads);
+    }
+}
+
+fn log_enemy_reloads(
+    mut events: EventReader<AssetEvent<EnemyDefinition>>,
+    asset_server: Res<AssetServer>,
+) {
+    for event in events.read() {
+        if let AssetEvent::Modified { id } = event {
+            let path = asset_server.get_path(*id);
+            log::info!("Reloaded enemy definition {path:?}");
+        }
+    }
+}
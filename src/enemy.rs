@@ -0,0 +1,414 @@
+//! Enemies: spawning them, hovering them up and down the top of the
+//! playfield, knocking them back when hit and firing their guns. What each kind
+//! of enemy is like comes from its definition in `enemies`.
+
+use std::time::Duration;
+
+use bevy::log;
+use bevy::prelude::*;
+use bevy::sprite::MaterialMesh2dBundle;
+use rand::Rng;
+
+use crate::audio::{PlaySfx, SfxId};
+use crate::beat::{BeatClock, FireOnBeat};
+use crate::boss::{advance_boss_encounter, Retreating};
+use crate::bullet::{create_bullet, move_bullets, DespawnOutOfBounds, Gun, Hostility};
+use crate::collision::{not_in_hitstop, Collider, ColliderShape, HitFlash};
+use crate::config::GameConfig;
+use crate::difficulty::Difficulty;
+use crate::enemies::{EnemyBook, EnemyDefinition, GRUNT};
+use crate::glow::GlowMaterial;
+use crate::mode::{endless_mode, stage_mode, EndlessRamp};
+use crate::muzzle_flash::{spawn_muzzle_flash, ENEMY_GUN_FLASH};
+use crate::palette::{OwnColor, Palette};
+use crate::patterns::{BulletPattern, PatternBook};
+use crate::rank::Rank;
+use crate::sprites::SpriteArt;
+use crate::state::AppState;
+use crate::{GameRng, GameSet, HitPoints, Playfield};
+
+const KNOCKBACK_DAMPING: f32 = 8.;
+
+#[derive(Component)]
+pub struct Enemy;
+
+/// Points for destroying an enemy, on top of the points for every hit
+#[derive(Component)]
+pub(crate) struct ScoreValue(pub(crate) u32);
+
+/// What kind of enemy it is, for the statistics: the name of its definition,
+/// or `BOSS_KIND` and `MID_BOSS_KIND` for bosses
+#[derive(Component, Clone, Debug)]
+pub(crate) struct EnemyKind(pub(crate) String);
+
+#[derive(Component)]
+pub(crate) struct HoverBehaviour {
+    pub(crate) upper_limit_base: f32,
+    pub(crate) upper_limit_margin: f32,
+    pub(crate) lower_limit_base: f32,
+    pub(crate) lower_limit_margin: f32,
+}
+
+#[derive(Component)]
+pub(crate) struct Direction(pub(crate) Vec3);
+
+/// Resistance to knockback: the applied impulse is divided by it.
+#[derive(Component)]
+pub(crate) struct Mass(pub(crate) f32);
+
+/// Velocity picked up from knockback, decaying back to zero over time.
+#[derive(Component, Default)]
+pub(crate) struct KnockbackVelocity(pub(crate) Vec3);
+
+#[derive(Event)]
+pub(crate) struct EnemyKilledEvent {
+    /// Where the enemy was
+    pub(crate) position: Vec2,
+    /// Points for destroying it
+    pub(crate) score: u32,
+    pub(crate) kind: Option<String>,
+}
+
+/// Asks for an enemy to be spawned. Every enemy the game sends in goes through
+/// it, and other plugins can send their own. Spawned within the fixed tick
+/// it is sent on, when sent before `GameSet::Movement`.
+#[derive(Event, Clone, Debug)]
+pub struct SpawnEnemy {
+    /// Name of its definition, falling back to the grunt when there is no
+    /// such enemy
+    pub enemy: String,
+    /// Where it appears, in world space
+    pub position: Vec2,
+    pub overrides: EnemyOverrides,
+}
+
+impl SpawnEnemy {
+    pub fn new(enemy: impl Into<String>, position: Vec2) -> Self {
+        Self {
+            enemy: enemy.into(),
+            position,
+            overrides: default(),
+        }
+    }
+
+    pub fn with_overrides(mut self, overrides: EnemyOverrides) -> Self {
+        self.overrides = overrides;
+        self
+    }
+}
+
+/// Stats taking the place of those in the enemy's definition
+#[derive(Clone, Debug, Default)]
+pub struct EnemyOverrides {
+    /// Before difficulty
+    pub hp: Option<u32>,
+    /// Name of the bullet pattern its gun fires
+    pub pattern: Option<String>,
+    pub score: Option<u32>,
+    pub color: Option<Color>,
+}
+
+impl EnemyOverrides {
+    fn apply(&self, mut definition: EnemyDefinition) -> EnemyDefinition {
+        if let Some(hp) = self.hp {
+            definition.hp = hp;
+        }
+        if let Some(pattern) = &self.pattern {
+            definition.gun.pattern = Some(pattern.clone());
+        }
+        if let Some(score) = self.score {
+            definition.score = score;
+        }
+        if let Some(color) = self.color {
+            definition.color = Some((color.r(), color.g(), color.b()));
+        }
+        definition
+    }
+}
+
+#[derive(Resource)]
+struct EnemySpawnTimer(Timer);
+
+impl Default for EnemySpawnTimer {
+    fn default() -> Self {
+        Self(Timer::from_seconds(2., TimerMode::Once))
+    }
+}
+
+pub struct EnemyPlugin;
+
+impl Plugin for EnemyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EnemySpawnTimer>()
+            .add_event::<EnemyKilledEvent>()
+            .add_systems(OnEnter(AppState::Running), reset_enemy_spawn_timer)
+            .add_event::<SpawnEnemy>()
+            .add_systems(
+                FixedUpdate,
+                (
+                    advance_boss_encounter.run_if(stage_mode),
+                    spawn_enemies.run_if(endless_mode),
+                    spawn_requested_enemies,
+                    set_enemies_direction,
+                    apply_enemy_velocity,
+                    apply_knockback,
+                    enemy_shots,
+                )
+                    .chain()
+                    .run_if(not_in_hitstop)
+                    .after(move_bullets)
+                    .in_set(GameSet::Movement),
+            );
+    }
+}
+
+/// Runs start on the same timer whatever the last one left it at, so they can
+/// be replayed
+fn reset_enemy_spawn_timer(mut enemy_spawn_timer: ResMut<EnemySpawnTimer>) {
+    *enemy_spawn_timer = EnemySpawnTimer::default();
+}
+
+fn spawn_enemies(
+    time: Res<Time>,
+    mut enemy_spawn_timer: ResMut<EnemySpawnTimer>,
+    mut spawn_events: EventWriter<SpawnEnemy>,
+    mut rng: ResMut<GameRng>,
+    playfield: Res<Playfield>,
+    difficulty: Res<Difficulty>,
+    ramp: Res<EndlessRamp>,
+    rank: Res<Rank>,
+) {
+    if enemy_spawn_timer.0.tick(time.delta()).just_finished() {
+        let half_width = playfield.rect.half_size().x * 0.8; // * 0.8 to not spawn enemies at the very edge
+        let random_x = playfield.rect.center().x + (rng.0.gen::<f32>() * 2. - 1.) * half_width;
+        let spawn_point = Vec2::new(random_x, playfield.rect.max.y);
+        log::info!(
+            "Enemy spawn timer finished. Spawning enemy at {:?}.",
+            spawn_point
+        );
+        spawn_events.send(SpawnEnemy::new(GRUNT, spawn_point));
+        enemy_spawn_timer.0.set_duration(Duration::from_secs_f32(
+            (1. + rng.0.gen::<f32>())
+                * difficulty.spawn_interval()
+                * ramp.spawn_interval()
+                * rank.spawn_interval(),
+        ));
+        enemy_spawn_timer.0.reset();
+    }
+}
+
+pub(crate) fn spawn_requested_enemies(
+    mut commands: Commands,
+    mut events: EventReader<SpawnEnemy>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    palette: Res<Palette>,
+    enemies: EnemyBook,
+    mut rng: ResMut<GameRng>,
+    playfield: Res<Playfield>,
+    difficulty: Res<Difficulty>,
+) {
+    for event in events.read() {
+        let enemy = spawn_enemy(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            &palette,
+            &event
+                .overrides
+                .apply(enemies.balanced(&event.enemy, *difficulty)),
+            &mut rng,
+            &playfield,
+            *difficulty,
+            event.position.extend(0.),
+        );
+        commands
+            .entity(enemy)
+            .insert(EnemyKind(event.enemy.clone()));
+    }
+}
+
+pub(crate) fn spawn_enemy(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    palette: &Palette,
+    definition: &EnemyDefinition,
+    rng: &mut GameRng,
+    playfield: &Playfield,
+    difficulty: Difficulty,
+    position: Vec3,
+) -> Entity {
+    let top = playfield.rect.max.y;
+    let size = definition.size();
+    let color = definition.color_or(palette.enemy);
+    let hover = &definition.hover;
+    let pick = |from: f32, to: f32, t: f32| from + (to - from) * t;
+    let mut enemy = commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes.add(shape::Quad::new(size).into()).into(),
+            material: materials.add(ColorMaterial::from(color)),
+            transform: Transform::from_translation(position),
+            ..default()
+        },
+        Enemy,
+        SpriteArt(definition.sprite),
+        HitFlash::new(color, palette.enemy_hit),
+        Collider,
+        ColliderShape::Aabb {
+            half_extents: size / 2.,
+        },
+        Gun {
+            cooldown_timer: Timer::from_seconds(
+                (1. + rng.0.gen::<f32>()) * definition.gun.interval / difficulty.fire_rate(),
+                TimerMode::Once,
+            ),
+            interval: definition.gun.interval,
+            pattern: definition.gun.pattern.clone(),
+            damage: definition.gun.damage,
+            knockback: None,
+            flash: ENEMY_GUN_FLASH,
+        },
+        Mass(definition.mass),
+        KnockbackVelocity::default(),
+        // Enemies spawn on the top edge and may hover slightly above it
+        DespawnOutOfBounds {
+            margin: size.y * 2.,
+        },
+        HitPoints(difficulty.scale_hp(definition.hp)),
+        ScoreValue(definition.score),
+        Hostility::Hostile,
+        Direction(Vec3::ZERO),
+        HoverBehaviour {
+            // Upper limits are picked from the bottom of the range up
+            upper_limit_base: top - pick(hover.upper.1, hover.upper.0, rng.0.gen()),
+            upper_limit_margin: hover.margin,
+            lower_limit_base: top - pick(hover.lower.0, hover.lower.1, rng.0.gen()),
+            lower_limit_margin: hover.margin,
+        },
+    ));
+    if definition.color.is_some() {
+        enemy.insert(OwnColor);
+    }
+    enemy.id()
+}
+
+pub(crate) fn set_enemies_direction(
+    mut query: Query<
+        (&Transform, &mut Direction, &HoverBehaviour),
+        (With<Enemy>, Without<Retreating>),
+    >,
+    mut rng: ResMut<GameRng>,
+) {
+    for (transform, mut direction, hover_behaviour) in query.iter_mut() {
+        if transform.translation.y
+            < hover_behaviour.lower_limit_base
+                - rng.0.gen::<f32>() * hover_behaviour.lower_limit_margin
+        {
+            direction.0 = Vec3::new(0., 1., 0.);
+        } else if transform.translation.y
+            > hover_behaviour.upper_limit_base
+                + rng.0.gen::<f32>() * hover_behaviour.upper_limit_margin
+        {
+            direction.0 = Vec3::new(0., -1., 0.);
+        }
+    }
+}
+
+fn apply_enemy_velocity(
+    time: Res<Time>,
+    mut query: Query<(&mut Transform, &Direction), With<Enemy>>,
+) {
+    for (mut transform, direction) in query.iter_mut() {
+        transform.translation += direction.0 * time.delta_seconds() * 100.;
+    }
+}
+
+fn apply_knockback(
+    time: Res<Time>,
+    mut query: Query<(&mut Transform, &mut KnockbackVelocity), With<Enemy>>,
+) {
+    for (mut transform, mut knockback_velocity) in query.iter_mut() {
+        transform.translation += knockback_velocity.0 * time.delta_seconds();
+        knockback_velocity.0 *= (-KNOCKBACK_DAMPING * time.delta_seconds()).exp();
+    }
+}
+
+pub(crate) fn enemy_shots(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &Transform, &mut Gun, Has<FireOnBeat>), With<Enemy>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<GlowMaterial>>,
+    palette: Res<Palette>,
+    mut rng: ResMut<GameRng>,
+    difficulty: Res<Difficulty>,
+    rank: Res<Rank>,
+    patterns: PatternBook,
+    beat_clock: Option<Res<BeatClock>>,
+    config: Res<GameConfig>,
+    mut sfx_events: EventWriter<PlaySfx>,
+) {
+    let on_beat = beat_clock.is_some_and(|clock| clock.running());
+    for (entity, transform, mut gun, fires_on_beat) in query.iter_mut() {
+        // Left to `fire_on_beat` while there is a beat to follow
+        if fires_on_beat && on_beat {
+            continue;
+        }
+        if gun.cooldown_timer.tick(time.delta()).just_finished() {
+            fire_enemy_bullet(
+                &mut commands,
+                &mut meshes,
+                &mut materials,
+                &palette,
+                &config,
+                &mut sfx_events,
+                entity,
+                transform,
+                &gun,
+                patterns.get(gun.pattern.as_deref()),
+                difficulty.bullet_speed() * rank.bullet_speed(),
+            );
+            let seconds = (1. + rng.0.gen::<f32>()) * gun.interval
+                / (difficulty.fire_rate() * rank.fire_rate());
+            gun.cooldown_timer
+                .set_duration(Duration::from_secs_f32(seconds));
+            gun.cooldown_timer.reset();
+        }
+    }
+}
+
+/// Fires a volley of `pattern` from an enemy, `speed_multiplier` times as
+/// fast as the base bullet speed
+pub(crate) fn fire_enemy_bullet(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<GlowMaterial>>,
+    palette: &Palette,
+    config: &GameConfig,
+    sfx_events: &mut EventWriter<PlaySfx>,
+    shooter: Entity,
+    transform: &Transform,
+    gun: &Gun,
+    pattern: &BulletPattern,
+    speed_multiplier: f32,
+) {
+    let muzzle = Vec2::new(0., -50.);
+    spawn_muzzle_flash(commands, shooter, &gun.flash, muzzle);
+    for velocity in pattern.velocities(config.enemy_bullet_speed * speed_multiplier) {
+        commands.spawn(create_bullet(
+            transform.translation + muzzle.extend(0.),
+            meshes,
+            materials,
+            palette,
+            config.bullet_radius,
+            velocity,
+            gun.damage,
+            true,
+        ));
+    }
+    sfx_events.send(PlaySfx::at(
+        SfxId::EnemyShot,
+        transform.translation.truncate(),
+    ));
+}
@@ -0,0 +1,107 @@
+//! The player's engine exhaust, a flickering flame behind the ship. It is a
+//! child of the ship, and `move_player` sets its thrust every tick: the flame
+//! burns long while the ship climbs, idles in between and shrinks right down
+//! while focused or backing off.
+
+use bevy::prelude::*;
+use bevy::sprite::MaterialMesh2dBundle;
+use rand::Rng;
+
+use crate::config::GameConfig;
+use crate::glow::{BlendMode, GlowMaterial};
+use crate::player::Player;
+use crate::state::gameplay_active;
+
+const FLAME_COLOR: Color = Color::rgb(1., 0.55, 0.15);
+/// Past full brightness, so the flame glows under bloom
+const FLAME_EMISSIVE: f32 = 2.;
+const FLAME_WIDTH: f32 = 14.;
+/// Length at full thrust
+const MAX_FLAME_LENGTH: f32 = 40.;
+/// Thrust while neither climbing nor backing off
+const IDLE_THRUST: f32 = 0.6;
+const FOCUSED_THRUST: f32 = 0.2;
+/// How quickly the flame catches up with the thrust, per second
+const FLAME_RESPONSE: f32 = 12.;
+/// Random change in length from frame to frame, as a fraction of it
+const FLICKER: f32 = 0.15;
+/// Behind the ship, but in front of the background
+const FLAME_Z: f32 = -0.01;
+
+#[derive(Component, Debug)]
+pub(crate) struct Exhaust {
+    /// From 0 to 1
+    thrust: f32,
+    /// Where the flame's length is at, easing towards the thrust
+    length: f32,
+}
+
+impl Exhaust {
+    /// Follows the ship's movement, a positive `movement.y` being a climb
+    pub(crate) fn set_thrust(&mut self, movement: Vec2, focused: bool) {
+        self.thrust = if focused {
+            FOCUSED_THRUST
+        } else {
+            IDLE_THRUST + movement.y.clamp(-1., 1.) * (1. - IDLE_THRUST)
+        };
+    }
+}
+
+pub struct ExhaustPlugin;
+
+impl Plugin for ExhaustPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (spawn_exhaust, animate_exhaust.run_if(gameplay_active)).chain(),
+        );
+    }
+}
+
+fn spawn_exhaust(
+    mut commands: Commands,
+    players: Query<Entity, Added<Player>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<GlowMaterial>>,
+    config: Res<GameConfig>,
+) {
+    let ship_length = config.player.size().y;
+    for player in players.iter() {
+        commands.entity(player).with_children(|parent| {
+            parent.spawn((
+                MaterialMesh2dBundle {
+                    mesh: meshes.add(shape::Quad::new(Vec2::ONE).into()).into(),
+                    material: materials.add(GlowMaterial::new(
+                        FLAME_COLOR * FLAME_EMISSIVE,
+                        BlendMode::Additive,
+                    )),
+                    transform: Transform::from_xyz(0., -ship_length / 2., FLAME_Z)
+                        .with_scale(Vec3::ZERO),
+                    ..default()
+                },
+                Exhaust {
+                    thrust: IDLE_THRUST,
+                    length: 0.,
+                },
+            ));
+        });
+    }
+}
+
+/// Hangs the flame off the back of the ship, as long as its thrust calls for
+fn animate_exhaust(
+    time: Res<Time>,
+    config: Res<GameConfig>,
+    mut query: Query<(&mut Exhaust, &mut Transform)>,
+) {
+    let ship_length = config.player.size().y;
+    let mut rng = rand::thread_rng();
+    for (mut exhaust, mut transform) in query.iter_mut() {
+        let target = exhaust.thrust * MAX_FLAME_LENGTH;
+        exhaust.length +=
+            (target - exhaust.length) * (FLAME_RESPONSE * time.delta_seconds()).min(1.);
+        let length = exhaust.length * (1. + rng.gen_range(-FLICKER..FLICKER));
+        transform.scale = Vec3::new(FLAME_WIDTH, length, 1.);
+        transform.translation.y = -(ship_length + length) / 2.;
+    }
+}
@@ -0,0 +1,73 @@
+//! Game speed, picked on the accessibility screen to learn the patterns at a
+//! slower pace. Below full speed, virtual time and the fixed timestep with it
+//! run slower, so the whole simulation does, while the menus stay at full
+//! speed. Every point scored is scaled down by as much, so slowed runs can't
+//! top the high scores.
+//!
+//! A run keeps the speed it started at, and replays play back at the speed
+//! they were recorded at. The music doesn't slow down, so guns following its
+//! beats go back to their cooldown below full speed, see `beat`.
+
+use bevy::log;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::replay::ReplayPlayback;
+use crate::settings::Settings;
+use crate::state::AppState;
+
+/// The speeds to pick from, in order
+pub(crate) const GAME_SPEEDS: [f32; 6] = [1., 0.9, 0.8, 0.7, 0.6, 0.5];
+const MIN_GAME_SPEED: f32 = 0.5;
+
+/// How fast the current run goes, 1 being full speed
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GameSpeed(pub f32);
+
+impl Default for GameSpeed {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
+
+impl GameSpeed {
+    /// `points` scaled down to the speed
+    pub(crate) fn points(self, points: u32) -> u32 {
+        (points as f32 * self.0).round() as u32
+    }
+}
+
+pub struct GameSpeedPlugin;
+
+impl Plugin for GameSpeedPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameSpeed>()
+            .add_systems(OnEnter(AppState::Running), start_at_game_speed)
+            .add_systems(OnExit(AppState::Running), back_to_full_speed);
+    }
+}
+
+/// Run condition for what only keeps up with the game at full speed
+pub(crate) fn full_game_speed(speed: Res<GameSpeed>) -> bool {
+    speed.0 == 1.
+}
+
+/// Any speed out of range, from an edited file, is brought back in
+fn start_at_game_speed(
+    settings: Res<Settings>,
+    playback: Res<ReplayPlayback>,
+    mut speed: ResMut<GameSpeed>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    *speed = playback
+        .game_speed()
+        .unwrap_or(GameSpeed(settings.game_speed.clamp(MIN_GAME_SPEED, 1.)));
+    if speed.0 != 1. {
+        log::info!("Running at {}% speed", (speed.0 * 100.).round());
+    }
+    time.set_relative_speed(speed.0);
+}
+
+fn back_to_full_speed(mut time: ResMut<Time<Virtual>>) {
+    time.set_relative_speed(1.);
+}
@@ -0,0 +1,154 @@
+//! Bloom, behind the bloom setting. Turning it on switches the camera to HDR
+//! and adds bloom, which makes anything drawn past full brightness glow, like
+//! the bullets.
+//!
+//! Bullets and particles are drawn with `GlowMaterial`, a flat color that can
+//! be blended additively instead of over what is behind it. Overlapping
+//! additive shapes add up to something brighter, so a dense pattern reads as
+//! a bright mass rather than a muddy one. It can also cut its mesh down to a
+//! smaller shape or ring it, going by the mesh's UVs, to tell things apart
+//! by more than their color.
+
+use bevy::core_pipeline::bloom::BloomSettings;
+use bevy::prelude::*;
+use bevy::render::mesh::MeshVertexBufferLayout;
+use bevy::render::render_resource::{
+    AsBindGroup, BlendComponent, BlendFactor, BlendOperation, BlendState, RenderPipelineDescriptor,
+    ShaderRef, SpecializedMeshPipelineError,
+};
+use bevy::sprite::{Material2d, Material2dKey, Material2dPlugin};
+
+use crate::settings::Settings;
+
+const GLOW_SHADER_PATH: &str = "shaders/glow.wgsl";
+
+/// Adds the color to what is behind it, scaled by its alpha
+const ADDITIVE_BLENDING: BlendState = BlendState {
+    color: BlendComponent {
+        src_factor: BlendFactor::SrcAlpha,
+        dst_factor: BlendFactor::One,
+        operation: BlendOperation::Add,
+    },
+    alpha: BlendComponent::OVER,
+};
+
+/// How a `GlowMaterial` is drawn over what is behind it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// Covers what is behind it, like any other shape
+    #[default]
+    Alpha,
+    /// Brightens what is behind it
+    Additive,
+}
+
+/// What part of its mesh a `GlowMaterial` covers
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum GlowShape {
+    /// All of it
+    #[default]
+    Full,
+    /// The diamond fitting inside it
+    Diamond,
+    /// All of it, with a ring in the outline color around the edge
+    Ringed,
+}
+
+#[derive(Asset, TypePath, AsBindGroup, Clone, Debug)]
+#[bind_group_data(GlowMaterialKey)]
+pub struct GlowMaterial {
+    #[uniform(0)]
+    pub color: Color,
+    /// Color of the ring of `GlowShape::Ringed`
+    #[uniform(1)]
+    pub outline: Color,
+    pub blend: BlendMode,
+    pub shape: GlowShape,
+}
+
+impl GlowMaterial {
+    pub fn new(color: Color, blend: BlendMode) -> Self {
+        Self {
+            color,
+            outline: Color::NONE,
+            blend,
+            shape: GlowShape::Full,
+        }
+    }
+}
+
+/// Materials that blend differently or cover other shapes need their own
+/// pipelines
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GlowMaterialKey {
+    blend: BlendMode,
+    shape: GlowShape,
+}
+
+impl From<&GlowMaterial> for GlowMaterialKey {
+    fn from(material: &GlowMaterial) -> Self {
+        Self {
+            blend: material.blend,
+            shape: material.shape,
+        }
+    }
+}
+
+impl Material2d for GlowMaterial {
+    fn fragment_shader() -> ShaderRef {
+        GLOW_SHADER_PATH.into()
+    }
+
+    fn specialize(
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayout,
+        key: Material2dKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            match key.bind_group_data.shape {
+                GlowShape::Full => {}
+                GlowShape::Diamond => fragment.shader_defs.push("DIAMOND".into()),
+                GlowShape::Ringed => fragment.shader_defs.push("RINGED".into()),
+            }
+        }
+        if key.bind_group_data.blend == BlendMode::Additive {
+            let targets = descriptor
+                .fragment
+                .iter_mut()
+                .flat_map(|fragment| fragment.targets.iter_mut().flatten());
+            for target in targets {
+                target.blend = Some(ADDITIVE_BLENDING);
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct GlowPlugin;
+
+impl Plugin for GlowPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(Material2dPlugin::<GlowMaterial>::default())
+            .add_systems(
+                Update,
+                apply_bloom_setting.run_if(resource_changed::<Settings>()),
+            );
+    }
+}
+
+fn apply_bloom_setting(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    mut cameras: Query<(Entity, &mut Camera, Has<BloomSettings>)>,
+) {
+    for (entity, mut camera, has_bloom) in cameras.iter_mut() {
+        if camera.hdr != settings.bloom {
+            camera.hdr = settings.bloom;
+        }
+        if settings.bloom && !has_bloom {
+            commands.entity(entity).insert(BloomSettings::NATURAL);
+        } else if !settings.bloom && has_bloom {
+            commands.entity(entity).remove::<BloomSettings>();
+        }
+    }
+}
@@ -0,0 +1,552 @@
+//! The high score table: the ten best runs of every game mode, with who
+//! played them, on what difficulty and when. It is kept in the data
+//! directory, see `storage`, and has its own screen off the main menu.
+//!
+//! Runs are recorded as they end, under the name entered last. One that made
+//! the table asks for a name on the game over screen, and keeps whatever is
+//! typed there. Practice runs don't count, and the daily challenge only keeps
+//! the runs of the day.
+//!
+//! Best scores kept from before the table, one per file in the working
+//! directory, are brought into it on the first launch without one.
+
+use bevy::app::AppExit;
+use bevy::log;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use fluent::fluent_args;
+use serde::{Deserialize, Serialize};
+
+use crate::bindings::{Action, ActionState};
+use crate::difficulty::Difficulty;
+use crate::locale::Locale;
+use crate::mode::{today, GameMode};
+use crate::replay::watching_replay;
+use crate::score::Score;
+use crate::state::AppState;
+use crate::storage;
+use crate::theme::UiTheme;
+
+const HIGH_SCORES_FILE: &str = "high_scores.ron";
+const TABLE_SIZE: usize = 10;
+const MAX_NAME_LENGTH: usize = 12;
+const DEFAULT_NAME: &str = "PLAYER";
+/// Where the best score of each mode was kept before the table. The stage and
+/// endless modes shared theirs.
+const LEGACY_HIGH_SCORE_FILES: [(&str, &[GameMode]); 4] = [
+    ("high_score.txt", &[GameMode::Stages, GameMode::Endless]),
+    ("time_attack_high_score.txt", &[GameMode::TimeAttack]),
+    ("daily_high_score.txt", &[GameMode::Daily]),
+    ("caravan_high_score.txt", &[GameMode::Caravan]),
+];
+/// Widths of the rank, name, score, difficulty and date columns
+const COLUMN_WIDTHS: [f32; 5] = [40., 180., 110., 110., 120.];
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HighScoreEntry {
+    pub name: String,
+    pub score: u32,
+    pub difficulty: Difficulty,
+    /// Days since the Unix epoch
+    pub day: u64,
+}
+
+/// The best runs of every mode, best first
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+pub struct HighScores {
+    tables: HashMap<GameMode, Vec<HighScoreEntry>>,
+    /// Runs are recorded under it until another is entered
+    last_name: String,
+}
+
+impl HighScores {
+    /// Starts out with the scores from before the table when there is no file
+    /// yet, and empty when there is an unreadable one
+    fn load() -> Self {
+        let contents = match storage::read(HIGH_SCORES_FILE) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                return Self::import_legacy();
+            }
+            Err(error) => {
+                log::warn!("Could not read the high scores: {error}");
+                return Self::default();
+            }
+        };
+        let mut high_scores: Self = ron::from_str(&contents).unwrap_or_else(|error| {
+            log::warn!("Could not parse the high scores: {error}");
+            Self::default()
+        });
+        high_scores.forget_past_dailies();
+        high_scores
+    }
+
+    /// Records the best scores kept before the table, and saves it right away
+    /// so they are only brought in once. The daily one only counts on its day.
+    fn import_legacy() -> Self {
+        let mut high_scores = Self::default();
+        for (file, modes) in LEGACY_HIGH_SCORE_FILES {
+            let Ok(contents) = std::fs::read_to_string(file) else {
+                continue;
+            };
+            let contents = contents.trim();
+            let (day, score) = match modes {
+                [GameMode::Daily] => match contents.split_once(' ') {
+                    Some((day, score)) => (day.parse().ok(), score),
+                    None => (None, contents),
+                },
+                _ => (Some(today()), contents),
+            };
+            let (Some(day), Ok(score)) = (day, score.parse()) else {
+                log::warn!("Could not parse the high score in {file}");
+                continue;
+            };
+            for &mode in modes {
+                high_scores.record(
+                    mode,
+                    HighScoreEntry {
+                        name: DEFAULT_NAME.to_string(),
+                        score,
+                        difficulty: Difficulty::default(),
+                        day,
+                    },
+                );
+            }
+        }
+        high_scores.forget_past_dailies();
+        if high_scores.tables.values().any(|table| !table.is_empty()) {
+            log::info!("Brought the high scores over from before the table");
+            high_scores.save();
+        }
+        high_scores
+    }
+
+    fn save(&self) {
+        let saved = ron::ser::to_string_pretty(self, default())
+            .map_err(|error| error.to_string())
+            .and_then(|contents| {
+                storage::write(HIGH_SCORES_FILE, &contents).map_err(|error| error.to_string())
+            });
+        if let Err(error) = saved {
+            log::warn!("Could not save the high scores: {error}");
+        }
+    }
+
+    fn forget_past_dailies(&mut self) {
+        if let Some(table) = self.tables.get_mut(&GameMode::Daily) {
+            let today = today();
+            table.retain(|entry| entry.day == today);
+        }
+    }
+
+    /// The table runs of `mode` are recorded in. Practice shares the stage
+    /// mode's, without adding to it.
+    pub fn table(&self, mode: GameMode) -> &[HighScoreEntry] {
+        let mode = match mode {
+            GameMode::Practice => GameMode::Stages,
+            mode => mode,
+        };
+        self.tables.get(&mode).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn best(&self, mode: GameMode) -> u32 {
+        self.table(mode).first().map_or(0, |entry| entry.score)
+    }
+
+    /// Adds a run to the table of its mode if it makes it, returning its rank
+    /// from 0
+    fn record(&mut self, mode: GameMode, entry: HighScoreEntry) -> Option<usize> {
+        if mode == GameMode::Practice || entry.score == 0 {
+            return None;
+        }
+        self.forget_past_dailies();
+        let table = self.tables.entry(mode).or_default();
+        // Ties go to whoever got there first
+        let rank = table.partition_point(|other| other.score >= entry.score);
+        if rank >= TABLE_SIZE {
+            return None;
+        }
+        table.insert(rank, entry);
+        table.truncate(TABLE_SIZE);
+        Some(rank)
+    }
+
+    fn name(&self) -> &str {
+        if self.last_name.is_empty() {
+            DEFAULT_NAME
+        } else {
+            &self.last_name
+        }
+    }
+}
+
+/// Where the run that just ended landed in the table, while its name can
+/// still be changed
+#[derive(Resource, Default, Debug)]
+pub(crate) struct NameEntry(Option<(GameMode, usize)>);
+
+impl NameEntry {
+    /// Line asking for the name, when the run made the table
+    pub(crate) fn prompt(&self, high_scores: &HighScores, locale: &Locale) -> Option<String> {
+        let (mode, rank) = self.0?;
+        let entry = high_scores.tables.get(&mode)?.get(rank)?;
+        Some(name_entry_text(locale, rank, &entry.name))
+    }
+}
+
+/// Run condition for hotkeys that would go off while a name is typed
+pub(crate) fn not_entering_name(entry: Res<NameEntry>) -> bool {
+    entry.0.is_none()
+}
+
+/// Text showing the name being typed
+#[derive(Component)]
+pub(crate) struct NameEntryText;
+
+#[derive(Component)]
+struct HighScoresScreen;
+
+/// Holds the rows of the table shown
+#[derive(Component)]
+struct HighScoreRows;
+
+/// Game mode whose table is shown
+#[derive(Resource, Default)]
+struct ShownTable(usize);
+
+impl ShownTable {
+    fn mode(&self) -> (GameMode, &'static str) {
+        GameMode::SELECTABLE[self.0 % GameMode::SELECTABLE.len()]
+    }
+}
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+enum HighScoresButton {
+    /// Cycles through the modes
+    Mode,
+    Back,
+}
+
+pub struct HighScoresPlugin;
+
+impl Plugin for HighScoresPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HighScores::load())
+            .init_resource::<NameEntry>()
+            .init_resource::<ShownTable>()
+            .add_systems(
+                OnExit(AppState::Running),
+                record_run.run_if(not(watching_replay)),
+            )
+            .add_systems(Update, type_name.run_if(in_state(AppState::GameOver)))
+            .add_systems(OnExit(AppState::GameOver), finish_name_entry)
+            // Quitting the app never leaves `Running`, so record on the way out too
+            .add_systems(
+                Last,
+                (
+                    record_run.run_if(in_state(AppState::Running).and_then(not(watching_replay))),
+                    save_high_scores,
+                )
+                    .chain()
+                    .run_if(on_event::<AppExit>()),
+            )
+            .add_systems(OnEnter(AppState::HighScores), spawn_high_scores_screen)
+            .add_systems(OnExit(AppState::HighScores), despawn_high_scores_screen)
+            .add_systems(
+                Update,
+                (high_scores_buttons, update_high_score_rows)
+                    .chain()
+                    .run_if(in_state(AppState::HighScores)),
+            );
+    }
+}
+
+/// Only runs ending on the game over screen ask for a name there. Those quit
+/// keep the last one.
+fn record_run(
+    mut high_scores: ResMut<HighScores>,
+    mut name_entry: ResMut<NameEntry>,
+    score: Res<Score>,
+    difficulty: Res<Difficulty>,
+    mode: Res<GameMode>,
+    app_state: Res<State<AppState>>,
+) {
+    let entry = HighScoreEntry {
+        name: high_scores.name().to_string(),
+        score: score.0,
+        difficulty: *difficulty,
+        day: today(),
+    };
+    let rank = high_scores.record(*mode, entry);
+    if rank.is_some() {
+        high_scores.save();
+    }
+    // Already the state being entered, when leaving `Running`
+    let game_over = *app_state.get() == AppState::GameOver;
+    name_entry.0 = rank.filter(|_| game_over).map(|rank| (*mode, rank));
+}
+
+fn save_high_scores(high_scores: Res<HighScores>) {
+    high_scores.save();
+}
+
+fn type_name(
+    mut characters: EventReader<ReceivedCharacter>,
+    input: Res<Input<KeyCode>>,
+    name_entry: Res<NameEntry>,
+    mut high_scores: ResMut<HighScores>,
+    locale: Res<Locale>,
+    mut texts: Query<&mut Text, With<NameEntryText>>,
+) {
+    // Keys pressed as the run ended aren't part of the name
+    if name_entry.is_changed() {
+        characters.clear();
+    }
+    let Some((mode, rank)) = name_entry.0 else {
+        return;
+    };
+    let Some(entry) = high_scores
+        .tables
+        .get_mut(&mode)
+        .and_then(|table| table.get_mut(rank))
+    else {
+        return;
+    };
+    let mut name = entry.name.clone();
+    for event in characters.read() {
+        if (event.char.is_alphanumeric() || event.char == ' ')
+            && name.chars().count() < MAX_NAME_LENGTH
+        {
+            name.push(event.char);
+        }
+    }
+    if input.just_pressed(KeyCode::Back) {
+        name.pop();
+    }
+    if name == entry.name && !locale.is_changed() {
+        return;
+    }
+    entry.name = name;
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = name_entry_text(&locale, rank, &entry.name);
+    }
+}
+
+fn name_entry_text(locale: &Locale, rank: usize, name: &str) -> String {
+    locale.format(
+        "game-over-name-entry",
+        &fluent_args!["rank" => rank + 1, "name" => format!("{name}_")],
+    )
+}
+
+/// Keeps the name for the runs to come
+fn finish_name_entry(mut name_entry: ResMut<NameEntry>, mut high_scores: ResMut<HighScores>) {
+    let Some((mode, rank)) = name_entry.0.take() else {
+        return;
+    };
+    let Some(entry) = high_scores
+        .tables
+        .get_mut(&mode)
+        .and_then(|table| table.get_mut(rank))
+    else {
+        return;
+    };
+    let name = entry.name.trim().to_string();
+    if name.is_empty() {
+        entry.name = DEFAULT_NAME.to_string();
+    } else {
+        entry.name = name.clone();
+        high_scores.last_name = name;
+    }
+    high_scores.save();
+}
+
+/// `day` days after the Unix epoch, as year-month-day
+pub(crate) fn format_day(day: u64) -> String {
+    // From Howard Hinnant's `civil_from_days`
+    let days = day as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day_of_month = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year}-{month:02}-{day_of_month:02}")
+}
+
+fn spawn_high_scores_screen(
+    mut commands: Commands,
+    theme: Res<UiTheme>,
+    locale: Res<Locale>,
+    shown: Res<ShownTable>,
+) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(16.),
+                    ..default()
+                },
+                ..default()
+            },
+            HighScoresScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(locale.text("high-scores", theme.title.clone()));
+            spawn_high_scores_button(
+                parent,
+                &theme,
+                locale.get(shown.mode().1),
+                HighScoresButton::Mode,
+            );
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(4.),
+                        ..default()
+                    },
+                    ..default()
+                },
+                HighScoreRows,
+            ));
+            spawn_high_scores_button(
+                parent,
+                &theme,
+                locale.get("button-back"),
+                HighScoresButton::Back,
+            );
+        });
+}
+
+fn spawn_high_scores_button(
+    parent: &mut ChildBuilder,
+    theme: &UiTheme,
+    label: String,
+    button: HighScoresButton,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    min_width: Val::Px(240.),
+                    height: Val::Px(50.),
+                    border: UiRect::all(Val::Px(5.)),
+                    padding: UiRect::horizontal(Val::Px(16.)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                border_color: BorderColor(Color::BLACK),
+                background_color: Color::WHITE.into(),
+                ..default()
+            },
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(label, theme.label.clone()));
+        });
+}
+
+fn despawn_high_scores_screen(
+    mut commands: Commands,
+    query: Query<Entity, With<HighScoresScreen>>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn high_scores_buttons(
+    actions: Res<ActionState>,
+    interaction_query: Query<(&Interaction, &HighScoresButton), Changed<Interaction>>,
+    mut shown: ResMut<ShownTable>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if actions.just_pressed(Action::MenuBack) {
+        next_state.set(AppState::MainMenu);
+    }
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            HighScoresButton::Mode => shown.0 = (shown.0 + 1) % GameMode::SELECTABLE.len(),
+            HighScoresButton::Back => next_state.set(AppState::MainMenu),
+        }
+    }
+}
+
+/// Fills in the table of the mode shown, whenever another is picked or the
+/// screen was just opened
+fn update_high_score_rows(
+    mut commands: Commands,
+    shown: Res<ShownTable>,
+    high_scores: Res<HighScores>,
+    theme: Res<UiTheme>,
+    locale: Res<Locale>,
+    rows: Query<(Entity, Ref<HighScoreRows>)>,
+    buttons: Query<(&HighScoresButton, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    let (mode, label) = shown.mode();
+    for (entity, row) in rows.iter() {
+        if !shown.is_changed() && !row.is_added() {
+            continue;
+        }
+        for (button, children) in buttons.iter() {
+            if *button != HighScoresButton::Mode {
+                continue;
+            }
+            let mut texts = texts.iter_many_mut(children);
+            while let Some(mut text) = texts.fetch_next() {
+                text.sections[0].value = locale.get(label);
+            }
+        }
+        let table = high_scores.table(mode);
+        commands
+            .entity(entity)
+            .despawn_descendants()
+            .with_children(|parent| {
+                if table.is_empty() {
+                    parent.spawn(locale.text("high-scores-empty", theme.hud.clone()));
+                }
+                for (rank, entry) in table.iter().enumerate() {
+                    let cells = [
+                        locale.format("high-scores-rank", &fluent_args!["rank" => rank + 1]),
+                        entry.name.clone(),
+                        entry.score.to_string(),
+                        locale.get(entry.difficulty.label()),
+                        format_day(entry.day),
+                    ];
+                    parent.spawn(NodeBundle::default()).with_children(|parent| {
+                        for (cell, width) in cells.into_iter().zip(COLUMN_WIDTHS) {
+                            parent
+                                .spawn(NodeBundle {
+                                    style: Style {
+                                        width: Val::Px(width),
+                                        ..default()
+                                    },
+                                    ..default()
+                                })
+                                .with_children(|parent| {
+                                    parent.spawn(TextBundle::from_section(cell, theme.hud.clone()));
+                                });
+                        }
+                    });
+                }
+            });
+    }
+}
@@ -0,0 +1,114 @@
+//! HUD layout. A root node covering the screen holds one region per corner,
+//! and every HUD widget names the region it belongs to with a `HudAnchor`.
+//! Widgets are spawned on their own and attached to their region afterwards,
+//! so any plugin can add HUD elements without knowing about the others.
+
+use bevy::prelude::*;
+
+use crate::state::AppState;
+
+const HUD_MARGIN: f32 = 10.;
+const WIDGET_GAP: f32 = 4.;
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HudRegion {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// Put on the top-level entity of a HUD widget to place it in a region.
+/// Widgets sharing a region stack away from the screen edge.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct HudAnchor(pub HudRegion);
+
+#[derive(Component)]
+struct HudRoot;
+
+pub struct HudLayoutPlugin;
+
+impl Plugin for HudLayoutPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::Running), spawn_hud_root)
+            .add_systems(
+                Update,
+                attach_hud_widgets.run_if(in_state(AppState::Running)),
+            );
+    }
+}
+
+fn spawn_hud_root(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    ..default()
+                },
+                ..default()
+            },
+            HudRoot,
+        ))
+        .with_children(|parent| {
+            for region in [
+                HudRegion::TopLeft,
+                HudRegion::TopRight,
+                HudRegion::BottomLeft,
+                HudRegion::BottomRight,
+            ] {
+                let (top, bottom) = match region {
+                    HudRegion::TopLeft | HudRegion::TopRight => (Val::Px(HUD_MARGIN), Val::Auto),
+                    HudRegion::BottomLeft | HudRegion::BottomRight => {
+                        (Val::Auto, Val::Px(HUD_MARGIN))
+                    }
+                };
+                let (left, right, align_items) = match region {
+                    HudRegion::TopLeft | HudRegion::BottomLeft => {
+                        (Val::Px(HUD_MARGIN), Val::Auto, AlignItems::FlexStart)
+                    }
+                    HudRegion::TopRight | HudRegion::BottomRight => {
+                        (Val::Auto, Val::Px(HUD_MARGIN), AlignItems::FlexEnd)
+                    }
+                };
+                parent.spawn((
+                    NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            top,
+                            bottom,
+                            left,
+                            right,
+                            flex_direction: match region {
+                                HudRegion::TopLeft | HudRegion::TopRight => FlexDirection::Column,
+                                // Bottom regions grow upwards, away from the edge
+                                HudRegion::BottomLeft | HudRegion::BottomRight => {
+                                    FlexDirection::ColumnReverse
+                                }
+                            },
+                            align_items,
+                            row_gap: Val::Px(WIDGET_GAP),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    region,
+                ));
+            }
+        });
+}
+
+/// Widgets wait at the top level until their region exists
+fn attach_hud_widgets(
+    mut commands: Commands,
+    widgets: Query<(Entity, &HudAnchor), Without<Parent>>,
+    regions: Query<(Entity, &HudRegion)>,
+) {
+    for (widget, anchor) in widgets.iter() {
+        if let Some((region, _)) = regions.iter().find(|(_, region)| **region == anchor.0) {
+            commands.entity(region).add_child(widget);
+        }
+    }
+}
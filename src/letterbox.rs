@@ -0,0 +1,134 @@
+//! The camera, and how the playfield fits the window. Whatever the window
+//! size, the playfield is scaled to fit and drawn in the middle, with black
+//! bars filling the rest. `Letterbox` can keep room for side panels on both
+//! sides, where the HUD can go. The UI is scaled along with it.
+
+use bevy::prelude::*;
+use bevy::render::camera::{CameraOutputMode, ScalingMode, Viewport};
+use bevy::render::render_resource::LoadOp;
+use bevy::window::{PrimaryWindow, WindowResized};
+
+use crate::Playfield;
+
+const BAR_COLOR: Color = Color::BLACK;
+
+/// What gets fit into the window: the playfield, plus any side panels
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct Letterbox {
+    /// Size of the playfield
+    pub playfield: Vec2,
+    /// Width of the panel on each side of the playfield, in playfield units
+    pub side_panel_width: f32,
+}
+
+impl FromWorld for Letterbox {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            playfield: world.resource::<Playfield>().rect.size(),
+            side_panel_width: 0.,
+        }
+    }
+}
+
+impl Letterbox {
+    /// Size of the playfield and side panels together
+    pub fn size(&self) -> Vec2 {
+        self.playfield + Vec2::new(self.side_panel_width * 2., 0.)
+    }
+
+    /// How many window pixels a playfield unit takes up
+    pub fn scale(&self, window_size: Vec2) -> f32 {
+        let size = self.size();
+        (window_size.x / size.x).min(window_size.y / size.y)
+    }
+}
+
+pub struct LetterboxPlugin;
+
+impl Plugin for LetterboxPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Letterbox>()
+            .add_systems(Startup, spawn_camera)
+            .add_systems(
+                Update,
+                (
+                    fit_camera_viewport,
+                    // Headless runs have neither windows nor UI to scale
+                    scale_ui_to_window.run_if(
+                        resource_exists::<Events<WindowResized>>()
+                            .and_then(resource_exists::<UiScale>()),
+                    ),
+                ),
+            );
+    }
+}
+
+/// Where a point of the window, in logical pixels, is on the playfield, unless
+/// it is on the bars around it
+pub fn window_to_playfield(
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    position: Vec2,
+) -> Option<Vec2> {
+    let viewport = camera.logical_viewport_rect()?;
+    if !viewport.contains(position) {
+        return None;
+    }
+    camera.viewport_to_world_2d(camera_transform, position - viewport.min)
+}
+
+/// The camera outlives runs, so screens between them have something to render to
+fn spawn_camera(mut commands: Commands, letterbox: Res<Letterbox>) {
+    let mut camera = Camera2dBundle::default();
+    camera.projection.scaling_mode = ScalingMode::Fixed {
+        width: letterbox.playfield.x,
+        height: letterbox.playfield.y,
+    };
+    // The background color only fills the viewport, the bars stay black
+    camera.camera.output_mode = CameraOutputMode::Write {
+        blend_state: None,
+        color_attachment_load_op: LoadOp::Clear(BAR_COLOR.into()),
+    };
+    commands.spawn(camera);
+}
+
+/// Keeps the camera drawing to the playfield's part of the window
+fn fit_camera_viewport(
+    letterbox: Res<Letterbox>,
+    windows: Query<Ref<Window>, With<PrimaryWindow>>,
+    mut cameras: Query<&mut Camera>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    if !window.is_changed() && !letterbox.is_changed() {
+        return;
+    }
+    let window_size = Vec2::new(
+        window.resolution.physical_width() as f32,
+        window.resolution.physical_height() as f32,
+    );
+    let scale = letterbox.scale(window_size);
+    let size = (letterbox.playfield * scale).max(Vec2::ONE);
+    let position = (window_size - letterbox.size() * scale) / 2.
+        + Vec2::new(letterbox.side_panel_width * scale, 0.);
+    for mut camera in cameras.iter_mut() {
+        camera.viewport = Some(Viewport {
+            physical_position: position.as_uvec2(),
+            physical_size: size.as_uvec2(),
+            ..default()
+        });
+    }
+}
+
+/// Scales the UI along with the playfield, so it keeps its size relative to it
+fn scale_ui_to_window(
+    letterbox: Res<Letterbox>,
+    mut resize_events: EventReader<WindowResized>,
+    mut ui_scale: ResMut<UiScale>,
+) {
+    if let Some(event) = resize_events.read().last() {
+        let scale = letterbox.scale(Vec2::new(event.width, event.height));
+        ui_scale.0 = scale as f64;
+    }
+}
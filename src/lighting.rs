@@ -0,0 +1,357 @@
+//! 2D lighting, built with the `lighting` cargo feature and switched on and
+//! off in the settings. The playfield is dimmed down to an ambient level, and
+//! lights carried by the player, the bullets and explosions bring the scene
+//! back up around them.
+//!
+//! Both are flat quads drawn over the playfield, blending with what is
+//! already there rather than covering it: the ambient quad multiplies the
+//! scene by the ambient level, and every light adds a share of the scene back
+//! in, most at its center and none past its radius.
+
+use bevy::prelude::*;
+use bevy::render::mesh::MeshVertexBufferLayout;
+use bevy::render::render_resource::{
+    AsBindGroup, BlendComponent, BlendFactor, BlendOperation, BlendState, RenderPipelineDescriptor,
+    ShaderRef, SpecializedMeshPipelineError,
+};
+use bevy::sprite::{Material2d, Material2dKey, Material2dPlugin, MaterialMesh2dBundle};
+
+use crate::bullet::{Bullet, Hostility};
+use crate::enemy::EnemyKilledEvent;
+use crate::palette::Palette;
+use crate::particles::ExplosionEvent;
+use crate::player::Player;
+use crate::settings::Settings;
+use crate::state::{gameplay_active, AppState};
+use crate::Playfield;
+
+const LIGHT_SHADER_PATH: &str = "shaders/light.wgsl";
+/// What the scene is dimmed to away from any light
+const AMBIENT: Color = Color::rgb_linear(0.5, 0.5, 0.5);
+/// Above the ships, bullets and particles, below the vignette
+const AMBIENT_Z: f32 = 30.;
+const LIGHT_Z: f32 = 31.;
+const PLAYER_LIGHT: Color = Color::rgb_linear(1., 0.95, 0.85);
+const PLAYER_LIGHT_RADIUS: f32 = 140.;
+const BULLET_LIGHT_RADIUS: f32 = 40.;
+const EXPLOSION_LIGHT: Color = Color::rgb_linear(1.5, 0.8, 0.3);
+const EXPLOSION_LIGHT_RADIUS: f32 = 180.;
+const EXPLOSION_LIGHT_SECONDS: f32 = 0.4;
+/// With reduced flashing, so explosions light up the scene without a pop
+const SOFT_EXPLOSION_LIGHT_SECONDS: f32 = 1.2;
+
+/// Keeps the destination alpha as it is
+const KEEP_ALPHA: BlendComponent = BlendComponent {
+    src_factor: BlendFactor::Zero,
+    dst_factor: BlendFactor::One,
+    operation: BlendOperation::Add,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LightKind {
+    /// Multiplies the scene by its color
+    Ambient,
+    /// Adds its color, times the scene, fading out towards the edges
+    Point,
+}
+
+#[derive(Asset, TypePath, AsBindGroup, Clone, Debug)]
+#[bind_group_data(LightMaterialKey)]
+pub struct LightMaterial {
+    #[uniform(0)]
+    pub color: Color,
+    pub kind: LightKind,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LightMaterialKey {
+    kind: LightKind,
+}
+
+impl From<&LightMaterial> for LightMaterialKey {
+    fn from(material: &LightMaterial) -> Self {
+        Self {
+            kind: material.kind,
+        }
+    }
+}
+
+impl Material2d for LightMaterial {
+    fn fragment_shader() -> ShaderRef {
+        LIGHT_SHADER_PATH.into()
+    }
+
+    fn specialize(
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayout,
+        key: Material2dKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        let Some(fragment) = &mut descriptor.fragment else {
+            return Ok(());
+        };
+        let dst_factor = match key.bind_group_data.kind {
+            LightKind::Ambient => BlendFactor::Zero,
+            LightKind::Point => {
+                fragment.shader_defs.push("POINT_LIGHT".into());
+                BlendFactor::One
+            }
+        };
+        for target in fragment.targets.iter_mut().flatten() {
+            target.blend = Some(BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::Dst,
+                    dst_factor,
+                    operation: BlendOperation::Add,
+                },
+                alpha: KEEP_ALPHA,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The mesh and materials every light shares
+#[derive(Resource)]
+struct LightAssets {
+    /// A unit square, scaled to every light's size
+    mesh: Handle<Mesh>,
+    ambient: Handle<LightMaterial>,
+    player: Handle<LightMaterial>,
+    /// Colored after the bullets, see `recolor_bullet_lights`
+    friendly_bullet: Handle<LightMaterial>,
+    hostile_bullet: Handle<LightMaterial>,
+    explosion: Handle<LightMaterial>,
+}
+
+impl FromWorld for LightAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mesh = world
+            .resource_mut::<Assets<Mesh>>()
+            .add(shape::Quad::new(Vec2::ONE).into());
+        let mut materials = world.resource_mut::<Assets<LightMaterial>>();
+        let mut light = |color, kind| materials.add(LightMaterial { color, kind });
+        Self {
+            mesh,
+            ambient: light(AMBIENT, LightKind::Ambient),
+            player: light(PLAYER_LIGHT, LightKind::Point),
+            friendly_bullet: light(Color::WHITE, LightKind::Point),
+            hostile_bullet: light(Color::WHITE, LightKind::Point),
+            explosion: light(EXPLOSION_LIGHT, LightKind::Point),
+        }
+    }
+}
+
+/// The quad dimming the playfield
+#[derive(Component)]
+struct Ambient;
+
+/// A light, following `source` around until it is gone, if it has one
+#[derive(Component)]
+struct Light {
+    source: Option<Entity>,
+}
+
+/// Has a light following it
+#[derive(Component)]
+struct Lit;
+
+/// Shrinks away to nothing over its lifetime
+#[derive(Component)]
+struct FadingLight {
+    radius: f32,
+    seconds: f32,
+    elapsed: f32,
+}
+
+pub struct LightingPlugin;
+
+impl Plugin for LightingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(Material2dPlugin::<LightMaterial>::default())
+            .init_resource::<LightAssets>()
+            .add_systems(
+                Update,
+                (
+                    recolor_bullet_lights.run_if(resource_changed::<Palette>()),
+                    remove_lighting.run_if(not(lighting_on)),
+                    (
+                        spawn_ambient,
+                        light_sources,
+                        spawn_explosion_lights.run_if(gameplay_active),
+                        follow_sources,
+                        fade_lights.run_if(gameplay_active),
+                    )
+                        .chain()
+                        .run_if(lighting_on),
+                )
+                    .chain()
+                    .run_if(in_state(AppState::Running)),
+            );
+    }
+}
+
+fn lighting_on(settings: Res<Settings>) -> bool {
+    settings.lighting
+}
+
+/// Bullets light up their surroundings in their own color
+fn recolor_bullet_lights(
+    palette: Res<Palette>,
+    assets: Res<LightAssets>,
+    mut materials: ResMut<Assets<LightMaterial>>,
+) {
+    for (handle, color) in [
+        (&assets.friendly_bullet, palette.friendly_bullet),
+        (&assets.hostile_bullet, palette.hostile_bullet),
+    ] {
+        if let Some(material) = materials.get_mut(handle) {
+            material.color = color;
+        }
+    }
+}
+
+fn light_bundle(
+    assets: &LightAssets,
+    material: &Handle<LightMaterial>,
+    position: Vec2,
+    radius: f32,
+) -> MaterialMesh2dBundle<LightMaterial> {
+    MaterialMesh2dBundle {
+        mesh: assets.mesh.clone().into(),
+        material: material.clone(),
+        transform: Transform::from_translation(position.extend(LIGHT_Z)).with_scale(Vec3::new(
+            radius * 2.,
+            radius * 2.,
+            1.,
+        )),
+        ..default()
+    }
+}
+
+fn spawn_ambient(
+    mut commands: Commands,
+    assets: Res<LightAssets>,
+    playfield: Res<Playfield>,
+    ambient: Query<(), With<Ambient>>,
+) {
+    if !ambient.is_empty() {
+        return;
+    }
+    let size = playfield.rect.size();
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: assets.mesh.clone().into(),
+            material: assets.ambient.clone(),
+            transform: Transform::from_translation(playfield.rect.center().extend(AMBIENT_Z))
+                .with_scale(size.extend(1.)),
+            ..default()
+        },
+        Ambient,
+    ));
+}
+
+/// Gives a light to everything that should carry one and doesn't yet
+fn light_sources(
+    mut commands: Commands,
+    assets: Res<LightAssets>,
+    players: Query<(Entity, &Transform), (With<Player>, Without<Lit>)>,
+    bullets: Query<(Entity, &Transform, &Hostility), (With<Bullet>, Without<Lit>)>,
+) {
+    let sources = players
+        .iter()
+        .map(|(entity, transform)| (entity, transform, &assets.player, PLAYER_LIGHT_RADIUS))
+        .chain(bullets.iter().map(|(entity, transform, hostility)| {
+            let material = match hostility {
+                Hostility::Hostile => &assets.hostile_bullet,
+                Hostility::Friendly => &assets.friendly_bullet,
+            };
+            (entity, transform, material, BULLET_LIGHT_RADIUS)
+        }));
+    for (entity, transform, material, radius) in sources {
+        commands.entity(entity).insert(Lit);
+        commands.spawn((
+            light_bundle(&assets, material, transform.translation.truncate(), radius),
+            Light {
+                source: Some(entity),
+            },
+        ));
+    }
+}
+
+fn spawn_explosion_lights(
+    mut commands: Commands,
+    assets: Res<LightAssets>,
+    settings: Res<Settings>,
+    mut kill_events: EventReader<EnemyKilledEvent>,
+    mut explosion_events: EventReader<ExplosionEvent>,
+) {
+    let positions = kill_events
+        .read()
+        .map(|event| event.position)
+        .chain(explosion_events.read().map(|event| event.position));
+    for position in positions {
+        commands.spawn((
+            light_bundle(&assets, &assets.explosion, position, EXPLOSION_LIGHT_RADIUS),
+            Light { source: None },
+            FadingLight {
+                radius: EXPLOSION_LIGHT_RADIUS,
+                seconds: if settings.reduced_flashing {
+                    SOFT_EXPLOSION_LIGHT_SECONDS
+                } else {
+                    EXPLOSION_LIGHT_SECONDS
+                },
+                elapsed: 0.,
+            },
+        ));
+    }
+}
+
+/// Lights aren't children of their sources, which are despawned without
+/// their children in many places, so they follow them around instead
+fn follow_sources(
+    mut commands: Commands,
+    mut lights: Query<(Entity, &Light, &mut Transform)>,
+    sources: Query<&Transform, (With<Lit>, Without<Light>)>,
+) {
+    for (entity, light, mut transform) in lights.iter_mut() {
+        let Some(source) = light.source else {
+            continue;
+        };
+        match sources.get(source) {
+            Ok(source) => {
+                transform.translation = source.translation.truncate().extend(LIGHT_Z);
+            }
+            Err(_) => commands.entity(entity).despawn(),
+        }
+    }
+}
+
+fn fade_lights(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut lights: Query<(Entity, &mut FadingLight, &mut Transform)>,
+) {
+    for (entity, mut light, mut transform) in lights.iter_mut() {
+        light.elapsed += time.delta_seconds();
+        if light.elapsed >= light.seconds {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        let diameter = light.radius * 2. * (1. - light.elapsed / light.seconds);
+        transform.scale = Vec3::new(diameter, diameter, 1.);
+    }
+}
+
+/// Takes every light and the dimming away, for the setting being turned off
+fn remove_lighting(
+    mut commands: Commands,
+    lighting: Query<Entity, Or<(With<Light>, With<Ambient>)>>,
+    lit: Query<Entity, With<Lit>>,
+) {
+    for entity in lighting.iter() {
+        commands.entity(entity).despawn();
+    }
+    for entity in lit.iter() {
+        commands.entity(entity).remove::<Lit>();
+    }
+}
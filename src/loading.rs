@@ -0,0 +1,405 @@
+//! The loading screen. `AppState::Loading` waits on everything the game needs
+//! up front, the font, the translations, the content files, the sprite sheet,
+//! the sound effects and the music, with a bar filling up as they come in.
+//! Whatever fails to load is listed on an error screen afterwards. None of it
+//! is essential, so the game carries on from there without it.
+
+use bevy::asset::{LoadState, UntypedAssetId};
+use bevy::log;
+use bevy::prelude::*;
+use bevy_kira_audio::AudioSource;
+use fluent::fluent_args;
+
+use crate::audio::{SoundEffects, MENU_MUSIC};
+use crate::balance::EnemyBalanceTable;
+use crate::boss_music::{BOSS_MUSIC, VICTORY_STING};
+use crate::caravan::CaravanTimeline;
+use crate::enemies::EnemyDefinitions;
+use crate::locale::{Language, Locale, TranslationFiles};
+use crate::mode::ENDLESS_MUSIC;
+use crate::patterns::BulletPatterns;
+use crate::sprites::SpriteSheet;
+use crate::stage::Stages;
+use crate::state::AppState;
+use crate::theme::{UiTheme, FONT_PATH};
+
+const BAR_WIDTH: f32 = 400.;
+const BAR_HEIGHT: f32 = 24.;
+
+/// Handles to every music track, loaded up front so tracks start as soon as
+/// they are asked for
+#[derive(Resource, Default)]
+struct MusicTracks(Vec<(String, Handle<AudioSource>)>);
+
+/// What failed to load
+#[derive(Resource, Default)]
+struct MissingAssets(Vec<TrackedAsset>);
+
+/// An asset the loading screen waits on
+struct TrackedAsset {
+    /// Message saying what it is on the error screen, filled in with `name`
+    label: &'static str,
+    name: String,
+    id: UntypedAssetId,
+    /// Message saying what the game does without it. `None` for optional
+    /// assets, which aren't missed.
+    fallback: Option<&'static str>,
+}
+
+impl TrackedAsset {
+    fn new(
+        label: &'static str,
+        name: impl Into<String>,
+        id: impl Into<UntypedAssetId>,
+        fallback: &'static str,
+    ) -> Self {
+        Self {
+            label,
+            name: name.into(),
+            id: id.into(),
+            fallback: Some(fallback),
+        }
+    }
+}
+
+#[derive(Component)]
+struct LoadingScreen;
+
+#[derive(Component)]
+struct LoadingBarFill;
+
+#[derive(Component)]
+struct MissingAssetsScreen;
+
+#[derive(Component)]
+struct ContinueButton;
+
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MusicTracks>()
+            .init_resource::<MissingAssets>()
+            .add_systems(
+                OnEnter(AppState::Loading),
+                (load_music, spawn_loading_screen),
+            )
+            .add_systems(OnExit(AppState::Loading), despawn_loading_screen)
+            .add_systems(Update, finish_loading.run_if(in_state(AppState::Loading)))
+            .add_systems(
+                OnEnter(AppState::LoadingFailed),
+                spawn_missing_assets_screen,
+            )
+            .add_systems(
+                OnExit(AppState::LoadingFailed),
+                despawn_missing_assets_screen,
+            )
+            .add_systems(
+                Update,
+                continue_past_missing_assets.run_if(in_state(AppState::LoadingFailed)),
+            );
+    }
+}
+
+fn load_music(
+    mut tracks: ResMut<MusicTracks>,
+    stages: Res<Stages>,
+    asset_server: Res<AssetServer>,
+) {
+    let mut paths = vec![MENU_MUSIC, ENDLESS_MUSIC, BOSS_MUSIC, VICTORY_STING];
+    paths.extend(stages.0.iter().filter_map(|stage| stage.music.as_deref()));
+    for path in paths {
+        if tracks.0.iter().all(|(track, _)| track != path) {
+            tracks
+                .0
+                .push((path.to_string(), asset_server.load(path.to_string())));
+        }
+    }
+}
+
+/// Everything the loading screen waits on
+fn tracked_assets(
+    theme: &UiTheme,
+    translations: &TranslationFiles,
+    stages: &Stages,
+    enemies: &EnemyDefinitions,
+    balance: &EnemyBalanceTable,
+    patterns: &BulletPatterns,
+    caravan: &CaravanTimeline,
+    sprite_sheet: &SpriteSheet,
+    sound_effects: &SoundEffects,
+    music: &MusicTracks,
+) -> Vec<TrackedAsset> {
+    let mut assets = vec![TrackedAsset::new(
+        "asset-font",
+        FONT_PATH,
+        &theme.font,
+        "fallback-no-text",
+    )];
+    for language in Language::ALL {
+        assets.push(TrackedAsset::new(
+            "asset-translations",
+            language.name(),
+            &translations.0[&language],
+            if language == Language::English {
+                "fallback-message-ids"
+            } else {
+                "fallback-english"
+            },
+        ));
+    }
+    for stage in &stages.0 {
+        assets.push(TrackedAsset::new(
+            "asset-stage-timeline",
+            &stage.name,
+            &stage.timeline,
+            "fallback-empty-stage",
+        ));
+    }
+    assets.push(TrackedAsset::new(
+        "asset-caravan-timeline",
+        "",
+        &caravan.0,
+        "fallback-empty-caravan",
+    ));
+    for (name, handle) in &enemies.0 {
+        assets.push(TrackedAsset::new(
+            "asset-enemy",
+            name,
+            handle,
+            "fallback-built-in-enemy",
+        ));
+    }
+    assets.push(TrackedAsset::new(
+        "asset-balance-table",
+        "",
+        &balance.0,
+        "fallback-unbalanced",
+    ));
+    for (name, handle) in &patterns.0 {
+        assets.push(TrackedAsset::new(
+            "asset-pattern",
+            name,
+            handle,
+            "fallback-single-bullets",
+        ));
+    }
+    for (sfx, handle) in &sound_effects.0 {
+        assets.push(TrackedAsset::new(
+            "asset-sound-effect",
+            format!("{sfx:?}"),
+            handle,
+            "fallback-silent",
+        ));
+    }
+    for (track, handle) in &music.0 {
+        assets.push(TrackedAsset::new(
+            "asset-music",
+            track,
+            handle,
+            "fallback-silent",
+        ));
+    }
+    // Plain shapes are drawn without it
+    assets.push(TrackedAsset {
+        label: "asset-sprite-sheet",
+        name: String::new(),
+        id: sprite_sheet.image.id().untyped(),
+        fallback: None,
+    });
+    assets
+}
+
+/// Fills the bar as assets come in, and moves on once they all have, or have
+/// failed
+fn finish_loading(
+    asset_server: Res<AssetServer>,
+    theme: Res<UiTheme>,
+    translations: Res<TranslationFiles>,
+    stages: Res<Stages>,
+    enemies: Res<EnemyDefinitions>,
+    balance: Res<EnemyBalanceTable>,
+    patterns: Res<BulletPatterns>,
+    caravan: Res<CaravanTimeline>,
+    sprite_sheet: Res<SpriteSheet>,
+    sound_effects: Res<SoundEffects>,
+    music: Res<MusicTracks>,
+    mut missing: ResMut<MissingAssets>,
+    mut bars: Query<&mut Style, With<LoadingBarFill>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let assets = tracked_assets(
+        &theme,
+        &translations,
+        &stages,
+        &enemies,
+        &balance,
+        &patterns,
+        &caravan,
+        &sprite_sheet,
+        &sound_effects,
+        &music,
+    );
+    let states = assets
+        .iter()
+        .map(|asset| asset_server.get_load_state(asset.id))
+        .collect::<Vec<_>>();
+    let done = states
+        .iter()
+        .filter(|state| matches!(state, Some(LoadState::Loaded) | Some(LoadState::Failed)))
+        .count();
+    for mut style in bars.iter_mut() {
+        style.width = Val::Percent(100. * done as f32 / assets.len() as f32);
+    }
+    if done < assets.len() {
+        return;
+    }
+
+    missing.0.clear();
+    for (asset, state) in assets.into_iter().zip(states) {
+        if state == Some(LoadState::Failed) && asset.fallback.is_some() {
+            let path = asset_server.get_path(asset.id);
+            log::warn!("Could not load {path:?}, going on without it");
+            missing.0.push(asset);
+        }
+    }
+    if missing.0.is_empty() {
+        next_state.set(AppState::MainMenu);
+    } else {
+        next_state.set(AppState::LoadingFailed);
+    }
+}
+
+fn spawn_loading_screen(mut commands: Commands, theme: Res<UiTheme>, locale: Res<Locale>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.),
+                    ..default()
+                },
+                ..default()
+            },
+            LoadingScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(locale.text("loading", theme.score.clone()));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(BAR_WIDTH),
+                        height: Val::Px(BAR_HEIGHT),
+                        border: UiRect::all(Val::Px(3.)),
+                        ..default()
+                    },
+                    border_color: BorderColor(Color::WHITE),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(0.),
+                                height: Val::Percent(100.),
+                                ..default()
+                            },
+                            background_color: Color::WHITE.into(),
+                            ..default()
+                        },
+                        LoadingBarFill,
+                    ));
+                });
+        });
+}
+
+fn spawn_missing_assets_screen(
+    mut commands: Commands,
+    theme: Res<UiTheme>,
+    missing: Res<MissingAssets>,
+    locale: Res<Locale>,
+) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(12.),
+                    ..default()
+                },
+                ..default()
+            },
+            MissingAssetsScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(locale.text("missing-assets", theme.score.clone()));
+            for asset in &missing.0 {
+                let name = fluent_args!["name" => asset.name.as_str()];
+                let args = fluent_args![
+                    "asset" => locale.format(asset.label, &name),
+                    "fallback" => locale.get(asset.fallback.unwrap_or_default()),
+                ];
+                parent.spawn(TextBundle::from_section(
+                    locale.format("missing-asset", &args),
+                    theme.hud.clone(),
+                ));
+            }
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            min_width: Val::Px(240.),
+                            height: Val::Px(65.),
+                            margin: UiRect::top(Val::Px(20.)),
+                            border: UiRect::all(Val::Px(5.)),
+                            padding: UiRect::horizontal(Val::Px(16.)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        border_color: BorderColor(Color::BLACK),
+                        background_color: Color::WHITE.into(),
+                        ..default()
+                    },
+                    ContinueButton,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(locale.text("button-continue", theme.button.clone()));
+                });
+        });
+}
+
+fn continue_past_missing_assets(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<ContinueButton>)>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if buttons
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        next_state.set(AppState::MainMenu);
+    }
+}
+
+fn despawn_loading_screen(mut commands: Commands, query: Query<Entity, With<LoadingScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn despawn_missing_assets_screen(
+    mut commands: Commands,
+    query: Query<Entity, With<MissingAssetsScreen>>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
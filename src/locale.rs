@@ -0,0 +1,290 @@
+//! Translations of everything the game says, as Fluent files under
+//! `assets/locales/`, one per language. The language is picked in the
+//! settings, and text on screen switches over to it right away. With the
+//! `dev` feature the files are watched, so edits show up as they are saved.
+//!
+//! Text that never changes is spawned with `Locale::text`, which keeps it in
+//! the current language. Anything filled in with numbers or names goes through
+//! `Locale::format` when it is written. Messages missing from a translation
+//! are shown in English, and ones missing from English too as their id.
+
+use std::sync::Arc;
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::log;
+use bevy::prelude::*;
+use bevy::utils::{BoxedFuture, HashMap};
+use fluent::concurrent::FluentBundle;
+use fluent::{FluentArgs, FluentResource};
+use fluent_syntax::ast::Entry;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use unic_langid::{langid, LanguageIdentifier};
+
+use crate::settings::Settings;
+use crate::state::AppState;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    English,
+    Spanish,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::Spanish];
+
+    pub(crate) fn next(self) -> Self {
+        match self {
+            Language::English => Language::Spanish,
+            Language::Spanish => Language::English,
+        }
+    }
+
+    /// What the language calls itself, so it can be found from any other
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::Spanish => "Español",
+        }
+    }
+
+    fn id(self) -> LanguageIdentifier {
+        match self {
+            Language::English => langid!("en-US"),
+            Language::Spanish => langid!("es-ES"),
+        }
+    }
+
+    fn path(self) -> &'static str {
+        match self {
+            Language::English => "locales/en-US.ftl",
+            Language::Spanish => "locales/es-ES.ftl",
+        }
+    }
+}
+
+/// The messages of one language
+#[derive(Asset, TypePath)]
+pub struct Translations(Arc<FluentResource>);
+
+/// Handles to the translations of every language
+#[derive(Resource, Debug)]
+pub struct TranslationFiles(pub HashMap<Language, Handle<Translations>>);
+
+impl FromWorld for TranslationFiles {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        Self(
+            Language::ALL
+                .into_iter()
+                .map(|language| (language, asset_server.load(language.path())))
+                .collect(),
+        )
+    }
+}
+
+/// The messages of the language picked in the settings, with English filling
+/// in for whatever it lacks
+#[derive(Resource)]
+pub struct Locale {
+    language: Language,
+    bundle: FluentBundle<Arc<FluentResource>>,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self::new(
+            Language::default(),
+            &TranslationFiles(HashMap::new()),
+            &Assets::default(),
+        )
+    }
+}
+
+impl Locale {
+    fn new(
+        language: Language,
+        files: &TranslationFiles,
+        translations: &Assets<Translations>,
+    ) -> Self {
+        let mut bundle = FluentBundle::new_concurrent(vec![language.id()]);
+        // The marks around every variable keep right-to-left text in order,
+        // but the UI font has no glyphs for them
+        bundle.set_use_isolating(false);
+        for language in [language, Language::English] {
+            let loaded = files
+                .0
+                .get(&language)
+                .and_then(|handle| translations.get(handle));
+            if let Some(loaded) = loaded {
+                // English only adds what the language has no message for, so
+                // the errors about the messages it would override are expected
+                let _ = bundle.add_resource(loaded.0.clone());
+            }
+        }
+        Self { language, bundle }
+    }
+
+    pub fn language(&self) -> Language {
+        self.language
+    }
+
+    /// The message `id`
+    pub fn get(&self, id: &str) -> String {
+        self.format(id, &FluentArgs::new())
+    }
+
+    /// The message `id`, with its variables filled in from `args`
+    pub fn format(&self, id: &str, args: &FluentArgs) -> String {
+        let Some(pattern) = self
+            .bundle
+            .get_message(id)
+            .and_then(|message| message.value())
+        else {
+            return id.to_string();
+        };
+        let mut errors = Vec::new();
+        let text = self.bundle.format_pattern(pattern, Some(args), &mut errors);
+        for error in errors {
+            log::warn!("Could not fill in the message {id}: {error}");
+        }
+        text.into_owned()
+    }
+
+    /// Text showing the message `id`, kept in the current language
+    pub fn text(&self, id: &'static str, style: TextStyle) -> (TextBundle, Localized) {
+        (TextBundle::from_section(self.get(id), style), Localized(id))
+    }
+}
+
+/// Keeps the text it is on showing the message with this id, in the current
+/// language
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Localized(pub &'static str);
+
+#[derive(Default)]
+pub struct TranslationsLoader;
+
+#[derive(Debug, Error)]
+pub enum TranslationsLoaderError {
+    #[error("could not read the translations: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse the translations: {}", .0.join("; "))]
+    Parse(Vec<String>),
+}
+
+impl AssetLoader for TranslationsLoader {
+    type Asset = Translations;
+    type Settings = ();
+    type Error = TranslationsLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Translations, Self::Error>> {
+        Box::pin(async move {
+            let mut source = String::new();
+            reader.read_to_string(&mut source).await?;
+            let resource = FluentResource::try_new(source).map_err(|(resource, errors)| {
+                let problems = errors.into_iter().map(|error| {
+                    let line = resource.source()[..error.pos.start].lines().count().max(1);
+                    format!("at line {line}: {error}")
+                });
+                TranslationsLoaderError::Parse(problems.collect())
+            })?;
+            Ok(Translations(Arc::new(resource)))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ftl"]
+    }
+}
+
+pub struct LocalePlugin;
+
+impl Plugin for LocalePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Translations>()
+            .init_asset_loader::<TranslationsLoader>()
+            .init_resource::<TranslationFiles>()
+            .init_resource::<Locale>()
+            .add_systems(OnExit(AppState::Loading), check_translations)
+            .add_systems(
+                Update,
+                (update_locale, translate_texts, log_translation_reloads).chain(),
+            );
+    }
+}
+
+/// Rebuilds the locale whenever the language is switched or a translation
+/// (re)loads
+pub(crate) fn update_locale(
+    settings: Res<Settings>,
+    files: Res<TranslationFiles>,
+    translations: Res<Assets<Translations>>,
+    mut events: EventReader<AssetEvent<Translations>>,
+    mut locale: ResMut<Locale>,
+) {
+    let loaded = events.read().count() > 0;
+    if loaded || locale.language != settings.language {
+        *locale = Locale::new(settings.language, &files, &translations);
+    }
+}
+
+fn translate_texts(locale: Res<Locale>, mut texts: Query<(Ref<Localized>, &mut Text)>) {
+    for (localized, mut text) in texts.iter_mut() {
+        if locale.is_changed() || localized.is_changed() {
+            text.sections[0].value = locale.get(localized.0);
+        }
+    }
+}
+
+/// Warns about messages a translation lacks, which would otherwise quietly
+/// show up in English
+fn check_translations(files: Res<TranslationFiles>, translations: Res<Assets<Translations>>) {
+    let ids = |language: Language| {
+        files
+            .0
+            .get(&language)
+            .and_then(|handle| translations.get(handle))
+            .map(|loaded| {
+                loaded
+                    .0
+                    .entries()
+                    .filter_map(|entry| match entry {
+                        Entry::Message(message) => Some(message.id.name),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default()
+    };
+    let english = ids(Language::English);
+    for language in Language::ALL {
+        let translated = ids(language);
+        let missing = english
+            .iter()
+            .filter(|id| !translated.contains(id))
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            log::warn!("The {language:?} translation has no {missing:?}");
+        }
+    }
+}
+
+fn log_translation_reloads(
+    mut events: EventReader<AssetEvent<Translations>>,
+    asset_server: Res<AssetServer>,
+) {
+    for event in events.read() {
+        if let AssetEvent::Modified { id } = event {
+            let path = asset_server.get_path(*id);
+            log::info!("Reloaded translations {path:?}");
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! Low HP warning. While the player is low on HP a beep repeats, twice as
+//! fast on the last life, and the HP bar blinks red along with it. A red
+//! vignette also fades in around the edges of the playfield, pulsing with
+//! every beep, unless turned off in the settings. With reduced flashing the
+//! bar stays red and the vignette holds still instead. All of it stops once
+//! HP is back up, whether from healing or from a lost life refilling it, and when
+//! the run ends.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+
+use crate::audio::{PlaySfx, SfxId};
+use crate::palette::Palette;
+use crate::player::{Lives, Player};
+use crate::settings::Settings;
+use crate::state::{gameplay_active, AppState};
+use crate::ui::HpBarFill;
+use crate::{HitPoints, MaxHitPoints, Playfield};
+
+const BEEP_SECONDS: f32 = 1.;
+const LAST_LIFE_BEEP_SECONDS: f32 = 0.5;
+const WARNING_COLOR: Color = Color::RED;
+/// Side of the generated vignette texture
+const VIGNETTE_RESOLUTION: u32 = 128;
+/// How far in from the edges the vignette reaches, as a fraction of the
+/// distance to the center
+const VIGNETTE_REACH: f32 = 0.5;
+/// Opacity at the very edge, between beeps and right on one
+const VIGNETTE_ALPHA: (f32, f32) = (0.35, 0.6);
+/// How quickly the vignette fades in and out, per second
+const VIGNETTE_FADE: f32 = 3.;
+/// In front of everything on the playfield
+const VIGNETTE_Z: f32 = 40.;
+
+/// Fraction of the max HP at or below which the warning goes off
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct LowHpThreshold(pub f32);
+
+impl Default for LowHpThreshold {
+    fn default() -> Self {
+        Self(0.3)
+    }
+}
+
+/// Time until the next beep. Starts out at zero so the first beep comes as
+/// soon as HP gets low.
+#[derive(Resource)]
+struct LowHpBeep(Timer);
+
+/// The red vignette over the playfield
+#[derive(Component, Default)]
+struct Vignette {
+    /// From 0, gone, to 1, fully faded in
+    intensity: f32,
+}
+
+impl Default for LowHpBeep {
+    fn default() -> Self {
+        Self(Timer::from_seconds(0., TimerMode::Once))
+    }
+}
+
+pub struct LowHpWarningPlugin;
+
+impl Plugin for LowHpWarningPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LowHpBeep>()
+            .init_resource::<LowHpThreshold>()
+            .add_systems(OnEnter(AppState::Running), spawn_vignette)
+            .add_systems(
+                Update,
+                (warn_low_hp, fade_vignette).chain().run_if(gameplay_active),
+            );
+    }
+}
+
+/// Whether the player is at or below the low HP threshold
+fn low_hp(
+    players: &Query<(&HitPoints, &MaxHitPoints), With<Player>>,
+    threshold: LowHpThreshold,
+) -> bool {
+    players
+        .get_single()
+        .is_ok_and(|(hp, max_hp)| hp.0 as f32 <= max_hp.0 as f32 * threshold.0)
+}
+
+fn warn_low_hp(
+    time: Res<Time>,
+    players: Query<(&HitPoints, &MaxHitPoints), With<Player>>,
+    threshold: Res<LowHpThreshold>,
+    lives: Res<Lives>,
+    palette: Res<Palette>,
+    settings: Res<Settings>,
+    mut beep: ResMut<LowHpBeep>,
+    mut sfx_events: EventWriter<PlaySfx>,
+    mut fills: Query<&mut BackgroundColor, With<HpBarFill>>,
+) {
+    if !low_hp(&players, *threshold) {
+        *beep = LowHpBeep::default();
+        for mut color in fills.iter_mut() {
+            if color.0 != palette.hp_bar {
+                color.0 = palette.hp_bar;
+            }
+        }
+        return;
+    }
+
+    if beep.0.tick(time.delta()).finished() {
+        sfx_events.send(PlaySfx::new(SfxId::LowHp));
+        let seconds = if lives.0 == 0 {
+            LAST_LIFE_BEEP_SECONDS
+        } else {
+            BEEP_SECONDS
+        };
+        beep.0.set_duration(Duration::from_secs_f32(seconds));
+        beep.0.reset();
+    }
+    // Red for the first half of every beep, or throughout with reduced
+    // flashing
+    let blink_on = settings.reduced_flashing || beep.0.percent() < 0.5;
+    for mut color in fills.iter_mut() {
+        color.0 = if blink_on {
+            WARNING_COLOR
+        } else {
+            palette.hp_bar
+        };
+    }
+}
+
+/// White, fading from clear in the middle to opaque at the edges, so tinting
+/// the sprite colors the vignette
+fn vignette_image() -> Image {
+    let size = VIGNETTE_RESOLUTION;
+    let mut data = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            // From 0 at the center to 1 at the nearest edge
+            let to_edge = |i: u32| ((i as f32 + 0.5) / size as f32 * 2. - 1.).abs();
+            let edge = to_edge(x).max(to_edge(y));
+            let t = ((edge - (1. - VIGNETTE_REACH)) / VIGNETTE_REACH).clamp(0., 1.);
+            let alpha = t * t * (3. - 2. * t);
+            data.extend([255, 255, 255, (alpha * 255.) as u8]);
+        }
+    }
+    Image::new(
+        Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+fn spawn_vignette(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    playfield: Res<Playfield>,
+) {
+    let mut color = WARNING_COLOR;
+    color.set_a(0.);
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color,
+                custom_size: Some(playfield.rect.size()),
+                ..default()
+            },
+            texture: images.add(vignette_image()),
+            transform: Transform::from_translation(playfield.rect.center().extend(VIGNETTE_Z)),
+            ..default()
+        },
+        Vignette::default(),
+    ));
+}
+
+/// Pulses along with the beep, brightest right as it goes off
+fn fade_vignette(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    players: Query<(&HitPoints, &MaxHitPoints), With<Player>>,
+    threshold: Res<LowHpThreshold>,
+    beep: Res<LowHpBeep>,
+    mut vignettes: Query<(&mut Vignette, &mut Sprite)>,
+) {
+    let target = if settings.low_hp_vignette && low_hp(&players, *threshold) {
+        1.
+    } else {
+        0.
+    };
+    for (mut vignette, mut sprite) in vignettes.iter_mut() {
+        let step = VIGNETTE_FADE * time.delta_seconds();
+        vignette.intensity += (target - vignette.intensity).clamp(-step, step);
+        let (low, high) = VIGNETTE_ALPHA;
+        let pulse = if settings.reduced_flashing {
+            0.5
+        } else {
+            1. - beep.0.percent()
+        };
+        sprite
+            .color
+            .set_a(vignette.intensity * (low + (high - low) * pulse));
+    }
+}
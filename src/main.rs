@@ -6,7 +6,8 @@ use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
 use bevy::winit::WinitWindows;
 use bevy::DefaultPlugins;
-use bevy_game::GamePlugin; // ToDo: Replace bevy_game with your new crate name.
+use bevy_game::mods::ModSourcePlugin;
+use bevy_game::{GamePlugin, GamePluginConfig}; // ToDo: Replace bevy_game with your new crate name.
 use std::io::Cursor;
 use winit::window::Icon;
 
@@ -15,6 +16,8 @@ fn main() {
         .insert_resource(Msaa::Off)
         .insert_resource(AssetMetaCheck::Never)
         .insert_resource(ClearColor(Color::rgb(0.4, 0.4, 0.4)))
+        // Before `DefaultPlugins`, to register where mods are loaded from
+        .add_plugins(ModSourcePlugin)
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
                 title: "Bevy game".to_string(), // ToDo
@@ -28,11 +31,20 @@ fn main() {
             }),
             ..default()
         }))
-        .add_plugins(GamePlugin)
+        .add_plugins(GamePlugin::new(GamePluginConfig {
+            // `--seed <n>` makes runs reproducible
+            seed: seed_from_args(),
+            ..default()
+        }))
         .add_systems(Startup, set_window_icon)
         .run();
 }
 
+fn seed_from_args() -> Option<u64> {
+    let mut args = std::env::args().skip_while(|arg| arg != "--seed").skip(1);
+    args.next().and_then(|seed| seed.parse().ok())
+}
+
 // Sets the icon on windows and X11
 fn set_window_icon(
     windows: NonSend<WinitWindows>,
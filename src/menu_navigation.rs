@@ -0,0 +1,120 @@
+//! Keyboard and gamepad navigation for every UI button. `Action::MenuUp` and
+//! `Action::MenuDown` (arrow keys or d-pad) move the focus and
+//! `Action::MenuConfirm` (Enter or the gamepad's south button) presses the
+//! focused button, which the regular `Interaction` handlers then pick up.
+
+use bevy::prelude::*;
+use bevy::ui::UiSystem;
+
+use crate::bindings::{Action, ActionState};
+use crate::theme::UiTheme;
+
+pub struct MenuNavigationPlugin;
+
+impl Plugin for MenuNavigationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MenuFocus>().add_systems(
+            PreUpdate,
+            (navigate_menus, highlight_focus)
+                .chain()
+                .after(UiSystem::Focus),
+        );
+    }
+}
+
+/// The button currently driven by keyboard and gamepad, and the one that was
+/// pressed that way last frame and still has to be released.
+#[derive(Resource, Default)]
+struct MenuFocus {
+    focused: Option<Entity>,
+    pressed: Option<Entity>,
+}
+
+fn navigate_menus(
+    mut focus: ResMut<MenuFocus>,
+    actions: Res<ActionState>,
+    mut buttons: Query<(Entity, &GlobalTransform, &mut Interaction), With<Button>>,
+    parents: Query<&Parent>,
+    z_indices: Query<&ZIndex>,
+) {
+    // Presses only last a single frame, like a mouse click would
+    if let Some(entity) = focus.pressed.take() {
+        if let Ok((_, _, mut interaction)) = buttons.get_mut(entity) {
+            if *interaction == Interaction::Pressed {
+                *interaction = Interaction::None;
+            }
+        }
+    }
+
+    // Only the topmost menu is navigable, e.g. the settings screen rather than
+    // the pause menu underneath it
+    let layer = |entity| {
+        let root = parents.iter_ancestors(entity).last().unwrap_or(entity);
+        match z_indices.get(root) {
+            Ok(ZIndex::Global(layer)) => *layer,
+            _ => 0,
+        }
+    };
+    // Hovering with the mouse moves the focus too, so both never disagree
+    let mut hovered = None;
+    let mut candidates = Vec::new();
+    for (entity, transform, interaction) in buttons.iter_mut() {
+        if interaction.is_changed() && *interaction == Interaction::Hovered {
+            hovered = Some(entity);
+        }
+        candidates.push((entity, layer(entity), transform.translation()));
+    }
+    let Some(top_layer) = candidates.iter().map(|(_, layer, _)| *layer).max() else {
+        focus.focused = None;
+        return;
+    };
+    candidates.retain(|(_, layer, _)| *layer == top_layer);
+    // Reading order: top to bottom, then left to right
+    candidates.sort_by(|(_, _, a), (_, _, b)| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)));
+    let order = candidates
+        .into_iter()
+        .map(|(entity, ..)| entity)
+        .collect::<Vec<_>>();
+
+    let current = match hovered.or(focus.focused) {
+        Some(entity) if order.contains(&entity) => entity,
+        _ => order[0],
+    };
+
+    let index = order.iter().position(|entity| *entity == current).unwrap();
+    let next = if actions.just_pressed(Action::MenuUp) {
+        order[(index + order.len() - 1) % order.len()]
+    } else if actions.just_pressed(Action::MenuDown) {
+        order[(index + 1) % order.len()]
+    } else {
+        current
+    };
+    focus.focused = Some(next);
+
+    if actions.just_pressed(Action::MenuConfirm) {
+        if let Ok((_, _, mut interaction)) = buttons.get_mut(next) {
+            *interaction = Interaction::Pressed;
+            focus.pressed = Some(next);
+        }
+    }
+}
+
+fn highlight_focus(
+    focus: Res<MenuFocus>,
+    theme: Option<Res<UiTheme>>,
+    mut buttons: Query<(Entity, &mut BorderColor), With<Button>>,
+) {
+    let Some(theme) = theme else {
+        return;
+    };
+    for (entity, mut border_color) in buttons.iter_mut() {
+        let color = if focus.focused == Some(entity) {
+            theme.focus_border
+        } else {
+            theme.button_border
+        };
+        if border_color.0 != color {
+            border_color.0 = color;
+        }
+    }
+}
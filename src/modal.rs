@@ -0,0 +1,187 @@
+//! Confirm/cancel dialogs shown on top of every other screen.
+//!
+//! Any system can open one with `spawn_confirm_dialog`, passing what to do if
+//! the player confirms. Cancelling, or pressing Esc, just closes the dialog.
+
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use bevy::ui::FocusPolicy;
+
+use crate::bindings::{Action, ActionState};
+use crate::locale::Locale;
+use crate::theme::UiTheme;
+
+const BUTTON_WIDTH: f32 = 200.;
+const BUTTON_HEIGHT: f32 = 65.;
+
+/// Text of a confirmation dialog
+#[derive(Clone, Debug)]
+pub struct ConfirmDialog {
+    pub title: String,
+    pub message: String,
+    pub confirm_label: String,
+    pub cancel_label: String,
+}
+
+impl ConfirmDialog {
+    /// A yes/no question, answered in the current language
+    pub fn new(locale: &Locale, title: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            message: message.into(),
+            confirm_label: locale.get("dialog-yes"),
+            cancel_label: locale.get("dialog-no"),
+        }
+    }
+}
+
+/// Root of an open dialog, holding what confirming it does
+#[derive(Component)]
+pub struct ModalDialog {
+    on_confirm: Arc<dyn Fn(&mut World) + Send + Sync>,
+}
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+enum DialogButton {
+    Confirm,
+    Cancel,
+}
+
+pub struct ModalPlugin;
+
+impl Plugin for ModalPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (dialog_buttons, cancel_on_escape).run_if(any_with_component::<ModalDialog>()),
+        );
+    }
+}
+
+/// Run condition for input handlers that should stay quiet behind a dialog
+pub fn no_modal_open(dialogs: Query<(), With<ModalDialog>>) -> bool {
+    dialogs.is_empty()
+}
+
+/// Opens a dialog on top of everything else. `on_confirm` runs once, if the
+/// player confirms, as an exclusive command.
+pub fn spawn_confirm_dialog(
+    commands: &mut Commands,
+    theme: &UiTheme,
+    dialog: ConfirmDialog,
+    on_confirm: impl Fn(&mut World) + Send + Sync + 'static,
+) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.8).into(),
+                // Keeps the mouse off the screen underneath
+                focus_policy: FocusPolicy::Block,
+                // Above everything, settings screen included
+                z_index: ZIndex::Global(2),
+                ..default()
+            },
+            ModalDialog {
+                on_confirm: Arc::new(on_confirm),
+            },
+        ))
+        .with_children(|parent| {
+            let mut title_style = theme.title.clone();
+            title_style.font_size = 56.;
+            parent.spawn(TextBundle::from_section(dialog.title, title_style));
+            parent.spawn(TextBundle::from_section(dialog.message, theme.hud.clone()));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(20.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    spawn_dialog_button(
+                        parent,
+                        theme,
+                        &dialog.confirm_label,
+                        DialogButton::Confirm,
+                    );
+                    spawn_dialog_button(parent, theme, &dialog.cancel_label, DialogButton::Cancel);
+                });
+        });
+}
+
+fn spawn_dialog_button(
+    parent: &mut ChildBuilder,
+    theme: &UiTheme,
+    label: &str,
+    button: DialogButton,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    min_width: Val::Px(BUTTON_WIDTH),
+                    height: Val::Px(BUTTON_HEIGHT),
+                    border: UiRect::all(Val::Px(5.)),
+                    padding: UiRect::horizontal(Val::Px(16.)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                border_color: BorderColor(Color::BLACK),
+                background_color: Color::WHITE.into(),
+                ..default()
+            },
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(label, theme.button.clone()));
+        });
+}
+
+fn dialog_buttons(
+    mut commands: Commands,
+    interaction_query: Query<(Entity, &Interaction, &DialogButton), Changed<Interaction>>,
+    parents: Query<&Parent>,
+    dialogs: Query<&ModalDialog>,
+) {
+    for (entity, interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let Some(root) = parents
+            .iter_ancestors(entity)
+            .find(|ancestor| dialogs.contains(*ancestor))
+        else {
+            continue;
+        };
+        if *button == DialogButton::Confirm {
+            let on_confirm = dialogs.get(root).unwrap().on_confirm.clone();
+            commands.add(move |world: &mut World| on_confirm(world));
+        }
+        commands.entity(root).despawn_recursive();
+    }
+}
+
+fn cancel_on_escape(
+    mut commands: Commands,
+    actions: Res<ActionState>,
+    dialogs: Query<Entity, With<ModalDialog>>,
+) {
+    if actions.just_pressed(Action::MenuBack) {
+        for entity in dialogs.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,334 @@
+//! Game modes. All modes share every gameplay system and only differ in what
+//! drives enemy spawns: stage mode follows the stage timelines up to their
+//! bosses, while endless mode keeps spawning enemies at random, faster and
+//! faster the longer the run lasts. Practice plays a single stage, time
+//! attack plays the stages against the clock, the daily challenge is an
+//! endless run seeded from the date so everyone gets the same one that day,
+//! and caravan is a short score attack on fixed waves, see `caravan`.
+
+use bevy::log;
+use bevy::prelude::*;
+use fluent::fluent_args;
+use serde::{Deserialize, Serialize};
+
+use crate::audio::MusicController;
+use crate::background::{BackgroundLayers, BackgroundScroll};
+use crate::banner::StageBanner;
+use crate::bindings::{Action, ActionState};
+use crate::collision::not_in_hitstop;
+use crate::difficulty::Difficulty;
+use crate::hud::{HudAnchor, HudRegion};
+use crate::locale::Locale;
+use crate::state::{AppState, GameOverEvent};
+use crate::theme::UiTheme;
+use crate::ui::{update_hud_binding, HudBinding, HudValue};
+use crate::GameSet;
+
+const ENDLESS_BACKGROUND: Color = Color::rgb(0.15, 0.15, 0.2);
+pub(crate) const ENDLESS_MUSIC: &str = "audio/flying.ogg";
+/// Seconds for the time between endless spawns to halve
+const RAMP_SECONDS: f32 = 60.;
+/// Endless spawns never come more than this many times faster than at the start
+const MAX_RAMP: f32 = 4.;
+const TIME_ATTACK_SECONDS: f32 = 180.;
+const CARAVAN_SECONDS: f32 = 120.;
+const BUTTON_WIDTH: f32 = 240.;
+const BUTTON_HEIGHT: f32 = 65.;
+
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GameMode {
+    /// Scripted stages with bosses
+    #[default]
+    Stages,
+    /// Random spawns that keep ramping up
+    Endless,
+    /// A single stage, see `practice`
+    Practice,
+    /// The stages, scored on how far a fixed amount of time gets
+    TimeAttack,
+    /// Endless, with the same spawns for every player on a given day
+    Daily,
+    /// Two minutes of dense waves, the same on every run
+    Caravan,
+}
+
+impl GameMode {
+    /// Modes on the mode select screen, with the messages labelling them
+    pub(crate) const SELECTABLE: [(GameMode, &'static str); 5] = [
+        (GameMode::Stages, "mode-stages"),
+        (GameMode::Endless, "mode-endless"),
+        (GameMode::TimeAttack, "mode-time-attack"),
+        (GameMode::Daily, "mode-daily"),
+        (GameMode::Caravan, "mode-caravan"),
+    ];
+
+    /// Runs meant to be compared between players all get the same difficulty
+    fn fixed_difficulty(self) -> Option<Difficulty> {
+        match self {
+            GameMode::Daily | GameMode::Caravan => Some(Difficulty::Normal),
+            _ => None,
+        }
+    }
+
+    /// Seconds a run lasts, for the modes played against the clock
+    fn time_limit(self) -> Option<f32> {
+        match self {
+            GameMode::TimeAttack => Some(TIME_ATTACK_SECONDS),
+            GameMode::Caravan => Some(CARAVAN_SECONDS),
+            _ => None,
+        }
+    }
+}
+
+/// How far into the current endless run the player is
+#[derive(Resource, Default, Debug)]
+pub struct EndlessRamp {
+    pub elapsed: f32,
+}
+
+impl EndlessRamp {
+    /// Multiplier on the time between regular enemy spawns
+    pub fn spawn_interval(&self) -> f32 {
+        1. / (1. + self.elapsed / RAMP_SECONDS).min(MAX_RAMP)
+    }
+}
+
+/// Time left in a timed run
+#[derive(Resource, Default)]
+pub struct TimeLimit(pub Timer);
+
+impl HudValue for TimeLimit {
+    fn hud_text(&self, locale: &Locale) -> String {
+        let seconds = self.0.remaining().as_secs_f32().ceil() as u32;
+        let time = format!("{}:{:02}", seconds / 60, seconds % 60);
+        locale.format("hud-time-left", &fluent_args!["time" => time])
+    }
+}
+
+/// Whether the stage timelines drive the run
+pub(crate) fn stage_mode(mode: Res<GameMode>) -> bool {
+    matches!(
+        *mode,
+        GameMode::Stages | GameMode::Practice | GameMode::TimeAttack
+    )
+}
+
+/// Whether the random spawner drives the run
+pub(crate) fn endless_mode(mode: Res<GameMode>) -> bool {
+    matches!(*mode, GameMode::Endless | GameMode::Daily)
+}
+
+pub(crate) fn practice_mode(mode: Res<GameMode>) -> bool {
+    *mode == GameMode::Practice
+}
+
+pub(crate) fn caravan_mode(mode: Res<GameMode>) -> bool {
+    *mode == GameMode::Caravan
+}
+
+/// Whether the run ends when a `TimeLimit` runs out
+pub(crate) fn timed_mode(mode: Res<GameMode>) -> bool {
+    mode.time_limit().is_some()
+}
+
+/// Days since the Unix epoch, in UTC, which makes the daily seed
+pub fn today() -> u64 {
+    // The web build has no wall clock through `std`, so it asks the browser
+    #[cfg(target_arch = "wasm32")]
+    return (js_sys::Date::now() / (24. * 60. * 60. * 1000.)) as u64;
+    #[cfg(not(target_arch = "wasm32"))]
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_secs() / (24 * 60 * 60))
+        .unwrap_or_default()
+}
+
+#[derive(Component)]
+struct ModeSelectScreen;
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+enum ModeButton {
+    Pick(GameMode),
+    /// Lists the saved replays, see `replay`
+    Replays,
+    Back,
+}
+
+pub struct GameModePlugin;
+
+impl Plugin for GameModePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameMode>()
+            .init_resource::<EndlessRamp>()
+            .init_resource::<TimeLimit>()
+            .add_systems(OnEnter(AppState::ModeSelect), spawn_mode_select)
+            .add_systems(OnExit(AppState::ModeSelect), despawn_mode_select)
+            .add_systems(Update, mode_buttons.run_if(in_state(AppState::ModeSelect)))
+            .add_systems(
+                OnEnter(AppState::Running),
+                (
+                    start_unstaged_run.run_if(not(stage_mode)),
+                    start_time_limit.run_if(timed_mode),
+                ),
+            )
+            .add_systems(
+                FixedUpdate,
+                tick_time_limit
+                    .run_if(timed_mode)
+                    .in_set(GameSet::Resolution),
+            )
+            .add_systems(
+                Update,
+                update_hud_binding::<TimeLimit>
+                    .run_if(timed_mode.and_then(in_state(AppState::Running))),
+            )
+            .add_systems(
+                FixedUpdate,
+                advance_endless_ramp
+                    .run_if(endless_mode.and_then(not_in_hitstop))
+                    .in_set(GameSet::Movement),
+            );
+    }
+}
+
+fn spawn_mode_select(mut commands: Commands, theme: Res<UiTheme>, locale: Res<Locale>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.),
+                    ..default()
+                },
+                ..default()
+            },
+            ModeSelectScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(locale.text("mode-select", theme.title.clone()));
+            for (mode, label) in GameMode::SELECTABLE {
+                spawn_mode_button(parent, &theme, &locale, label, ModeButton::Pick(mode));
+            }
+            spawn_mode_button(parent, &theme, &locale, "mode-replays", ModeButton::Replays);
+            spawn_mode_button(parent, &theme, &locale, "button-back", ModeButton::Back);
+        });
+}
+
+fn spawn_mode_button(
+    parent: &mut ChildBuilder,
+    theme: &UiTheme,
+    locale: &Locale,
+    label: &'static str,
+    button: ModeButton,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    min_width: Val::Px(BUTTON_WIDTH),
+                    height: Val::Px(BUTTON_HEIGHT),
+                    border: UiRect::all(Val::Px(5.)),
+                    padding: UiRect::horizontal(Val::Px(16.)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                border_color: BorderColor(Color::BLACK),
+                background_color: Color::WHITE.into(),
+                ..default()
+            },
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn(locale.text(label, theme.button.clone()));
+        });
+}
+
+fn despawn_mode_select(mut commands: Commands, query: Query<Entity, With<ModeSelectScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn mode_buttons(
+    actions: Res<ActionState>,
+    interaction_query: Query<(&Interaction, &ModeButton), Changed<Interaction>>,
+    mut mode: ResMut<GameMode>,
+    mut difficulty: ResMut<Difficulty>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if actions.just_pressed(Action::MenuBack) {
+        next_state.set(AppState::MainMenu);
+    }
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match *button {
+            ModeButton::Pick(picked) => {
+                *mode = picked;
+                match picked.fixed_difficulty() {
+                    Some(fixed) => {
+                        *difficulty = fixed;
+                        next_state.set(AppState::Running);
+                    }
+                    None => next_state.set(AppState::DifficultySelect),
+                }
+            }
+            ModeButton::Replays => next_state.set(AppState::Replays),
+            ModeButton::Back => next_state.set(AppState::MainMenu),
+        }
+    }
+}
+
+/// Runs without a stage to set the scene get it set here instead
+fn start_unstaged_run(
+    mut commands: Commands,
+    mode: Res<GameMode>,
+    mut music: ResMut<MusicController>,
+    mut stage_banner: ResMut<StageBanner>,
+    locale: Res<Locale>,
+) {
+    log::info!("Starting {:?} run", *mode);
+    *stage_banner = StageBanner {
+        text: locale.get(match *mode {
+            GameMode::Daily => "banner-daily",
+            GameMode::Caravan => "banner-caravan",
+            _ => "banner-endless",
+        }),
+    };
+    commands.insert_resource(EndlessRamp::default());
+    commands.insert_resource(ClearColor(ENDLESS_BACKGROUND));
+    commands.insert_resource(BackgroundScroll::default());
+    commands.insert_resource(BackgroundLayers::default());
+    music.play(Some(ENDLESS_MUSIC));
+}
+
+fn start_time_limit(mut commands: Commands, mode: Res<GameMode>, theme: Res<UiTheme>) {
+    let seconds = mode.time_limit().unwrap_or_default();
+    commands.insert_resource(TimeLimit(Timer::from_seconds(seconds, TimerMode::Once)));
+    commands.spawn((
+        TextBundle::from_section("", theme.hud.clone()),
+        HudBinding::<TimeLimit>::default(),
+        HudAnchor(HudRegion::TopRight),
+    ));
+}
+
+fn tick_time_limit(
+    time: Res<Time>,
+    mut limit: ResMut<TimeLimit>,
+    mut game_over_events: EventWriter<GameOverEvent>,
+) {
+    if limit.0.tick(time.delta()).just_finished() {
+        log::info!("Time up");
+        game_over_events.send_default();
+    }
+}
+
+fn advance_endless_ramp(time: Res<Time>, mut ramp: ResMut<EndlessRamp>) {
+    ramp.elapsed += time.delta_seconds();
+}
@@ -0,0 +1,196 @@
+//! Mods: folders under `mods/`, next to `assets/`, that add to the game's
+//! content or replace parts of it. Each mod can have any of
+//!
+//! - `enemies/<name>.enemy.ron`, an enemy definition
+//! - `patterns/<name>.pattern.ron`, a bullet pattern
+//! - `stages/<name>.timeline.ron`, the timeline of the stage called `<name>`,
+//!   or of a new stage after the others that looks like the first one
+//! - `sprites.png`, a sprite sheet laid out like the game's own
+//!
+//! Mods are loaded once at startup, in the order listed in
+//! `mods/load_order.txt`, one folder name per line, followed by any mods it
+//! leaves out in alphabetical order. Whatever a later mod has replaces what
+//! came before, and every replacement is logged along with what it replaced.
+//!
+//! Only native builds look for mods, and only once `ModSourcePlugin` has been
+//! added ahead of Bevy's `AssetPlugin`.
+
+use std::path::{Path, PathBuf};
+
+use bevy::log;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::enemies::EnemyDefinitions;
+use crate::patterns::BulletPatterns;
+use crate::sprites::SpriteSheet;
+use crate::stage::Stages;
+
+/// Folder next to `assets/` that mods are in
+const MODS_PATH: &str = "mods";
+/// Asset source reading from `MODS_PATH`
+const MODS_SOURCE: &str = "mods";
+/// Lists mods in the order they should load, one per line
+const LOAD_ORDER_FILE: &str = "load_order.txt";
+
+/// Where the mods are, when they can be loaded at all
+#[derive(Resource)]
+struct ModsDir(PathBuf);
+
+/// Makes mods loadable as assets. Has to be added before `DefaultPlugins`,
+/// whose `AssetPlugin` freezes the asset sources.
+pub struct ModSourcePlugin;
+
+impl Plugin for ModSourcePlugin {
+    fn build(&self, app: &mut App) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            use bevy::asset::io::file::FileAssetReader;
+            use bevy::asset::io::AssetSource;
+            use bevy::asset::AssetApp;
+
+            app.register_asset_source(
+                MODS_SOURCE,
+                AssetSource::build()
+                    .with_reader(AssetSource::get_default_reader(MODS_PATH.to_string()))
+                    .with_watcher(AssetSource::get_default_watcher(
+                        MODS_PATH.to_string(),
+                        std::time::Duration::from_millis(300),
+                    )),
+            )
+            .insert_resource(ModsDir(FileAssetReader::get_base_path().join(MODS_PATH)));
+        }
+    }
+}
+
+pub struct ModsPlugin;
+
+impl Plugin for ModsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load_mods);
+    }
+}
+
+/// Who provides each piece of content, to tell which mod replaced what
+#[derive(Default)]
+struct Providers(HashMap<(&'static str, String), String>);
+
+impl Providers {
+    fn base_game(&mut self, kind: &'static str, names: impl IntoIterator<Item = String>) {
+        for name in names {
+            self.0.insert((kind, name), "the base game".to_string());
+        }
+    }
+
+    fn provide(&mut self, kind: &'static str, name: &str, by: &str) {
+        let previous = self.0.insert((kind, name.to_string()), format!("mod {by}"));
+        if let Some(previous) = previous {
+            log::warn!("Mod {by} replaces the {kind} {name} from {previous}");
+        }
+    }
+}
+
+fn load_mods(
+    dir: Option<Res<ModsDir>>,
+    asset_server: Res<AssetServer>,
+    mut enemies: ResMut<EnemyDefinitions>,
+    mut patterns: ResMut<BulletPatterns>,
+    mut stages: ResMut<Stages>,
+    mut sprite_sheet: ResMut<SpriteSheet>,
+) {
+    let Some(dir) = dir else {
+        return;
+    };
+    let mods = load_order(&dir.0);
+    if mods.is_empty() {
+        return;
+    }
+    log::info!("Loading mods in this order: {}", mods.join(", "));
+
+    let mut providers = Providers::default();
+    providers.base_game("enemy", enemies.0.keys().cloned());
+    providers.base_game("bullet pattern", patterns.0.keys().cloned());
+    providers.base_game(
+        "stage",
+        stages.0.iter().map(|stage| stage.name.to_lowercase()),
+    );
+    providers.base_game("sprite sheet", ["sprites.png".to_string()]);
+
+    for name in &mods {
+        let root = dir.0.join(name);
+        let path = |file: &str| format!("{MODS_SOURCE}://{name}/{file}");
+        for (enemy, file) in files_ending_in(&root.join("enemies"), ".enemy.ron") {
+            providers.provide("enemy", &enemy, name);
+            let handle = asset_server.load(path(&format!("enemies/{file}")));
+            enemies.0.insert(enemy, handle);
+        }
+        for (pattern, file) in files_ending_in(&root.join("patterns"), ".pattern.ron") {
+            providers.provide("bullet pattern", &pattern, name);
+            let handle = asset_server.load(path(&format!("patterns/{file}")));
+            patterns.0.insert(pattern, handle);
+        }
+        for (stage_name, file) in files_ending_in(&root.join("stages"), ".timeline.ron") {
+            providers.provide("stage", &stage_name.to_lowercase(), name);
+            let timeline = asset_server.load(path(&format!("stages/{file}")));
+            let existing = stages
+                .0
+                .iter_mut()
+                .find(|stage| stage.name.eq_ignore_ascii_case(&stage_name));
+            match existing {
+                Some(stage) => stage.timeline = timeline,
+                None => {
+                    let mut stage = stages.0[0].clone();
+                    stage.name = stage_name;
+                    stage.timeline = timeline;
+                    stages.0.push(stage);
+                }
+            }
+        }
+        if root.join("sprites.png").is_file() {
+            providers.provide("sprite sheet", "sprites.png", name);
+            sprite_sheet.image = asset_server.load(path("sprites.png"));
+        }
+    }
+}
+
+/// Every mod folder, in the order they load
+fn load_order(dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut found = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect::<Vec<_>>();
+    found.sort();
+
+    let listed = std::fs::read_to_string(dir.join(LOAD_ORDER_FILE)).unwrap_or_default();
+    let mut order = Vec::new();
+    for name in listed.lines().map(str::trim) {
+        if name.is_empty() || name.starts_with('#') {
+            continue;
+        }
+        match found.iter().position(|found| found == name) {
+            Some(index) => order.push(found.remove(index)),
+            None => log::warn!("{LOAD_ORDER_FILE} lists the mod {name}, which isn't there"),
+        }
+    }
+    order.extend(found);
+    order
+}
+
+/// Names and file names of the files in `dir` ending in `suffix`, with the
+/// suffix taken off the names
+fn files_ending_in(dir: &Path, suffix: &str) -> Vec<(String, String)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter_map(|file| Some((file.strip_suffix(suffix)?.to_string(), file.clone())))
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
@@ -0,0 +1,100 @@
+//! Muzzle flashes. Every shot lights up a short flash at the gun's muzzle,
+//! shrinking and fading within a few frames. Flashes are children of the
+//! shooter, so they move along with it. Every gun brings its own flash.
+//!
+//! Guns firing fast would strobe, so there are none with reduced flashing.
+
+use bevy::prelude::*;
+
+use crate::settings::Settings;
+use crate::state::gameplay_active;
+
+/// In front of the shooter
+const FLASH_Z: f32 = 1.;
+
+/// What a gun's muzzle flash looks like
+#[derive(Clone, Copy, Debug)]
+pub struct MuzzleFlash {
+    pub color: Color,
+    /// Side of the flash when it lights up
+    pub size: f32,
+    pub seconds: f32,
+}
+
+pub(crate) const PLAYER_GUN_FLASH: MuzzleFlash = MuzzleFlash {
+    color: Color::rgb(1., 1., 0.6),
+    size: 18.,
+    seconds: 0.05,
+};
+
+pub(crate) const ENEMY_GUN_FLASH: MuzzleFlash = MuzzleFlash {
+    color: Color::ORANGE_RED,
+    size: 16.,
+    seconds: 0.08,
+};
+
+pub(crate) const BOSS_GUN_FLASH: MuzzleFlash = MuzzleFlash {
+    color: Color::ORANGE_RED,
+    size: 32.,
+    seconds: 0.12,
+};
+
+pub struct MuzzleFlashPlugin;
+
+impl Plugin for MuzzleFlashPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, fade_muzzle_flashes.run_if(gameplay_active));
+    }
+}
+
+#[derive(Component)]
+struct Flash {
+    size: f32,
+    seconds: f32,
+    elapsed: f32,
+}
+
+/// Lights up `flash` on `shooter`, `muzzle` away from its center
+pub(crate) fn spawn_muzzle_flash(
+    commands: &mut Commands,
+    shooter: Entity,
+    flash: &MuzzleFlash,
+    muzzle: Vec2,
+) {
+    commands.entity(shooter).with_children(|parent| {
+        parent.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: flash.color,
+                    custom_size: Some(Vec2::splat(flash.size)),
+                    ..default()
+                },
+                transform: Transform::from_translation(muzzle.extend(FLASH_Z)),
+                ..default()
+            },
+            Flash {
+                size: flash.size,
+                seconds: flash.seconds,
+                elapsed: 0.,
+            },
+        ));
+    });
+}
+
+fn fade_muzzle_flashes(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut query: Query<(Entity, &mut Flash, &mut Sprite)>,
+) {
+    for (entity, mut flash, mut sprite) in query.iter_mut() {
+        flash.elapsed += time.delta_seconds();
+        if flash.elapsed >= flash.seconds || settings.reduced_flashing {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let remaining = 1. - flash.elapsed / flash.seconds;
+        sprite.custom_size = Some(Vec2::splat(flash.size * remaining));
+        sprite.color.set_a(remaining);
+    }
+}
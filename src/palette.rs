@@ -0,0 +1,279 @@
+//! Palettes, the colors the whole game is drawn in. `Palette` holds the one in
+//! use: each stage brings its own, unless the palette setting picks one for
+//! every run. Anything already on screen is repainted when it changes, so the
+//! game can be reskinned mid-run.
+//!
+//! The colorblind setting goes on top of whichever palette that is: it
+//! swaps the bullet colors for a pair told apart with that kind of color
+//! blindness, and has bullets told apart by shape as well, see
+//! `bullet_material`.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::bullet::{bullet_material, Bullet, Hostility};
+use crate::collision::HitFlash;
+use crate::config::GameConfig;
+use crate::enemy::Enemy;
+use crate::glow::GlowMaterial;
+use crate::mode::{stage_mode, GameMode};
+use crate::player::Player;
+use crate::settings::Settings;
+use crate::stage::{StageProgress, Stages};
+use crate::state::AppState;
+use crate::ui::HpBarFill;
+
+/// Drawn in its own color whatever the palette, like bosses
+#[derive(Component)]
+pub(crate) struct OwnColor;
+
+#[derive(Resource, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Palette {
+    pub player: Color,
+    /// What the player flashes when hit
+    pub player_hit: Color,
+    pub enemy: Color,
+    pub enemy_hit: Color,
+    pub friendly_bullet: Color,
+    pub hostile_bullet: Color,
+    /// Titles and HUD text
+    pub text: Color,
+    pub hp_bar: Color,
+    pub life_icon: Color,
+    pub bomb_icon: Color,
+    /// Tell friendly and hostile bullets apart by shape, not only color
+    pub distinct_bullets: bool,
+}
+
+impl Palette {
+    pub const CLASSIC: Palette = Palette {
+        player: Color::WHITE,
+        player_hit: Color::RED,
+        enemy: Color::GRAY,
+        enemy_hit: Color::WHITE,
+        friendly_bullet: Color::YELLOW,
+        hostile_bullet: Color::YELLOW,
+        text: Color::WHITE,
+        hp_bar: Color::LIME_GREEN,
+        life_icon: Color::WHITE,
+        bomb_icon: Color::ORANGE,
+        distinct_bullets: false,
+    };
+
+    pub const NEON: Palette = Palette {
+        player: Color::CYAN,
+        player_hit: Color::RED,
+        enemy: Color::FUCHSIA,
+        enemy_hit: Color::WHITE,
+        friendly_bullet: Color::rgb(0.3, 1., 1.),
+        hostile_bullet: Color::rgb(1., 0.3, 0.8),
+        text: Color::rgb(0.8, 1., 1.),
+        hp_bar: Color::CYAN,
+        life_icon: Color::CYAN,
+        bomb_icon: Color::FUCHSIA,
+        distinct_bullets: false,
+    };
+
+    pub const EMBER: Palette = Palette {
+        player: Color::rgb(1., 0.9, 0.7),
+        player_hit: Color::rgb(0.6, 0., 0.),
+        enemy: Color::rgb(0.5, 0.3, 0.25),
+        enemy_hit: Color::rgb(1., 0.9, 0.6),
+        friendly_bullet: Color::rgb(1., 0.9, 0.5),
+        hostile_bullet: Color::rgb(1., 0.4, 0.1),
+        text: Color::rgb(1., 0.9, 0.8),
+        hp_bar: Color::ORANGE,
+        life_icon: Color::rgb(1., 0.9, 0.7),
+        bomb_icon: Color::rgb(1., 0.4, 0.1),
+        distinct_bullets: false,
+    };
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::CLASSIC
+    }
+}
+
+/// The palette setting
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PaletteChoice {
+    /// Whatever the stage being played brings
+    #[default]
+    Stage,
+    Classic,
+    Neon,
+    Ember,
+}
+
+impl PaletteChoice {
+    pub(crate) fn next(self) -> Self {
+        match self {
+            PaletteChoice::Stage => PaletteChoice::Classic,
+            PaletteChoice::Classic => PaletteChoice::Neon,
+            PaletteChoice::Neon => PaletteChoice::Ember,
+            PaletteChoice::Ember => PaletteChoice::Stage,
+        }
+    }
+
+    /// Message naming the choice
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            PaletteChoice::Stage => "palette-stage",
+            PaletteChoice::Classic => "palette-classic",
+            PaletteChoice::Neon => "palette-neon",
+            PaletteChoice::Ember => "palette-ember",
+        }
+    }
+
+    /// The palette picked, `None` to leave it to the stage
+    fn palette(self, classic: Palette) -> Option<Palette> {
+        match self {
+            PaletteChoice::Stage => None,
+            PaletteChoice::Classic => Some(classic),
+            PaletteChoice::Neon => Some(Palette::NEON),
+            PaletteChoice::Ember => Some(Palette::EMBER),
+        }
+    }
+}
+
+/// The colorblind setting
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ColorblindMode {
+    #[default]
+    Off,
+    /// Red and green look alike
+    Deuteranopia,
+    /// Red and green look alike, and reds look dark
+    Protanopia,
+    /// Blue and green look alike, and so do yellow and pink
+    Tritanopia,
+}
+
+impl ColorblindMode {
+    pub(crate) fn next(self) -> Self {
+        match self {
+            ColorblindMode::Off => ColorblindMode::Deuteranopia,
+            ColorblindMode::Deuteranopia => ColorblindMode::Protanopia,
+            ColorblindMode::Protanopia => ColorblindMode::Tritanopia,
+            ColorblindMode::Tritanopia => ColorblindMode::Off,
+        }
+    }
+
+    /// Message naming the mode
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            ColorblindMode::Off => "settings-off",
+            ColorblindMode::Deuteranopia => "colorblind-deuteranopia",
+            ColorblindMode::Protanopia => "colorblind-protanopia",
+            ColorblindMode::Tritanopia => "colorblind-tritanopia",
+        }
+    }
+
+    /// Friendly and hostile bullet colors, from the Okabe-Ito palette
+    fn bullet_colors(self) -> Option<(Color, Color)> {
+        let sky_blue = Color::rgb_u8(86, 180, 233);
+        match self {
+            ColorblindMode::Off => None,
+            ColorblindMode::Deuteranopia => Some((sky_blue, Color::rgb_u8(230, 159, 0))),
+            ColorblindMode::Protanopia => Some((sky_blue, Color::rgb_u8(240, 228, 66))),
+            ColorblindMode::Tritanopia => Some((sky_blue, Color::rgb_u8(213, 94, 0))),
+        }
+    }
+
+    /// `palette` as drawn in this mode
+    fn adapt(self, palette: Palette) -> Palette {
+        let Some((friendly_bullet, hostile_bullet)) = self.bullet_colors() else {
+            return palette;
+        };
+        Palette {
+            friendly_bullet,
+            hostile_bullet,
+            distinct_bullets: true,
+            ..palette
+        }
+    }
+}
+
+pub struct PalettePlugin;
+
+impl Plugin for PalettePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Palette>().add_systems(
+            Update,
+            (
+                choose_palette,
+                (repaint_ships, repaint_bullets, repaint_ui).run_if(resource_changed::<Palette>()),
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Outside of runs through the stages, the classic palette is used. The
+/// colorblind setting applies to all of them.
+pub(crate) fn choose_palette(
+    settings: Res<Settings>,
+    mode: Res<GameMode>,
+    state: Res<State<AppState>>,
+    stages: Res<Stages>,
+    progress: Res<StageProgress>,
+    config: Res<GameConfig>,
+    mut palette: ResMut<Palette>,
+) {
+    let classic = config.classic_palette;
+    let in_run = matches!(state.get(), AppState::Running | AppState::GameOver);
+    let wanted = settings.palette.palette(classic).unwrap_or_else(|| {
+        if in_run && stage_mode(mode) {
+            progress.stage(&stages).palette
+        } else {
+            classic
+        }
+    });
+    palette.set_if_neq(settings.colorblind.adapt(wanted));
+}
+
+fn repaint_ships(
+    palette: Res<Palette>,
+    mut query: Query<
+        (&mut HitFlash, &Handle<ColorMaterial>, Has<Player>),
+        (Or<(With<Player>, With<Enemy>)>, Without<OwnColor>),
+    >,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (mut flash, handle, is_player) in query.iter_mut() {
+        (flash.color, flash.flash_color) = if is_player {
+            (palette.player, palette.player_hit)
+        } else {
+            (palette.enemy, palette.enemy_hit)
+        };
+        // A flash under way ends on the new color
+        if !flash.timer.finished() {
+            continue;
+        }
+        if let Some(material) = materials.get_mut(handle) {
+            material.color = flash.color;
+        }
+    }
+}
+
+fn repaint_bullets(
+    palette: Res<Palette>,
+    query: Query<(&Hostility, &Handle<GlowMaterial>), With<Bullet>>,
+    mut materials: ResMut<Assets<GlowMaterial>>,
+) {
+    for (hostility, handle) in query.iter() {
+        if let Some(material) = materials.get_mut(handle) {
+            *material = bullet_material(&palette, matches!(hostility, Hostility::Hostile));
+        }
+    }
+}
+
+/// Recolors the HP bar. The HUD icon rows rebuild themselves, and the text
+/// is recolored along with the rest of the `UiTheme`.
+fn repaint_ui(palette: Res<Palette>, mut fills: Query<&mut BackgroundColor, With<HpBarFill>>) {
+    for mut color in fills.iter_mut() {
+        color.0 = palette.hp_bar;
+    }
+}
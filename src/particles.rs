@@ -0,0 +1,178 @@
+//! Particle bursts. Enemy deaths, bullet impacts, the player losing a life and
+//! any `ExplosionEvent` throw out a burst of small squares that fly apart,
+//! shrinking and fading until they are gone. The number alive at once is
+//! capped, so a screen full of explosions doesn't eat into the frame time;
+//! bursts past the cap come out smaller, or not at all. Sparks and explosions
+//! blend additively, so overlapping bursts flare up.
+
+use bevy::prelude::*;
+use bevy::sprite::MaterialMesh2dBundle;
+use rand::Rng;
+
+use crate::collision::CollisionEvent;
+use crate::enemy::EnemyKilledEvent;
+use crate::glow::{BlendMode, GlowMaterial};
+use crate::player::LifeLostEvent;
+use crate::state::gameplay_active;
+
+/// An explosion with no enemy behind it, like the ones going off across a
+/// dying boss
+#[derive(Event)]
+pub struct ExplosionEvent {
+    pub position: Vec2,
+}
+
+/// Most particles alive at once
+const PARTICLE_BUDGET: usize = 600;
+/// In front of the ships and bullets, behind the damage numbers
+const PARTICLE_Z: f32 = 5.;
+
+struct Burst {
+    count: usize,
+    color: Color,
+    /// Side of every square when it spawns
+    size: f32,
+    /// Slowest and fastest a particle can fly off at
+    speed: (f32, f32),
+    lifetime_seconds: f32,
+    blend: BlendMode,
+}
+
+const IMPACT_BURST: Burst = Burst {
+    count: 4,
+    color: Color::YELLOW,
+    size: 4.,
+    speed: (80., 160.),
+    lifetime_seconds: 0.2,
+    blend: BlendMode::Additive,
+};
+
+const ENEMY_BURST: Burst = Burst {
+    count: 24,
+    color: Color::ORANGE,
+    size: 8.,
+    speed: (60., 260.),
+    lifetime_seconds: 0.6,
+    blend: BlendMode::Additive,
+};
+
+const PLAYER_BURST: Burst = Burst {
+    count: 60,
+    color: Color::WHITE,
+    size: 10.,
+    speed: (40., 360.),
+    lifetime_seconds: 1.,
+    blend: BlendMode::Alpha,
+};
+
+pub struct ParticlesPlugin;
+
+impl Plugin for ParticlesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ExplosionEvent>()
+            .init_resource::<ParticleMesh>()
+            .add_systems(
+                Update,
+                (spawn_bursts, animate_particles)
+                    .chain()
+                    .run_if(gameplay_active),
+            );
+    }
+}
+
+/// A unit square, scaled to every particle's size
+#[derive(Resource)]
+struct ParticleMesh(Handle<Mesh>);
+
+impl FromWorld for ParticleMesh {
+    fn from_world(world: &mut World) -> Self {
+        let mut meshes = world.resource_mut::<Assets<Mesh>>();
+        Self(meshes.add(shape::Quad::new(Vec2::ONE).into()))
+    }
+}
+
+#[derive(Component)]
+struct Particle {
+    velocity: Vec2,
+    size: f32,
+    lifetime_seconds: f32,
+    elapsed: f32,
+}
+
+fn spawn_bursts(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut kill_events: EventReader<EnemyKilledEvent>,
+    mut explosion_events: EventReader<ExplosionEvent>,
+    mut life_lost_events: EventReader<LifeLostEvent>,
+    particles: Query<(), With<Particle>>,
+    mesh: Res<ParticleMesh>,
+    mut materials: ResMut<Assets<GlowMaterial>>,
+) {
+    // The player's burst goes first, so it is the last to be cut
+    let bursts = life_lost_events
+        .read()
+        .map(|event| (event.position, &PLAYER_BURST))
+        .chain(
+            kill_events
+                .read()
+                .map(|event| (event.position, &ENEMY_BURST)),
+        )
+        .chain(
+            explosion_events
+                .read()
+                .map(|event| (event.position, &ENEMY_BURST)),
+        )
+        .chain(
+            collision_events
+                .read()
+                .map(|event| (event.position, &IMPACT_BURST)),
+        );
+    let mut budget = PARTICLE_BUDGET.saturating_sub(particles.iter().count());
+    let mut rng = rand::thread_rng();
+    for (position, burst) in bursts {
+        let count = burst.count.min(budget);
+        budget -= count;
+        for _ in 0..count {
+            let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+            let speed = rng.gen_range(burst.speed.0..burst.speed.1);
+            commands.spawn((
+                // Every particle fades on its own, so they can't share a material
+                MaterialMesh2dBundle {
+                    mesh: mesh.0.clone().into(),
+                    material: materials.add(GlowMaterial::new(burst.color, burst.blend)),
+                    transform: Transform::from_translation(position.extend(PARTICLE_Z))
+                        .with_scale(Vec3::splat(burst.size)),
+                    ..default()
+                },
+                Particle {
+                    velocity: Vec2::from_angle(angle) * speed,
+                    size: burst.size,
+                    lifetime_seconds: burst.lifetime_seconds,
+                    elapsed: 0.,
+                },
+            ));
+        }
+    }
+}
+
+fn animate_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Particle, &mut Transform, &Handle<GlowMaterial>)>,
+    mut materials: ResMut<Assets<GlowMaterial>>,
+) {
+    for (entity, mut particle, mut transform, material) in query.iter_mut() {
+        particle.elapsed += time.delta_seconds();
+        if particle.elapsed >= particle.lifetime_seconds {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        transform.translation += (particle.velocity * time.delta_seconds()).extend(0.);
+        let remaining = 1. - particle.elapsed / particle.lifetime_seconds;
+        transform.scale = Vec2::splat(particle.size * remaining).extend(1.);
+        if let Some(material) = materials.get_mut(material) {
+            material.color.set_a(remaining);
+        }
+    }
+}
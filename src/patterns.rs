@@ -0,0 +1,219 @@
+//! Bullet patterns: how many bullets a gun fires at once and how they fan
+//! out, as RON assets under `assets/patterns/`. Enemy definitions name the
+//! pattern their gun fires and stage waves can swap it for another. With the
+//! `dev` feature the files are watched, so edits apply to the next volley.
+//!
+//! Patterns are checked as they load, and one that doesn't make sense fails
+//! with a message saying what is wrong with it. Guns whose pattern is missing,
+//! unknown or failed to load fire a single bullet straight down.
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::ecs::system::SystemParam;
+use bevy::log;
+use bevy::prelude::*;
+use bevy::utils::{BoxedFuture, HashMap};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::enemies::EnemyDefinition;
+use crate::state::AppState;
+
+/// Every bullet pattern, by name
+const PATTERN_PATHS: [(&str, &str); 2] = [
+    ("fan", "patterns/fan.pattern.ron"),
+    ("ring", "patterns/ring.pattern.ron"),
+];
+/// More than this many bullets in a volley is almost certainly a typo
+const MAX_BULLETS: u32 = 64;
+
+#[derive(Asset, TypePath, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BulletPattern {
+    /// Bullets in every volley
+    pub bullets: u32,
+    /// Degrees between the first bullet and the last, with the rest spread
+    /// evenly between them
+    #[serde(default)]
+    pub spread: f32,
+    /// Degrees the middle of the volley is turned from straight down,
+    /// counterclockwise
+    #[serde(default)]
+    pub angle: f32,
+    /// Multiplier on the gun's bullet speed
+    #[serde(default = "BulletPattern::default_speed")]
+    pub speed: f32,
+}
+
+impl BulletPattern {
+    /// A single bullet straight down
+    pub const SINGLE: BulletPattern = BulletPattern {
+        bullets: 1,
+        spread: 0.,
+        angle: 0.,
+        speed: 1.,
+    };
+
+    fn default_speed() -> f32 {
+        1.
+    }
+
+    /// Velocity of every bullet in a volley, for bullets going `speed` fast
+    /// before the pattern's own multiplier
+    pub fn velocities(&self, speed: f32) -> impl Iterator<Item = Vec2> + '_ {
+        let step = if self.bullets > 1 {
+            self.spread / (self.bullets - 1) as f32
+        } else {
+            0.
+        };
+        let first = self.angle - self.spread / 2.;
+        (0..self.bullets).map(move |i| {
+            let angle = (first + step * i as f32).to_radians();
+            Vec2::from_angle(angle).rotate(Vec2::NEG_Y) * speed * self.speed
+        })
+    }
+
+    /// Everything wrong with the pattern, if anything
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !(1..=MAX_BULLETS).contains(&self.bullets) {
+            problems.push(format!(
+                "`bullets` must be between 1 and {MAX_BULLETS}, got {}",
+                self.bullets
+            ));
+        }
+        if !(0. ..=360.).contains(&self.spread) {
+            problems.push(format!(
+                "`spread` must be between 0 and 360 degrees, got {}",
+                self.spread
+            ));
+        }
+        if !self.angle.is_finite() {
+            problems.push(format!("`angle` must be a number, got {}", self.angle));
+        }
+        if !(self.speed > 0. && self.speed.is_finite()) {
+            problems.push(format!("`speed` must be above 0, got {}", self.speed));
+        }
+        problems
+    }
+}
+
+/// Handles to every bullet pattern, by name
+#[derive(Resource, Debug)]
+pub struct BulletPatterns(pub HashMap<String, Handle<BulletPattern>>);
+
+impl FromWorld for BulletPatterns {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        Self(
+            PATTERN_PATHS
+                .into_iter()
+                .map(|(name, path)| (name.to_string(), asset_server.load(path)))
+                .collect(),
+        )
+    }
+}
+
+/// Looks up bullet patterns by name
+#[derive(SystemParam)]
+pub struct PatternBook<'w> {
+    patterns: Res<'w, BulletPatterns>,
+    assets: Res<'w, Assets<BulletPattern>>,
+}
+
+impl PatternBook<'_> {
+    /// The pattern called `name`, or a single bullet for `None`
+    pub fn get(&self, name: Option<&str>) -> &BulletPattern {
+        name.and_then(|name| self.patterns.0.get(name))
+            .and_then(|handle| self.assets.get(handle))
+            .unwrap_or(&BulletPattern::SINGLE)
+    }
+}
+
+#[derive(Default)]
+pub struct BulletPatternLoader;
+
+#[derive(Debug, Error)]
+pub enum BulletPatternLoaderError {
+    #[error("could not read the bullet pattern: {0}")]
+    Io(#[from] std::io::Error),
+    #[error(
+        "could not parse the bullet pattern, at line {}, column {}: {}",
+        .0.position.line,
+        .0.position.col,
+        .0.code
+    )]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("invalid bullet pattern: {}", .0.join("; "))]
+    Invalid(Vec<String>),
+}
+
+impl AssetLoader for BulletPatternLoader {
+    type Asset = BulletPattern;
+    type Settings = ();
+    type Error = BulletPatternLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<BulletPattern, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let pattern: BulletPattern = ron::de::from_bytes(&bytes)?;
+            let problems = pattern.problems();
+            if !problems.is_empty() {
+                return Err(BulletPatternLoaderError::Invalid(problems));
+            }
+            Ok(pattern)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["pattern.ron"]
+    }
+}
+
+pub struct PatternsPlugin;
+
+impl Plugin for PatternsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<BulletPattern>()
+            .init_asset_loader::<BulletPatternLoader>()
+            .init_resource::<BulletPatterns>()
+            .add_systems(OnExit(AppState::Loading), check_pattern_names)
+            .add_systems(Update, log_pattern_reloads);
+    }
+}
+
+/// Warns about enemies asking for a pattern that doesn't exist, which would
+/// otherwise quietly fire single bullets. Stage waves are checked along with
+/// the rest of their timeline.
+fn check_pattern_names(
+    patterns: Res<BulletPatterns>,
+    enemies: Res<Assets<EnemyDefinition>>,
+    asset_server: Res<AssetServer>,
+) {
+    for (id, enemy) in enemies.iter() {
+        if let Some(name) = &enemy.gun.pattern {
+            if !patterns.0.contains_key(name) {
+                let path = asset_server.get_path(id);
+                log::warn!("{path:?} asks for an unknown bullet pattern {name:?}");
+            }
+        }
+    }
+}
+
+fn log_pattern_reloads(
+    mut events: EventReader<AssetEvent<BulletPattern>>,
+    asset_server: Res<AssetServer>,
+) {
+    for event in events.read() {
+        if let AssetEvent::Modified { id } = event {
+            let path = asset_server.get_path(*id);
+            log::info!("Reloaded bullet pattern {path:?}");
+        }
+    }
+}
@@ -0,0 +1,374 @@
+//! The player's ship: reading input into `TickInput` once per fixed tick,
+//! moving and shooting with it, and taking hits. Lives and bombs in stock are
+//! kept here too.
+//!
+//! Apps can drive the ship themselves, with an AI, a replay or input coming
+//! over the network, by switching `PlayerController` to `External` and writing
+//! `TickInput` from a system in `PlayerControllerSet`. Everything past that
+//! works the same as with the keyboard.
+
+use bevy::log;
+use bevy::prelude::*;
+use bevy::sprite::MaterialMesh2dBundle;
+use serde::{Deserialize, Serialize};
+
+use crate::audio::{PlaySfx, SfxId};
+use crate::bindings::{Action, ActionState};
+use crate::bullet::{create_bullet, Gun, Hostility, Knockback};
+use crate::collision::{Collider, ColliderShape, HitEvent, HitFlash};
+use crate::config::GameConfig;
+use crate::exhaust::Exhaust;
+use crate::glow::GlowMaterial;
+use crate::muzzle_flash::{spawn_muzzle_flash, PLAYER_GUN_FLASH};
+use crate::palette::Palette;
+use crate::score::{reset_score, track_run_stats, RunStats};
+use crate::settings::Settings;
+use crate::sprites::{SpriteArt, SpriteId};
+use crate::state::{AppState, GameOverEvent};
+use crate::{DeathEvent, DeathKind, GameSet, HitPoints, MaxHitPoints, Playfield};
+
+#[derive(Component)]
+pub struct Player;
+
+/// Extra lives left. Losing all HP with a life in stock refills it instead of
+/// ending the run.
+#[derive(Resource)]
+pub struct Lives(pub u32);
+
+impl FromWorld for Lives {
+    fn from_world(world: &mut World) -> Self {
+        Self(world.resource::<GameConfig>().player.lives)
+    }
+}
+
+/// Bombs left in stock
+#[derive(Resource)]
+pub struct Bombs(pub u32);
+
+impl FromWorld for Bombs {
+    fn from_world(world: &mut World) -> Self {
+        Self(world.resource::<GameConfig>().player.bombs)
+    }
+}
+
+/// The player ran out of HP with a life in stock
+#[derive(Event)]
+pub(crate) struct LifeLostEvent {
+    /// Where the player was
+    pub(crate) position: Vec2,
+}
+
+/// Player input for the current simulation tick
+#[derive(Resource, Default, Clone, Copy, Debug, PartialEq)]
+pub struct TickInput {
+    /// Direction to move in, at full speed from a length of 1 on. Shorter
+    /// moves slower, to stop right on a spot.
+    pub movement: Vec2,
+    pub fire: bool,
+    /// Slows the ship down for precise dodging
+    pub focus: bool,
+    /// Held down, a bomb goes off when it is first pressed
+    pub bomb: bool,
+}
+
+/// How the fire action fires, a setting
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FireMode {
+    /// Fires for as long as it is held
+    #[default]
+    Hold,
+    /// Every press starts or stops firing, and runs start firing
+    Toggle,
+    /// Every press fires a single shot, as soon as the gun is ready
+    Tap,
+}
+
+impl FireMode {
+    pub(crate) fn next(self) -> Self {
+        match self {
+            FireMode::Hold => FireMode::Toggle,
+            FireMode::Toggle => FireMode::Tap,
+            FireMode::Tap => FireMode::Hold,
+        }
+    }
+
+    /// Message naming the mode
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            FireMode::Hold => "fire-mode-hold",
+            FireMode::Toggle => "fire-mode-toggle",
+            FireMode::Tap => "fire-mode-tap",
+        }
+    }
+}
+
+/// What the fire action did on the ticks so far, for the fire modes going by
+/// presses rather than holds
+#[derive(Resource, Debug)]
+pub(crate) struct FireState {
+    /// Whether it was held on the last tick
+    held: bool,
+    /// Whether toggled on
+    firing: bool,
+    /// Whether a tap is yet to fire its shot
+    queued: bool,
+}
+
+impl Default for FireState {
+    fn default() -> Self {
+        Self {
+            held: false,
+            firing: true,
+            queued: false,
+        }
+    }
+}
+
+/// What writes `TickInput` every tick
+#[derive(Resource, Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlayerController {
+    /// The keyboard, with the controls picked in the settings, and the mouse
+    /// or touch screen if picked too, see `pointer`
+    #[default]
+    Local,
+    /// A system added by the app, in `PlayerControllerSet`
+    External,
+}
+
+/// Where `TickInput` is written, ahead of everything reading it. Part of
+/// `GameSet::Input`.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlayerControllerSet;
+
+pub struct PlayerPlugin;
+
+impl Plugin for PlayerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Lives>()
+            .init_resource::<Bombs>()
+            .init_resource::<TickInput>()
+            .init_resource::<PlayerController>()
+            .init_resource::<FireState>()
+            .add_event::<LifeLostEvent>()
+            .configure_sets(FixedUpdate, PlayerControllerSet.in_set(GameSet::Input))
+            .add_systems(OnEnter(AppState::Running), spawn_player.after(reset_score))
+            .add_systems(
+                FixedUpdate,
+                sample_player_input
+                    .run_if(resource_equals(PlayerController::Local))
+                    .in_set(PlayerControllerSet),
+            )
+            .add_systems(
+                FixedUpdate,
+                (move_player, limit_player_bounds, shoot)
+                    .chain()
+                    .in_set(GameSet::Movement),
+            )
+            .add_systems(
+                FixedUpdate,
+                player_hit
+                    .after(track_run_stats)
+                    .in_set(GameSet::Resolution),
+            );
+    }
+}
+
+pub(crate) fn spawn_player(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    palette: Res<Palette>,
+    playfield: Res<Playfield>,
+    config: Res<GameConfig>,
+) {
+    let player = &config.player;
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes.add(shape::Quad::new(player.size()).into()).into(),
+            material: materials.add(ColorMaterial::from(palette.player)),
+            transform: Transform::from_translation(Vec3::new(
+                playfield.rect.center().x,
+                playfield.rect.min.y + player.size().y,
+                0.,
+            )),
+            ..default()
+        },
+        Player,
+        SpriteArt(SpriteId::Player),
+        HitFlash::new(palette.player, palette.player_hit),
+        Gun {
+            cooldown_timer: Timer::from_seconds(player.shot_cooldown, TimerMode::Once),
+            interval: player.shot_cooldown,
+            pattern: None,
+            damage: player.shot_damage,
+            knockback: Some(player.shot_knockback),
+            flash: PLAYER_GUN_FLASH,
+        },
+        HitPoints(player.max_hp),
+        MaxHitPoints(player.max_hp),
+        Hostility::Friendly,
+        Collider,
+        ColliderShape::Aabb {
+            half_extents: player.size() / 2.,
+        },
+    ));
+    commands.insert_resource(Lives(player.lives));
+    commands.insert_resource(Bombs(player.bombs));
+    commands.insert_resource(FireState::default());
+}
+
+pub(crate) fn sample_player_input(
+    actions: Res<ActionState>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+    guns: Query<&Gun, With<Player>>,
+    mut fire_state: ResMut<FireState>,
+    mut tick_input: ResMut<TickInput>,
+) {
+    // Presses are told apart tick by tick, as a frame can run any number of
+    // ticks
+    let held = actions.pressed(Action::Fire);
+    let pressed = held && !fire_state.held;
+    fire_state.held = held;
+    // The fire mode is folded in here so replays of the tick inputs don't
+    // depend on the settings they were recorded with
+    let fire = match settings.fire_mode {
+        FireMode::Hold => held,
+        FireMode::Toggle => {
+            fire_state.firing ^= pressed;
+            fire_state.firing
+        }
+        FireMode::Tap => {
+            fire_state.queued |= pressed;
+            // The gun gets ready in `shoot`, further into this tick
+            let ready = guns
+                .iter()
+                .any(|gun| gun.cooldown_timer.remaining() <= time.delta());
+            let fire = fire_state.queued && ready;
+            fire_state.queued &= !fire;
+            fire
+        }
+    };
+    *tick_input = TickInput {
+        fire,
+        movement: actions.movement(),
+        focus: actions.pressed(Action::Focus),
+        bomb: actions.pressed(Action::Bomb),
+    };
+}
+
+fn move_player(
+    time: Res<Time>,
+    tick_input: Res<TickInput>,
+    config: Res<GameConfig>,
+    mut query: Query<&mut Transform, With<Player>>,
+    mut exhausts: Query<&mut Exhaust>,
+) {
+    let speed = if tick_input.focus {
+        config.player.focused_speed
+    } else {
+        config.player.speed
+    };
+    let movement = tick_input.movement.clamp_length_max(1.).extend(0.);
+    for mut transform in query.iter_mut() {
+        transform.translation += movement * time.delta_seconds() * speed;
+    }
+    for mut exhaust in exhausts.iter_mut() {
+        exhaust.set_thrust(tick_input.movement, tick_input.focus);
+    }
+}
+
+fn limit_player_bounds(
+    playfield: Res<Playfield>,
+    config: Res<GameConfig>,
+    mut query: Query<&mut Transform, With<Player>>,
+) {
+    let min = playfield.rect.min + config.player.size() / 2.;
+    let max = playfield.rect.max - config.player.size() / 2.;
+    for mut transform in query.iter_mut() {
+        let position = transform.translation.truncate().clamp(min, max);
+        transform.translation = position.extend(transform.translation.z);
+    }
+}
+
+pub(crate) fn shoot(
+    mut commands: Commands,
+    tick_input: Res<TickInput>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<GlowMaterial>>,
+    palette: Res<Palette>,
+    config: Res<GameConfig>,
+    mut query: Query<(Entity, &Transform, &mut Gun), With<Player>>,
+    time: Res<Time>,
+    mut sfx_events: EventWriter<PlaySfx>,
+    mut run_stats: ResMut<RunStats>,
+) {
+    for (entity, transform, mut gun) in query.iter_mut() {
+        if gun.cooldown_timer.tick(time.delta()).finished() && tick_input.fire {
+            let muzzle = Vec2::new(0., 50.);
+            let mut bullet = commands.spawn(create_bullet(
+                transform.translation + muzzle.extend(0.),
+                &mut meshes,
+                &mut materials,
+                &palette,
+                config.bullet_radius,
+                Vec2::new(0., config.player.shot_speed),
+                gun.damage,
+                false,
+            ));
+            if let Some(knockback) = gun.knockback {
+                bullet.insert(Knockback(knockback));
+            }
+            spawn_muzzle_flash(&mut commands, entity, &gun.flash, muzzle);
+            sfx_events.send(PlaySfx::at(
+                SfxId::PlayerShot,
+                transform.translation.truncate(),
+            ));
+            gun.cooldown_timer.reset();
+            run_stats.shots_fired += 1;
+        }
+    }
+}
+
+pub(crate) fn player_hit(
+    mut hit_events: EventReader<HitEvent>,
+    mut query: Query<
+        (
+            Entity,
+            &Transform,
+            &mut HitPoints,
+            &MaxHitPoints,
+            &mut HitFlash,
+        ),
+        With<Player>,
+    >,
+    mut game_over_events: EventWriter<GameOverEvent>,
+    mut lives: ResMut<Lives>,
+    mut life_lost_events: EventWriter<LifeLostEvent>,
+    mut death_events: EventWriter<DeathEvent>,
+) {
+    for event in hit_events.read() {
+        for (entity, transform, mut hp, max_hp, mut flash) in query.iter_mut() {
+            hp.0 = hp.0.saturating_sub(event.damage);
+            log::info!("Player was hit, HP is now {:?}", hp.0,);
+            if hp.0 == 0 {
+                death_events.send(DeathEvent {
+                    entity,
+                    position: transform.translation.truncate(),
+                    kind: DeathKind::Player,
+                });
+                if lives.0 > 0 {
+                    lives.0 -= 1;
+                    hp.0 = max_hp.0;
+                    log::info!("Player lost a life, {} left", lives.0);
+                    life_lost_events.send(LifeLostEvent {
+                        position: transform.translation.truncate(),
+                    });
+                } else {
+                    game_over_events.send_default();
+                }
+            }
+            flash.start();
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! Moving the ship with the mouse or a finger, for trackpads and touch
+//! screens. Picked in the settings, from the controls screen: the ship either
+//! follows the pointer to where it is over the playfield, or is dragged along
+//! by how far it moves, from wherever it is. Either way it moves no faster
+//! than with the keys, which take over while held. Holding a mouse button or
+//! touching the screen fires.
+//!
+//! The pointer is read every frame and turned into `TickInput` like the keys
+//! are, so runs played with it replay the same.
+
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use serde::{Deserialize, Serialize};
+
+use crate::config::GameConfig;
+use crate::letterbox::window_to_playfield;
+use crate::player::{
+    sample_player_input, Player, PlayerController, PlayerControllerSet, TickInput,
+};
+use crate::settings::Settings;
+use crate::state::gameplay_active;
+
+/// The mouse and touch movement setting
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PointerMovement {
+    /// Only the keys move the ship
+    #[default]
+    Off,
+    /// The ship goes to the pointer
+    Follow,
+    /// The ship moves as much as the pointer does
+    Drag,
+}
+
+impl PointerMovement {
+    pub(crate) fn next(self) -> Self {
+        match self {
+            PointerMovement::Off => PointerMovement::Follow,
+            PointerMovement::Follow => PointerMovement::Drag,
+            PointerMovement::Drag => PointerMovement::Off,
+        }
+    }
+
+    /// Message naming the option
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            PointerMovement::Off => "settings-off",
+            PointerMovement::Follow => "pointer-follow",
+            PointerMovement::Drag => "pointer-drag",
+        }
+    }
+}
+
+/// The mouse cursor, or the first finger on the screen, over the playfield
+#[derive(Resource, Default, Debug)]
+struct PointerInput {
+    /// Where it is, unless off the playfield
+    position: Option<Vec2>,
+    /// Whether that is a finger rather than the cursor
+    touch: bool,
+    /// How far it moved during play that the ship has yet to
+    offset: Vec2,
+    /// Whether a mouse button is held or a finger is down
+    pressed: bool,
+}
+
+pub struct PointerPlugin;
+
+impl Plugin for PointerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PointerInput>()
+            // Ahead of the fixed ticks reading it
+            .add_systems(
+                PreUpdate,
+                (
+                    track_pointer,
+                    // Moving it around the menus doesn't drag the ship
+                    forget_pointer_offset.run_if(not(gameplay_active)),
+                )
+                    .chain()
+                    .after(InputSystem),
+            )
+            .add_systems(
+                FixedUpdate,
+                follow_pointer
+                    .after(sample_player_input)
+                    .run_if(resource_equals(PlayerController::Local))
+                    .in_set(PlayerControllerSet),
+            );
+    }
+}
+
+fn track_pointer(
+    mut pointer: ResMut<PointerInput>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    touches: Res<Touches>,
+    mouse_buttons: Res<Input<MouseButton>>,
+) {
+    let (Ok(window), Ok((camera, camera_transform))) = (windows.get_single(), cameras.get_single())
+    else {
+        return;
+    };
+    let touch = touches.iter().next();
+    let position = touch
+        .map(|touch| touch.position())
+        .or_else(|| window.cursor_position())
+        .and_then(|position| window_to_playfield(camera, camera_transform, position));
+    let touching = touch.is_some();
+    // Nothing to add up when the pointer only just showed up, or switched
+    // between the cursor and a finger
+    if let (Some(last), Some(position)) = (pointer.position, position) {
+        if touching == pointer.touch {
+            pointer.offset += position - last;
+        }
+    }
+    *pointer = PointerInput {
+        position,
+        touch: touching,
+        offset: pointer.offset,
+        pressed: touching || mouse_buttons.pressed(MouseButton::Left),
+    };
+}
+
+fn forget_pointer_offset(mut pointer: ResMut<PointerInput>) {
+    pointer.offset = Vec2::ZERO;
+}
+
+fn follow_pointer(
+    mut pointer: ResMut<PointerInput>,
+    settings: Res<Settings>,
+    config: Res<GameConfig>,
+    time: Res<Time>,
+    players: Query<&Transform, With<Player>>,
+    mut tick_input: ResMut<TickInput>,
+) {
+    let offset = std::mem::take(&mut pointer.offset);
+    if settings.pointer_movement == PointerMovement::Off {
+        return;
+    }
+    tick_input.fire |= pointer.pressed;
+    let Ok(transform) = players.get_single() else {
+        return;
+    };
+    let dragging = settings.pointer_movement == PointerMovement::Drag;
+    let wanted = match pointer.position {
+        _ if dragging => offset,
+        Some(position) => position - transform.translation.truncate(),
+        None => return,
+    };
+    if tick_input.movement != Vec2::ZERO {
+        return;
+    }
+    let speed = if tick_input.focus {
+        config.player.focused_speed
+    } else {
+        config.player.speed
+    };
+    let step = speed * time.delta_seconds();
+    tick_input.movement = (wanted / step).clamp_length_max(1.);
+    // What the ship can't cover this tick it covers on the next ones
+    if dragging {
+        pointer.offset = wanted - tick_input.movement * step;
+    }
+}
@@ -0,0 +1,259 @@
+//! Practice. Any stage reached in stage mode, and any boss fought there, can
+//! be started directly from the practice menu with a chosen bomb stock.
+//! Practice runs end after their stage instead of moving on to the next one.
+//! What has been reached is kept in the save slot played on, see `save_slots`.
+
+use bevy::prelude::*;
+use fluent::fluent_args;
+use serde::{Deserialize, Serialize};
+
+use crate::bindings::{Action, ActionState};
+use crate::config::GameConfig;
+use crate::locale::Locale;
+use crate::mode::{practice_mode, GameMode};
+use crate::player::{spawn_player, Bombs};
+use crate::save_slots::SaveSlots;
+use crate::settings::Settings;
+use crate::stage::{StageProgress, Stages};
+use crate::state::{AppState, PauseState};
+use crate::theme::UiTheme;
+use crate::timeline::StageTimeline;
+
+const MAX_PRACTICE_BOMBS: u32 = 9;
+const BUTTON_WIDTH: f32 = 300.;
+const BUTTON_HEIGHT: f32 = 50.;
+
+/// Where the next practice run starts, and with what
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+pub struct PracticeStart {
+    /// Index into `Stages`
+    pub stage: usize,
+    /// Skip straight to the boss of the stage
+    pub at_boss: bool,
+    pub bombs: u32,
+}
+
+impl FromWorld for PracticeStart {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            stage: 0,
+            at_boss: false,
+            bombs: world.resource::<GameConfig>().player.bombs,
+        }
+    }
+}
+
+impl PracticeStart {
+    pub fn progress(&self, stages: &Stages, timelines: &Assets<StageTimeline>) -> StageProgress {
+        let mut progress = StageProgress {
+            stage: self.stage,
+            ..default()
+        };
+        if self.at_boss {
+            let timeline = timelines.get(&progress.stage(stages).timeline);
+            progress.elapsed = timeline
+                .and_then(StageTimeline::boss_time)
+                .unwrap_or_default();
+        }
+        progress
+    }
+}
+
+#[derive(Component)]
+struct PracticeScreen;
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+enum PracticeButton {
+    Bombs,
+    Start { stage: usize, at_boss: bool },
+    Back,
+}
+
+pub struct PracticePlugin;
+
+impl Plugin for PracticePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PracticeStart>()
+            .add_systems(OnEnter(AppState::PracticeSelect), spawn_practice_screen)
+            .add_systems(OnExit(AppState::PracticeSelect), despawn_practice_screen)
+            .add_systems(
+                Update,
+                (practice_buttons, update_bombs_label)
+                    .chain()
+                    .run_if(in_state(AppState::PracticeSelect)),
+            )
+            .add_systems(
+                OnEnter(AppState::Running),
+                set_practice_bombs.after(spawn_player).run_if(practice_mode),
+            )
+            .add_systems(
+                OnExit(PauseState::StageClear),
+                end_practice.run_if(practice_mode),
+            );
+    }
+}
+
+fn bombs_label(locale: &Locale, bombs: u32) -> String {
+    locale.format("practice-bombs", &fluent_args!["bombs" => bombs])
+}
+
+fn spawn_practice_screen(
+    mut commands: Commands,
+    theme: Res<UiTheme>,
+    slots: Res<SaveSlots>,
+    settings: Res<Settings>,
+    stages: Res<Stages>,
+    practice: Res<PracticeStart>,
+    locale: Res<Locale>,
+) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(12.),
+                    ..default()
+                },
+                ..default()
+            },
+            PracticeScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(locale.text("practice", theme.title.clone()));
+            spawn_practice_button(
+                parent,
+                &theme,
+                bombs_label(&locale, practice.bombs),
+                PracticeButton::Bombs,
+            );
+            let unlocked = slots.active(&settings);
+            let stage_count = unlocked.stages.min(stages.0.len());
+            for (index, stage) in stages.0.iter().enumerate().take(stage_count) {
+                let args = fluent_args!["number" => index + 1, "name" => stage.name.as_str()];
+                spawn_practice_button(
+                    parent,
+                    &theme,
+                    locale.format("practice-stage", &args),
+                    PracticeButton::Start {
+                        stage: index,
+                        at_boss: false,
+                    },
+                );
+                if index < unlocked.bosses {
+                    spawn_practice_button(
+                        parent,
+                        &theme,
+                        locale.format("practice-boss", &args),
+                        PracticeButton::Start {
+                            stage: index,
+                            at_boss: true,
+                        },
+                    );
+                }
+            }
+            spawn_practice_button(
+                parent,
+                &theme,
+                locale.get("button-back"),
+                PracticeButton::Back,
+            );
+        });
+}
+
+fn spawn_practice_button(
+    parent: &mut ChildBuilder,
+    theme: &UiTheme,
+    label: String,
+    button: PracticeButton,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    min_width: Val::Px(BUTTON_WIDTH),
+                    height: Val::Px(BUTTON_HEIGHT),
+                    border: UiRect::all(Val::Px(5.)),
+                    padding: UiRect::horizontal(Val::Px(16.)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                border_color: BorderColor(Color::BLACK),
+                background_color: Color::WHITE.into(),
+                ..default()
+            },
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(label, theme.button.clone()));
+        });
+}
+
+fn despawn_practice_screen(mut commands: Commands, query: Query<Entity, With<PracticeScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn practice_buttons(
+    actions: Res<ActionState>,
+    interaction_query: Query<(&Interaction, &PracticeButton), Changed<Interaction>>,
+    mut practice: ResMut<PracticeStart>,
+    mut mode: ResMut<GameMode>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if actions.just_pressed(Action::MenuBack) {
+        next_state.set(AppState::MainMenu);
+    }
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match *button {
+            PracticeButton::Bombs => {
+                practice.bombs = (practice.bombs + 1) % (MAX_PRACTICE_BOMBS + 1)
+            }
+            PracticeButton::Start { stage, at_boss } => {
+                practice.stage = stage;
+                practice.at_boss = at_boss;
+                *mode = GameMode::Practice;
+                next_state.set(AppState::DifficultySelect);
+            }
+            PracticeButton::Back => next_state.set(AppState::MainMenu),
+        }
+    }
+}
+
+fn update_bombs_label(
+    practice: Res<PracticeStart>,
+    locale: Res<Locale>,
+    buttons: Query<(&PracticeButton, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    if !practice.is_changed() {
+        return;
+    }
+    for (button, children) in buttons.iter() {
+        if *button != PracticeButton::Bombs {
+            continue;
+        }
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value = bombs_label(&locale, practice.bombs);
+            }
+        }
+    }
+}
+
+fn set_practice_bombs(mut commands: Commands, practice: Res<PracticeStart>) {
+    commands.insert_resource(Bombs(practice.bombs));
+}
+
+/// Practice covers a single stage, so clearing it goes back to the menu
+fn end_practice(mut next_state: ResMut<NextState<AppState>>) {
+    next_state.set(AppState::PracticeSelect);
+}
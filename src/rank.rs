@@ -0,0 +1,82 @@
+//! Rank. A hidden measure of how well the run is going, on top of the picked
+//! difficulty: it creeps up the longer the player survives and with every
+//! kill, and drops sharply whenever a life is lost. Enemies fire more often,
+//! with faster bullets, and the endless spawner speeds up as it climbs.
+
+use bevy::log;
+use bevy::prelude::*;
+
+use crate::enemy::EnemyKilledEvent;
+use crate::player::{player_hit, LifeLostEvent};
+use crate::state::AppState;
+use crate::GameSet;
+
+/// Rank gained per second survived
+const RISE_PER_SECOND: f32 = 0.004;
+const RISE_PER_KILL: f32 = 0.005;
+const DROP_PER_LIFE_LOST: f32 = 0.3;
+/// How much more aggressive enemies get at full rank
+const MAX_AGGRESSION: f32 = 0.6;
+
+/// From 0, the start of every run, to 1
+#[derive(Resource, Default, Debug)]
+pub struct Rank(pub f32);
+
+impl Rank {
+    fn aggression(&self) -> f32 {
+        1. + self.0 * MAX_AGGRESSION
+    }
+
+    /// Multiplier on how often enemies fire
+    pub fn fire_rate(&self) -> f32 {
+        self.aggression()
+    }
+
+    /// Multiplier on the speed of enemy bullets, which grows slower than the
+    /// fire rate so high rank stays readable
+    pub fn bullet_speed(&self) -> f32 {
+        1. + (self.aggression() - 1.) / 2.
+    }
+
+    /// Multiplier on the time between regular enemy spawns
+    pub fn spawn_interval(&self) -> f32 {
+        1. / self.aggression()
+    }
+}
+
+pub struct RankPlugin;
+
+impl Plugin for RankPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Rank>()
+            .add_systems(OnEnter(AppState::Running), reset_rank)
+            .add_systems(
+                FixedUpdate,
+                update_rank
+                    // Lives are lost within the same tick
+                    .after(player_hit)
+                    .in_set(GameSet::Resolution),
+            );
+    }
+}
+
+fn reset_rank(mut rank: ResMut<Rank>) {
+    *rank = Rank::default();
+}
+
+fn update_rank(
+    time: Res<Time>,
+    mut kill_events: EventReader<EnemyKilledEvent>,
+    mut life_lost_events: EventReader<LifeLostEvent>,
+    mut rank: ResMut<Rank>,
+) {
+    let kills = kill_events.read().count() as f32;
+    let lives_lost = life_lost_events.read().count() as f32;
+    let rank_before = rank.0;
+    rank.0 = (rank.0 + RISE_PER_SECOND * time.delta_seconds() + RISE_PER_KILL * kills
+        - DROP_PER_LIFE_LOST * lives_lost)
+        .clamp(0., 1.);
+    if lives_lost > 0. {
+        log::debug!("Rank dropped from {rank_before:.2} to {:.2}", rank.0);
+    }
+}
@@ -0,0 +1,623 @@
+//! Replays. Every run is recorded as it is played: its seed, what it was
+//! started with, the `TickInput` of every fixed tick, and the beats of the
+//! music, which follow the audio rather than the ticks. Since the whole
+//! simulation is deterministic given those, playing the inputs back into a
+//! run seeded the same and at the same game speed reproduces it exactly. The
+//! game over screen can save the run just played, and the last few saved are
+//! listed off the mode select screen to be watched.
+//!
+//! Replays are kept in the data directory, see `storage`, with the inputs as
+//! runs of identical ticks, which keeps even long runs small. They only play
+//! back right on the version of the game they were recorded on, with the same
+//! `config.ron` and mods. Runs picked up from a checkpoint can't be replayed,
+//! since they don't start from the beginning.
+
+use bevy::log;
+use bevy::prelude::*;
+use fluent::fluent_args;
+use serde::{Deserialize, Serialize};
+
+use crate::beat::{fire_on_beat, BeatClock, BeatEvent};
+use crate::bindings::{Action, ActionState};
+use crate::difficulty::Difficulty;
+use crate::enemy::enemy_shots;
+use crate::game_speed::GameSpeed;
+use crate::high_scores::format_day;
+use crate::hud::{HudAnchor, HudRegion};
+use crate::locale::{Locale, Localized};
+use crate::mode::{today, GameMode};
+use crate::player::{PlayerController, PlayerControllerSet, TickInput};
+use crate::practice::PracticeStart;
+use crate::score::{reset_score, Score};
+use crate::stage::StageCheckpoint;
+use crate::state::{AppState, GameOverEvent};
+use crate::storage;
+use crate::theme::UiTheme;
+use crate::{GameSet, RunSeed};
+
+/// Replays made with another version of the format are skipped
+const REPLAY_VERSION: u32 = 1;
+/// How many saved replays are kept, the oldest going first
+const REPLAY_COUNT: usize = 5;
+const BUTTON_WIDTH: f32 = 520.;
+const BUTTON_HEIGHT: f32 = 65.;
+
+const FIRE: u8 = 1;
+const FOCUS: u8 = 2;
+const BOMB: u8 = 4;
+
+/// A `TickInput` packed small: the movement, then the buttons held as bits
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+struct RecordedInput(f32, f32, u8);
+
+impl From<TickInput> for RecordedInput {
+    fn from(input: TickInput) -> Self {
+        let mut buttons = 0;
+        for (held, bit) in [(input.fire, FIRE), (input.focus, FOCUS), (input.bomb, BOMB)] {
+            if held {
+                buttons |= bit;
+            }
+        }
+        Self(input.movement.x, input.movement.y, buttons)
+    }
+}
+
+impl From<RecordedInput> for TickInput {
+    fn from(RecordedInput(x, y, buttons): RecordedInput) -> Self {
+        Self {
+            movement: Vec2::new(x, y),
+            fire: buttons & FIRE != 0,
+            focus: buttons & FOCUS != 0,
+            bomb: buttons & BOMB != 0,
+        }
+    }
+}
+
+/// What a run's ticks got from outside the simulation, by tick
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct Recording {
+    /// The input of every tick, as how many ticks in a row had it
+    ticks: Vec<(u32, RecordedInput)>,
+    /// Ticks on which the beat clock started or stopped following a track,
+    /// with whether it follows one since, see `beat`
+    clock: Vec<(u32, bool)>,
+    /// Ticks on which a beat went out, with its number
+    beats: Vec<(u32, u64)>,
+}
+
+/// A recorded run
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    version: u32,
+    pub seed: u64,
+    pub mode: GameMode,
+    pub difficulty: Difficulty,
+    /// Where a practice run started
+    pub practice: Option<PracticeStart>,
+    /// Full speed in replays from before it could be picked
+    #[serde(default)]
+    pub game_speed: GameSpeed,
+    /// The score the run ended on
+    pub score: u32,
+    /// Days since the Unix epoch
+    pub day: u64,
+    recording: Recording,
+}
+
+impl Replay {
+    fn file(index: usize) -> String {
+        format!("replay{}.ron", index + 1)
+    }
+
+    fn load(index: usize) -> Option<Self> {
+        let contents = match storage::read(&Self::file(index)) {
+            Ok(contents) => contents,
+            Err(error) => {
+                if error.kind() != std::io::ErrorKind::NotFound {
+                    log::warn!("Could not read replay {}: {error}", index + 1);
+                }
+                return None;
+            }
+        };
+        let replay: Self = ron::from_str(&contents)
+            .map_err(|error| log::warn!("Could not parse replay {}: {error}", index + 1))
+            .ok()?;
+        if replay.version != REPLAY_VERSION {
+            log::warn!(
+                "Skipping replay {}, which was recorded by another version",
+                index + 1
+            );
+            return None;
+        }
+        Some(replay)
+    }
+
+    /// Written without the pretty printing, so the recording takes up little
+    /// space
+    fn save(&self, index: usize) {
+        let saved = ron::to_string(self)
+            .map_err(|error| error.to_string())
+            .and_then(|contents| {
+                storage::write(&Self::file(index), &contents).map_err(|error| error.to_string())
+            });
+        if let Err(error) = saved {
+            log::warn!("Could not save replay {}: {error}", index + 1);
+        }
+    }
+
+    /// The replay's line in the list
+    fn label(&self, locale: &Locale) -> String {
+        let mode = GameMode::SELECTABLE
+            .into_iter()
+            .find(|(mode, _)| *mode == self.mode)
+            .map_or("menu-practice", |(_, label)| label);
+        locale.format(
+            "replay-entry",
+            &fluent_args![
+                "mode" => locale.get(mode),
+                "difficulty" => locale.get(self.difficulty.label()),
+                "score" => self.score,
+                "date" => format_day(self.day),
+            ],
+        )
+    }
+}
+
+/// The saved replays, newest first
+#[derive(Resource, Debug)]
+pub struct Replays(pub Vec<Replay>);
+
+impl Default for Replays {
+    fn default() -> Self {
+        Self((0..REPLAY_COUNT).filter_map(Replay::load).collect())
+    }
+}
+
+impl Replays {
+    /// Adds `replay` as the newest, rewriting the files since every replay
+    /// moves down one
+    fn add(&mut self, replay: Replay) {
+        self.0.insert(0, replay);
+        self.0.truncate(REPLAY_COUNT);
+        for (index, replay) in self.0.iter().enumerate() {
+            replay.save(index);
+        }
+    }
+}
+
+/// Records the run being played, and keeps the last one once it ended
+#[derive(Resource, Default, Debug)]
+pub(crate) struct ReplayRecorder {
+    recording: Recording,
+    /// Ticks recorded so far
+    tick: u32,
+    /// Whether the run started from the beginning, and not off a replay
+    replayable: bool,
+    saved: bool,
+}
+
+impl ReplayRecorder {
+    /// Whether the game over screen offers to save the run
+    pub(crate) fn can_save(&self) -> bool {
+        self.replayable && !self.saved
+    }
+}
+
+/// What was picked before watching, put back afterwards
+#[derive(Debug)]
+struct PickedBefore {
+    mode: GameMode,
+    difficulty: Difficulty,
+    practice: PracticeStart,
+    controller: PlayerController,
+}
+
+/// The replay being watched, and how far along
+#[derive(Debug)]
+struct Playback {
+    replay: Replay,
+    /// Ticks played so far
+    tick: u32,
+    /// Index into the recorded ticks
+    run: usize,
+    /// Ticks of that run already played
+    played: u32,
+    /// Indices into the recorded clock changes and beats
+    clock: usize,
+    beats: usize,
+    before: PickedBefore,
+}
+
+impl Playback {
+    fn rewind(&mut self) {
+        self.tick = 0;
+        self.run = 0;
+        self.played = 0;
+        self.clock = 0;
+        self.beats = 0;
+    }
+}
+
+/// Set while a replay is watched, from picking it until back on the main menu
+#[derive(Resource, Default, Debug)]
+pub struct ReplayPlayback(Option<Playback>);
+
+impl ReplayPlayback {
+    /// Seed of the replay watched, which its runs start from
+    pub fn seed(&self) -> Option<u64> {
+        self.0.as_ref().map(|playback| playback.replay.seed)
+    }
+
+    /// Game speed of the replay watched, which its runs go at
+    pub fn game_speed(&self) -> Option<GameSpeed> {
+        self.0.as_ref().map(|playback| playback.replay.game_speed)
+    }
+}
+
+/// Run condition for what runs watched shouldn't touch, like the high scores
+pub(crate) fn watching_replay(playback: Res<ReplayPlayback>) -> bool {
+    playback.0.is_some()
+}
+
+/// Asks for the run that just ended to be saved as a replay
+#[derive(Event, Default)]
+pub(crate) struct SaveReplayEvent;
+
+#[derive(Component)]
+struct ReplaysScreen;
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+enum ReplayButton {
+    Watch(usize),
+    Back,
+}
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Replays>()
+            .init_resource::<ReplayRecorder>()
+            .init_resource::<ReplayPlayback>()
+            .add_event::<SaveReplayEvent>()
+            .add_systems(
+                OnEnter(AppState::Running),
+                // Before the checkpoint to resume from is taken
+                (start_recording.before(reset_score), rewind_playback),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    record_tick
+                        .after(PlayerControllerSet)
+                        .in_set(GameSet::Input)
+                        .run_if(not(watching_replay)),
+                    play_back_tick
+                        .in_set(PlayerControllerSet)
+                        .run_if(watching_replay),
+                ),
+            )
+            // In place of the track's beats, see `beat`
+            .add_systems(
+                FixedUpdate,
+                (
+                    record_beats
+                        .after(fire_on_beat)
+                        .run_if(not(watching_replay)),
+                    play_back_beats
+                        .after(enemy_shots)
+                        .before(fire_on_beat)
+                        .run_if(watching_replay),
+                )
+                    .in_set(GameSet::Movement),
+            )
+            .add_systems(Update, save_replay.run_if(on_event::<SaveReplayEvent>()))
+            .add_systems(OnEnter(AppState::MainMenu), stop_playback)
+            .add_systems(OnEnter(AppState::Replays), spawn_replays_screen)
+            .add_systems(OnExit(AppState::Replays), despawn_replays_screen)
+            .add_systems(Update, replay_buttons.run_if(in_state(AppState::Replays)));
+    }
+}
+
+fn start_recording(
+    mut recorder: ResMut<ReplayRecorder>,
+    checkpoint: Res<StageCheckpoint>,
+    playback: Res<ReplayPlayback>,
+) {
+    *recorder = ReplayRecorder {
+        recording: Recording::default(),
+        tick: 0,
+        replayable: !checkpoint.resume && playback.0.is_none(),
+        saved: false,
+    };
+}
+
+fn record_tick(tick_input: Res<TickInput>, mut recorder: ResMut<ReplayRecorder>) {
+    if !recorder.replayable {
+        return;
+    }
+    let input = RecordedInput::from(*tick_input);
+    let ticks = &mut recorder.recording.ticks;
+    match ticks.last_mut() {
+        Some((count, last)) if *last == input => *count += 1,
+        _ => ticks.push((1, input)),
+    }
+    recorder.tick += 1;
+}
+
+fn record_beats(
+    mut recorder: ResMut<ReplayRecorder>,
+    clock: Res<BeatClock>,
+    mut beat_events: EventReader<BeatEvent>,
+) {
+    if !recorder.replayable {
+        beat_events.clear();
+        return;
+    }
+    // Counting from the tick `record_tick` just recorded
+    let tick = recorder.tick.saturating_sub(1);
+    let recording = &mut recorder.recording;
+    let following = recording.clock.last().is_some_and(|(_, running)| *running);
+    if clock.running() != following {
+        recording.clock.push((tick, clock.running()));
+    }
+    for event in beat_events.read() {
+        recording.beats.push((tick, event.beat));
+    }
+}
+
+/// Every run of a replay starts it over, retries included
+fn rewind_playback(
+    mut commands: Commands,
+    mut playback: ResMut<ReplayPlayback>,
+    theme: Res<UiTheme>,
+    locale: Res<Locale>,
+) {
+    let Some(playback) = &mut playback.0 else {
+        return;
+    };
+    playback.rewind();
+    commands.spawn((
+        locale.text("replay-watching", theme.hud.clone()),
+        HudAnchor(HudRegion::BottomRight),
+    ));
+}
+
+/// Ends the run once the replay runs out, for runs that were quit rather
+/// than lost
+fn play_back_tick(
+    mut playback: ResMut<ReplayPlayback>,
+    mut tick_input: ResMut<TickInput>,
+    mut game_over_events: EventWriter<GameOverEvent>,
+) {
+    let Some(playback) = &mut playback.0 else {
+        return;
+    };
+    let Some(&(count, input)) = playback.replay.recording.ticks.get(playback.run) else {
+        *tick_input = TickInput::default();
+        game_over_events.send_default();
+        return;
+    };
+    *tick_input = input.into();
+    playback.tick += 1;
+    playback.played += 1;
+    if playback.played == count {
+        playback.run += 1;
+        playback.played = 0;
+    }
+}
+
+/// Sends the beats recorded on this tick, in place of the track's, which
+/// `emit_beats` doesn't send while watching
+fn play_back_beats(
+    mut playback: ResMut<ReplayPlayback>,
+    mut clock: ResMut<BeatClock>,
+    mut beat_events: EventWriter<BeatEvent>,
+) {
+    let Some(playback) = &mut playback.0 else {
+        return;
+    };
+    let tick = playback.tick.saturating_sub(1);
+    let recording = &playback.replay.recording;
+    while let Some(&(at, running)) = recording.clock.get(playback.clock) {
+        if at > tick {
+            break;
+        }
+        clock.replayed = running;
+        playback.clock += 1;
+    }
+    while let Some(&(at, beat)) = recording.beats.get(playback.beats) {
+        if at > tick {
+            break;
+        }
+        beat_events.send(BeatEvent { beat });
+        playback.beats += 1;
+    }
+}
+
+fn save_replay(
+    mut events: EventReader<SaveReplayEvent>,
+    mut recorder: ResMut<ReplayRecorder>,
+    mut replays: ResMut<Replays>,
+    seed: Res<RunSeed>,
+    mode: Res<GameMode>,
+    difficulty: Res<Difficulty>,
+    practice: Res<PracticeStart>,
+    speed: Res<GameSpeed>,
+    score: Res<Score>,
+    mut labels: Query<&mut Localized>,
+) {
+    events.clear();
+    if !recorder.can_save() {
+        return;
+    }
+    replays.add(Replay {
+        version: REPLAY_VERSION,
+        seed: seed.0,
+        mode: *mode,
+        difficulty: *difficulty,
+        practice: (*mode == GameMode::Practice).then(|| practice.clone()),
+        game_speed: *speed,
+        score: score.0,
+        day: today(),
+        recording: recorder.recording.clone(),
+    });
+    recorder.saved = true;
+    log::info!("Saved the replay");
+    for mut label in labels.iter_mut() {
+        if label.0 == "game-over-save-replay" {
+            label.0 = "game-over-replay-saved";
+        }
+    }
+}
+
+fn start_playback(world: &mut World, replay: Replay) {
+    let before = PickedBefore {
+        mode: *world.resource::<GameMode>(),
+        difficulty: *world.resource::<Difficulty>(),
+        practice: world.resource::<PracticeStart>().clone(),
+        controller: *world.resource::<PlayerController>(),
+    };
+    log::info!("Watching a {:?} replay", replay.mode);
+    world.insert_resource(replay.mode);
+    world.insert_resource(replay.difficulty);
+    if let Some(practice) = &replay.practice {
+        world.insert_resource(practice.clone());
+    }
+    world.insert_resource(PlayerController::External);
+    world.resource_mut::<StageCheckpoint>().resume = false;
+    let playback = Playback {
+        replay,
+        tick: 0,
+        run: 0,
+        played: 0,
+        clock: 0,
+        beats: 0,
+        before,
+    };
+    world.insert_resource(ReplayPlayback(Some(playback)));
+    world
+        .resource_mut::<NextState<AppState>>()
+        .set(AppState::Running);
+}
+
+fn stop_playback(
+    mut playback: ResMut<ReplayPlayback>,
+    mut mode: ResMut<GameMode>,
+    mut difficulty: ResMut<Difficulty>,
+    mut practice: ResMut<PracticeStart>,
+    mut controller: ResMut<PlayerController>,
+) {
+    let Some(Playback { before, .. }) = playback.0.take() else {
+        return;
+    };
+    *mode = before.mode;
+    *difficulty = before.difficulty;
+    *practice = before.practice;
+    *controller = before.controller;
+}
+
+fn spawn_replays_screen(
+    mut commands: Commands,
+    theme: Res<UiTheme>,
+    locale: Res<Locale>,
+    replays: Res<Replays>,
+) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.),
+                    ..default()
+                },
+                ..default()
+            },
+            ReplaysScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(locale.text("replays", theme.title.clone()));
+            if replays.0.is_empty() {
+                parent.spawn(locale.text("replays-empty", theme.hud.clone()));
+            }
+            for (index, replay) in replays.0.iter().enumerate() {
+                spawn_replay_button(
+                    parent,
+                    &theme,
+                    replay.label(&locale),
+                    ReplayButton::Watch(index),
+                );
+            }
+            spawn_replay_button(
+                parent,
+                &theme,
+                locale.get("button-back"),
+                ReplayButton::Back,
+            );
+        });
+}
+
+fn spawn_replay_button(
+    parent: &mut ChildBuilder,
+    theme: &UiTheme,
+    label: String,
+    button: ReplayButton,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    min_width: Val::Px(BUTTON_WIDTH),
+                    height: Val::Px(BUTTON_HEIGHT),
+                    border: UiRect::all(Val::Px(5.)),
+                    padding: UiRect::horizontal(Val::Px(16.)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                border_color: BorderColor(Color::BLACK),
+                background_color: Color::WHITE.into(),
+                ..default()
+            },
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(label, theme.label.clone()));
+        });
+}
+
+fn despawn_replays_screen(mut commands: Commands, query: Query<Entity, With<ReplaysScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn replay_buttons(
+    mut commands: Commands,
+    actions: Res<ActionState>,
+    interaction_query: Query<(&Interaction, &ReplayButton), Changed<Interaction>>,
+    replays: Res<Replays>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if actions.just_pressed(Action::MenuBack) {
+        next_state.set(AppState::ModeSelect);
+    }
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match *button {
+            ReplayButton::Watch(index) => {
+                let Some(replay) = replays.0.get(index).cloned() else {
+                    continue;
+                };
+                commands.add(move |world: &mut World| start_playback(world, replay));
+            }
+            ReplayButton::Back => next_state.set(AppState::ModeSelect),
+        }
+    }
+}
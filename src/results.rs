@@ -0,0 +1,274 @@
+//! Stage results. Every boss that goes down clears a stage: once the stage
+//! transition has faded out, the game freezes on a screen tallying how the
+//! stage went, grades it and pays out a bonus before play moves on to the
+//! next one.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use fluent::fluent_args;
+use serde::{Deserialize, Serialize};
+
+use crate::bullet::{Bullet, Hostility};
+use crate::collision::{ColliderShape, HitEvent};
+use crate::enemy::EnemyKilledEvent;
+use crate::game_speed::GameSpeed;
+use crate::locale::Locale;
+use crate::mode::GameMode;
+use crate::player::Player;
+use crate::replay::ReplayPlayback;
+use crate::save_slots::saves_progress;
+use crate::score::{RunStats, Score};
+use crate::settings::Settings;
+use crate::state::{AppState, PauseState};
+use crate::theme::UiTheme;
+use crate::GameSet;
+
+/// Hostile bullets passing this close to the player's center count as grazes
+const GRAZE_RADIUS: f32 = 45.;
+const CONTINUE_BUTTON_WIDTH: f32 = 240.;
+
+/// How the current stage is going, reset whenever a new one starts
+#[derive(Resource, Default, Debug)]
+pub(crate) struct StageStats {
+    kills: u32,
+    grazes: u32,
+    damage_taken: u32,
+    time: Duration,
+}
+
+/// Best first
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Grade {
+    S,
+    A,
+    B,
+    C,
+}
+
+impl Grade {
+    /// Stages are graded on how cleanly they were played: taking no damage
+    /// while grazing plenty earns an S, and the grade drops with every hit.
+    pub(crate) fn for_stage(stats: &StageStats) -> Self {
+        match stats.damage_taken {
+            0 if stats.grazes >= 10 => Grade::S,
+            0..=20 => Grade::A,
+            21..=60 => Grade::B,
+            _ => Grade::C,
+        }
+    }
+
+    fn bonus(self, stats: &StageStats) -> u32 {
+        let multiplier = match self {
+            Grade::S => 4,
+            Grade::A => 3,
+            Grade::B => 2,
+            Grade::C => 1,
+        };
+        multiplier * (1000 + 10 * stats.kills + 5 * stats.grazes)
+    }
+}
+
+/// Bullets that already counted as a graze
+#[derive(Component)]
+struct Grazed;
+
+#[derive(Component)]
+struct ResultsScreen;
+
+#[derive(Component)]
+struct ContinueButton;
+
+pub struct StageResultsPlugin;
+
+impl Plugin for StageResultsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StageStats>()
+            .add_systems(OnEnter(AppState::Running), reset_stage_stats)
+            .add_systems(FixedUpdate, count_grazes.in_set(GameSet::Collision))
+            .add_systems(FixedUpdate, track_stage_stats.in_set(GameSet::Resolution))
+            .add_systems(OnEnter(PauseState::StageClear), spawn_results_screen)
+            .add_systems(
+                OnExit(PauseState::StageClear),
+                (despawn_results_screen, reset_stage_stats),
+            )
+            .add_systems(
+                Update,
+                continue_from_results.run_if(in_state(PauseState::StageClear)),
+            );
+    }
+}
+
+fn reset_stage_stats(mut commands: Commands) {
+    commands.insert_resource(StageStats::default());
+}
+
+fn count_grazes(
+    mut commands: Commands,
+    mut stats: ResMut<StageStats>,
+    mut run_stats: ResMut<RunStats>,
+    bullets: Query<
+        (Entity, &Transform, &Hostility, &ColliderShape),
+        (With<Bullet>, Without<Grazed>),
+    >,
+    players: Query<&Transform, With<Player>>,
+) {
+    let graze_shape = ColliderShape::Circle {
+        radius: GRAZE_RADIUS,
+    };
+    for player_transform in players.iter() {
+        let player_position = player_transform.translation.truncate();
+        for (entity, transform, hostility, shape) in bullets.iter() {
+            if let Hostility::Friendly = hostility {
+                continue;
+            }
+            if shape.intersects(
+                transform.translation.truncate(),
+                &graze_shape,
+                player_position,
+            ) {
+                // The bullet may already be queued for despawning this tick
+                commands.add(move |world: &mut World| {
+                    if let Some(mut bullet) = world.get_entity_mut(entity) {
+                        bullet.insert(Grazed);
+                    }
+                });
+                stats.grazes += 1;
+                run_stats.grazes += 1;
+            }
+        }
+    }
+}
+
+fn track_stage_stats(
+    time: Res<Time>,
+    mut kill_events: EventReader<EnemyKilledEvent>,
+    mut hit_events: EventReader<HitEvent>,
+    mut stats: ResMut<StageStats>,
+) {
+    stats.kills += kill_events.read().count() as u32;
+    stats.damage_taken += hit_events.read().map(|event| event.damage).sum::<u32>();
+    stats.time += time.delta();
+}
+
+fn spawn_results_screen(
+    mut commands: Commands,
+    theme: Res<UiTheme>,
+    stats: Res<StageStats>,
+    mut score: ResMut<Score>,
+    mode: Res<GameMode>,
+    settings: Res<Settings>,
+    locale: Res<Locale>,
+    playback: Res<ReplayPlayback>,
+    speed: Res<GameSpeed>,
+) {
+    let saved = saves_progress(mode, playback);
+    let grade = Grade::for_stage(&stats);
+    let bonus = speed.points(grade.bonus(&stats));
+    score.0 += bonus;
+
+    let seconds = stats.time.as_secs();
+    let time = format!("{}:{:02}", seconds / 60, seconds % 60);
+    let lines = [
+        locale.format("results-kills", &fluent_args!["kills" => stats.kills]),
+        locale.format("results-grazes", &fluent_args!["grazes" => stats.grazes]),
+        locale.format(
+            "results-damage",
+            &fluent_args!["damage" => stats.damage_taken],
+        ),
+        locale.format("results-time", &fluent_args!["time" => time]),
+        locale.format("results-bonus", &fluent_args!["bonus" => bonus]),
+    ];
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.6).into(),
+                ..default()
+            },
+            ResultsScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(locale.text("stage-clear", theme.title.clone()));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        row_gap: Val::Px(4.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for line in lines {
+                        parent.spawn(TextBundle::from_section(line, theme.hud.clone()));
+                    }
+                });
+            parent.spawn(TextBundle::from_section(
+                locale.format(
+                    "results-grade",
+                    &fluent_args!["grade" => format!("{grade:?}")],
+                ),
+                theme.score.clone(),
+            ));
+            if saved {
+                parent.spawn(TextBundle::from_section(
+                    locale.format(
+                        "results-saved",
+                        &fluent_args!["slot" => settings.save_slot + 1],
+                    ),
+                    theme.hud.clone(),
+                ));
+            }
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            min_width: Val::Px(CONTINUE_BUTTON_WIDTH),
+                            height: Val::Px(65.),
+                            border: UiRect::all(Val::Px(5.)),
+                            padding: UiRect::horizontal(Val::Px(16.)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        border_color: BorderColor(Color::BLACK),
+                        background_color: Color::WHITE.into(),
+                        ..default()
+                    },
+                    ContinueButton,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(locale.text("button-continue", theme.button.clone()));
+                });
+        });
+}
+
+fn despawn_results_screen(mut commands: Commands, query: Query<Entity, With<ResultsScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn continue_from_results(
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<ContinueButton>)>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+) {
+    if interaction_query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        next_pause_state.set(PauseState::Unpaused);
+    }
+}
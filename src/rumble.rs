@@ -0,0 +1,73 @@
+//! Gamepad rumble. The player getting hit, a bomb going off and a boss going
+//! down rumble every connected gamepad, off the same events their sounds and
+//! screen shake come from. How hard is up to the rumble setting, which starts
+//! out off.
+
+use std::time::Duration;
+
+use bevy::input::gamepad::{GamepadRumbleIntensity, GamepadRumbleRequest};
+use bevy::prelude::*;
+
+use crate::bomb::BombUsedEvent;
+use crate::boss::BossDefeatedEvent;
+use crate::collision::HitEvent;
+use crate::settings::Settings;
+use crate::state::{gameplay_active, AppState};
+
+/// Strong and weak motor intensities at full rumble, and for how long
+const PLAYER_HIT_RUMBLE: (f32, f32, f32) = (0.5, 0.8, 0.25);
+const BOMB_RUMBLE: (f32, f32, f32) = (0.8, 0.4, 0.5);
+const BOSS_DEFEATED_RUMBLE: (f32, f32, f32) = (1., 1., 1.);
+
+pub struct RumblePlugin;
+
+impl Plugin for RumblePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, rumble.run_if(gameplay_active))
+            .add_systems(OnExit(AppState::Running), stop_rumble);
+    }
+}
+
+fn rumble(
+    settings: Res<Settings>,
+    gamepads: Res<Gamepads>,
+    mut hit_events: EventReader<HitEvent>,
+    mut bomb_events: EventReader<BombUsedEvent>,
+    mut boss_defeated_events: EventReader<BossDefeatedEvent>,
+    mut requests: EventWriter<GamepadRumbleRequest>,
+) {
+    // Several hits in a frame rumble as one
+    let rumbles = [
+        (hit_events.read().count() > 0, PLAYER_HIT_RUMBLE),
+        (bomb_events.read().count() > 0, BOMB_RUMBLE),
+        (
+            boss_defeated_events.read().count() > 0,
+            BOSS_DEFEATED_RUMBLE,
+        ),
+    ];
+    if settings.rumble == 0. {
+        return;
+    }
+    for (strong, weak, seconds) in rumbles
+        .into_iter()
+        .filter_map(|(happened, rumble)| happened.then_some(rumble))
+    {
+        for gamepad in gamepads.iter() {
+            requests.send(GamepadRumbleRequest::Add {
+                duration: Duration::from_secs_f32(seconds),
+                intensity: GamepadRumbleIntensity {
+                    strong_motor: strong * settings.rumble,
+                    weak_motor: weak * settings.rumble,
+                },
+                gamepad,
+            });
+        }
+    }
+}
+
+/// Nothing keeps rumbling on the screens between runs
+fn stop_rumble(gamepads: Res<Gamepads>, mut requests: EventWriter<GamepadRumbleRequest>) {
+    for gamepad in gamepads.iter() {
+        requests.send(GamepadRumbleRequest::Stop { gamepad });
+    }
+}
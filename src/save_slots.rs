@@ -0,0 +1,349 @@
+//! Save slots, each keeping one player's progress through the stages: how far
+//! they got, which bosses they fought, both of which practice can start from,
+//! and the best grade of every stage cleared. The slot played on is picked on
+//! its own screen off the main menu, and kept in the settings.
+//!
+//! Progress counts as soon as it is made, but only reaches the disk at the
+//! save points, once every stage is cleared. Practice runs leave the slot as
+//! it is, and so do the replays watched.
+//!
+//! The progress kept from before the slots, in the working directory, goes
+//! into the slot picked on the first launch without any.
+
+use std::collections::BTreeMap;
+
+use bevy::log;
+use bevy::prelude::*;
+use fluent::fluent_args;
+use serde::{Deserialize, Serialize};
+
+use crate::bindings::{Action, ActionState};
+use crate::boss::BossEncounter;
+use crate::locale::Locale;
+use crate::mode::GameMode;
+use crate::replay::ReplayPlayback;
+use crate::results::{Grade, StageStats};
+use crate::settings::Settings;
+use crate::stage::{StageProgress, Stages};
+use crate::state::{AppState, PauseState};
+use crate::storage;
+use crate::theme::UiTheme;
+
+const SLOT_COUNT: usize = 3;
+/// Where the stages and bosses reached were kept before the slots
+const LEGACY_UNLOCKS_FILE: &str = "unlocks.txt";
+const BUTTON_WIDTH: f32 = 360.;
+const BUTTON_HEIGHT: f32 = 65.;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SaveSlot {
+    /// How many stages have been reached, counting from the first
+    pub stages: usize,
+    /// How many of their bosses have been fought
+    pub bosses: usize,
+    /// Best grade of every stage cleared, by stage name
+    pub grades: BTreeMap<String, Grade>,
+}
+
+impl Default for SaveSlot {
+    fn default() -> Self {
+        // The first stage is always open
+        Self {
+            stages: 1,
+            bosses: 0,
+            grades: BTreeMap::new(),
+        }
+    }
+}
+
+impl SaveSlot {
+    fn file(index: usize) -> String {
+        format!("slot{}.ron", index + 1)
+    }
+
+    /// Nothing when there is no file yet, and empty when there is an
+    /// unreadable one
+    fn load(index: usize) -> Option<Self> {
+        let contents = match storage::read(&Self::file(index)) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(error) => {
+                log::warn!("Could not read save slot {}: {error}", index + 1);
+                return Some(Self::default());
+            }
+        };
+        Some(ron::from_str(&contents).unwrap_or_else(|error| {
+            log::warn!("Could not parse save slot {}: {error}", index + 1);
+            Self::default()
+        }))
+    }
+
+    /// The stages and bosses reached before the slots, if they were kept
+    fn load_legacy() -> Option<Self> {
+        let contents = std::fs::read_to_string(LEGACY_UNLOCKS_FILE).ok()?;
+        let mut numbers = contents.split_whitespace().map(str::parse);
+        Some(Self {
+            stages: numbers.next()?.ok()?,
+            bosses: numbers.next()?.ok()?,
+            ..default()
+        })
+    }
+
+    fn save(&self, index: usize) {
+        let saved = ron::ser::to_string_pretty(self, default())
+            .map_err(|error| error.to_string())
+            .and_then(|contents| {
+                storage::write(&Self::file(index), &contents).map_err(|error| error.to_string())
+            });
+        match saved {
+            Ok(()) => log::info!("Saved to slot {}", index + 1),
+            Err(error) => log::warn!("Could not save to slot {}: {error}", index + 1),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Every save slot, as last saved or played
+#[derive(Resource, Debug)]
+pub struct SaveSlots(pub Vec<SaveSlot>);
+
+/// Saves the progress from before the slots right away, so it is only brought
+/// in once
+impl FromWorld for SaveSlots {
+    fn from_world(world: &mut World) -> Self {
+        let loaded: Vec<_> = (0..SLOT_COUNT).map(SaveSlot::load).collect();
+        let first_launch = loaded.iter().all(Option::is_none);
+        let mut slots = Self(loaded.into_iter().map(Option::unwrap_or_default).collect());
+        if let Some(legacy) = first_launch.then(SaveSlot::load_legacy).flatten() {
+            let (index, slot) = slots.active_mut(world.resource::<Settings>());
+            *slot = legacy;
+            log::info!("Brought the stages reached over from before the save slots");
+            slot.save(index);
+        }
+        slots
+    }
+}
+
+impl SaveSlots {
+    /// The slot the settings pick
+    pub fn active(&self, settings: &Settings) -> &SaveSlot {
+        &self.0[settings.save_slot.min(self.0.len() - 1)]
+    }
+
+    fn active_mut(&mut self, settings: &Settings) -> (usize, &mut SaveSlot) {
+        let index = settings.save_slot.min(self.0.len() - 1);
+        (index, &mut self.0[index])
+    }
+}
+
+/// Whether the run makes progress in the save slot. Replays watched never do.
+pub(crate) fn saves_progress(mode: Res<GameMode>, playback: Res<ReplayPlayback>) -> bool {
+    matches!(*mode, GameMode::Stages | GameMode::TimeAttack) && playback.seed().is_none()
+}
+
+#[derive(Component)]
+struct SlotSelectScreen;
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+enum SlotButton {
+    Pick(usize),
+    Back,
+}
+
+pub struct SaveSlotsPlugin;
+
+impl Plugin for SaveSlotsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SaveSlots>()
+            .add_systems(
+                Update,
+                record_progress.run_if(in_state(AppState::Running).and_then(saves_progress)),
+            )
+            .add_systems(
+                OnEnter(PauseState::StageClear),
+                save_progress.run_if(saves_progress),
+            )
+            .add_systems(OnEnter(AppState::SlotSelect), spawn_slot_select)
+            .add_systems(OnExit(AppState::SlotSelect), despawn_slot_select)
+            .add_systems(Update, slot_buttons.run_if(in_state(AppState::SlotSelect)));
+    }
+}
+
+/// Opens up the stages and bosses reached for practice
+fn record_progress(
+    mut slots: ResMut<SaveSlots>,
+    settings: Res<Settings>,
+    stages: Res<Stages>,
+    progress: Res<StageProgress>,
+    encounter: Res<BossEncounter>,
+) {
+    let stage = progress.stage % stages.0.len();
+    let fighting_boss = matches!(
+        *encounter,
+        BossEncounter::Fighting | BossEncounter::Dying(_)
+    );
+    let slot = slots.active(&settings);
+    let reached_stage = slot.stages < stage + 1;
+    let reached_boss = fighting_boss && slot.bosses < stage + 1;
+    if !reached_stage && !reached_boss {
+        return;
+    }
+    let (index, slot) = slots.active_mut(&settings);
+    slot.stages = slot.stages.max(stage + 1);
+    if fighting_boss {
+        slot.bosses = slot.bosses.max(stage + 1);
+    }
+    log::info!(
+        "Reached stage {} and {} bosses in slot {}",
+        slot.stages,
+        slot.bosses,
+        index + 1
+    );
+}
+
+/// The save point after every stage clear, which also opens up the next stage
+fn save_progress(
+    mut slots: ResMut<SaveSlots>,
+    settings: Res<Settings>,
+    stages: Res<Stages>,
+    progress: Res<StageProgress>,
+    stats: Res<StageStats>,
+) {
+    let stage = progress.stage % stages.0.len();
+    let grade = Grade::for_stage(&stats);
+    let (index, slot) = slots.active_mut(&settings);
+    slot.stages = slot.stages.max(stage + 2).min(stages.0.len());
+    slot.bosses = slot.bosses.max(stage + 1);
+    let best = slot
+        .grades
+        .entry(progress.stage(&stages).name.clone())
+        .or_insert(grade);
+    // Grades sort best first
+    *best = (*best).min(grade);
+    slot.save(index);
+}
+
+/// The slot's progress in a line, with the best grade of every stage or a
+/// dash for the ones not cleared yet
+fn slot_label(locale: &Locale, stages: &Stages, index: usize, slot: &SaveSlot) -> String {
+    if slot.is_empty() {
+        return locale.format("slot-empty", &fluent_args!["slot" => index + 1]);
+    }
+    let grades = stages
+        .0
+        .iter()
+        .map(|stage| {
+            slot.grades
+                .get(&stage.name)
+                .map_or("-".to_string(), |grade| format!("{grade:?}"))
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    locale.format(
+        "slot-progress",
+        &fluent_args![
+            "slot" => index + 1,
+            "stage" => slot.stages.min(stages.0.len()),
+            "grades" => grades,
+        ],
+    )
+}
+
+fn spawn_slot_select(
+    mut commands: Commands,
+    theme: Res<UiTheme>,
+    locale: Res<Locale>,
+    slots: Res<SaveSlots>,
+    stages: Res<Stages>,
+    settings: Res<Settings>,
+) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.),
+                    ..default()
+                },
+                ..default()
+            },
+            SlotSelectScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(locale.text("save-slots", theme.title.clone()));
+            for (index, slot) in slots.0.iter().enumerate() {
+                let mut label = slot_label(&locale, &stages, index, slot);
+                if index == settings.save_slot {
+                    label = locale.format("slot-current", &fluent_args!["slot" => label]);
+                }
+                spawn_slot_button(parent, &theme, label, SlotButton::Pick(index));
+            }
+            spawn_slot_button(parent, &theme, locale.get("button-back"), SlotButton::Back);
+        });
+}
+
+fn spawn_slot_button(
+    parent: &mut ChildBuilder,
+    theme: &UiTheme,
+    label: String,
+    button: SlotButton,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    min_width: Val::Px(BUTTON_WIDTH),
+                    height: Val::Px(BUTTON_HEIGHT),
+                    border: UiRect::all(Val::Px(5.)),
+                    padding: UiRect::horizontal(Val::Px(16.)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                border_color: BorderColor(Color::BLACK),
+                background_color: Color::WHITE.into(),
+                ..default()
+            },
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(label, theme.button.clone()));
+        });
+}
+
+fn despawn_slot_select(mut commands: Commands, query: Query<Entity, With<SlotSelectScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn slot_buttons(
+    actions: Res<ActionState>,
+    interaction_query: Query<(&Interaction, &SlotButton), Changed<Interaction>>,
+    mut settings: ResMut<Settings>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if actions.just_pressed(Action::MenuBack) {
+        next_state.set(AppState::MainMenu);
+    }
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match *button {
+            SlotButton::Pick(index) => {
+                settings.save_slot = index;
+                next_state.set(AppState::MainMenu);
+            }
+            SlotButton::Back => next_state.set(AppState::MainMenu),
+        }
+    }
+}
@@ -0,0 +1,185 @@
+//! The score, the combo multiplying it, the high score to beat and the stats
+//! of the current run. Points are scaled down below full game speed, see
+//! `game_speed`.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::collision::CollisionEvent;
+use crate::enemy::EnemyKilledEvent;
+use crate::game_speed::GameSpeed;
+use crate::high_scores::HighScores;
+use crate::mode::GameMode;
+use crate::state::AppState;
+use crate::GameSet;
+
+const COMBO_WINDOW_SECONDS: f32 = 2.;
+
+/// Every this many chained kills add one to the score multiplier
+const KILLS_PER_MULTIPLIER_STEP: u32 = 5;
+
+const MAX_SCORE_MULTIPLIER: u32 = 5;
+
+#[derive(Resource, Default)]
+pub struct Score(pub u32);
+
+/// Kills chained in quick succession. Each kill restarts the window; when it
+/// runs out the combo drops back to zero.
+#[derive(Resource)]
+pub(crate) struct Combo {
+    pub(crate) kills: u32,
+    pub(crate) window: Timer,
+}
+
+impl Default for Combo {
+    fn default() -> Self {
+        Self {
+            kills: 0,
+            window: Timer::from_seconds(COMBO_WINDOW_SECONDS, TimerMode::Once),
+        }
+    }
+}
+
+impl Combo {
+    pub(crate) fn score_multiplier(&self) -> u32 {
+        (1 + self.kills / KILLS_PER_MULTIPLIER_STEP).min(MAX_SCORE_MULTIPLIER)
+    }
+}
+
+/// Best score reached in the current mode, the top of its table in
+/// `HighScores`, or the current run's once beaten
+#[derive(Resource, Default)]
+pub struct HighScore(pub u32);
+
+/// Stats about the current run, shown on the game over screen and added to
+/// the `LifetimeStats` as it ends
+#[derive(Resource, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct RunStats {
+    pub(crate) shots_fired: u32,
+    /// Shots that struck an enemy
+    pub(crate) shots_hit: u32,
+    pub(crate) enemies_killed: u32,
+    /// Kills of every kind of enemy, see `EnemyKind`
+    pub(crate) kills_by_kind: BTreeMap<String, u32>,
+    pub(crate) grazes: u32,
+    pub(crate) bombs_used: u32,
+    pub(crate) time_survived: Duration,
+}
+
+impl RunStats {
+    /// Share of the shots fired that hit, once any were fired
+    pub(crate) fn accuracy(&self) -> Option<f32> {
+        (self.shots_fired > 0).then(|| self.shots_hit as f32 / self.shots_fired as f32)
+    }
+
+    /// Adds up the stats of two runs
+    pub(crate) fn add(&mut self, other: &RunStats) {
+        self.shots_fired += other.shots_fired;
+        self.shots_hit += other.shots_hit;
+        self.enemies_killed += other.enemies_killed;
+        for (kind, kills) in &other.kills_by_kind {
+            *self.kills_by_kind.entry(kind.clone()).or_default() += kills;
+        }
+        self.grazes += other.grazes;
+        self.bombs_used += other.bombs_used;
+        self.time_survived += other.time_survived;
+    }
+}
+
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Score>()
+            .init_resource::<RunStats>()
+            .init_resource::<Combo>()
+            .init_resource::<HighScore>()
+            .add_systems(
+                OnEnter(AppState::Running),
+                (load_high_score, reset_score).chain(),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    update_combo,
+                    increase_score,
+                    update_high_score,
+                    track_run_stats,
+                )
+                    .chain()
+                    .in_set(GameSet::Resolution),
+            );
+    }
+}
+
+/// Every run starts from nothing. The score and stats of the last one are kept
+/// until then, for the game over screen.
+pub(crate) fn reset_score(mut commands: Commands) {
+    commands.insert_resource(Score::default());
+    commands.insert_resource(RunStats::default());
+    commands.insert_resource(Combo::default());
+}
+
+fn update_combo(
+    time: Res<Time>,
+    mut kill_events: EventReader<EnemyKilledEvent>,
+    mut combo: ResMut<Combo>,
+) {
+    let kills = kill_events.read().count() as u32;
+    if kills > 0 {
+        combo.kills += kills;
+        combo.window.reset();
+    } else if combo.kills > 0 && combo.window.tick(time.delta()).just_finished() {
+        combo.kills = 0;
+    }
+}
+
+fn increase_score(
+    mut events: EventReader<CollisionEvent>,
+    mut kill_events: EventReader<EnemyKilledEvent>,
+    mut score: ResMut<Score>,
+    combo: Res<Combo>,
+    speed: Res<GameSpeed>,
+) {
+    for _ in events.read() {
+        score.0 += speed.points(10 * combo.score_multiplier());
+    }
+    for event in kill_events.read() {
+        score.0 += speed.points(event.score * combo.score_multiplier());
+    }
+}
+
+fn update_high_score(score: Res<Score>, mut high_score: ResMut<HighScore>) {
+    // Only touch the high score when beaten, so the HUD isn't rewritten every tick
+    if score.0 > high_score.0 {
+        high_score.0 = score.0;
+    }
+}
+
+pub(crate) fn track_run_stats(
+    time: Res<Time>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut kill_events: EventReader<EnemyKilledEvent>,
+    mut run_stats: ResMut<RunStats>,
+) {
+    run_stats.shots_hit += collision_events.read().count() as u32;
+    for event in kill_events.read() {
+        run_stats.enemies_killed += 1;
+        if let Some(kind) = &event.kind {
+            *run_stats.kills_by_kind.entry(kind.clone()).or_default() += 1;
+        }
+    }
+    run_stats.time_survived += time.delta();
+}
+
+fn load_high_score(
+    mode: Res<GameMode>,
+    high_scores: Res<HighScores>,
+    mut high_score: ResMut<HighScore>,
+) {
+    high_score.0 = high_scores.best(*mode);
+}
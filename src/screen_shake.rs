@@ -0,0 +1,94 @@
+//! Screen shake. Heavy moments add trauma, which decays over time, and the
+//! camera is knocked around by an offset that grows with the square of it, so
+//! small knocks barely register while big ones rattle the screen. The offset
+//! is scaled by the screen shake setting, all the way down to off, and kept
+//! to a light wobble with reduced flashing.
+//!
+//! The player getting hit and a boss going down shake the screen here; other
+//! systems add their own trauma through `ScreenShake`.
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::boss::BossDefeatedEvent;
+use crate::collision::HitEvent;
+use crate::settings::Settings;
+use crate::state::{gameplay_active, AppState};
+
+/// Furthest the camera moves at full trauma and full intensity
+const MAX_OFFSET: f32 = 24.;
+/// How much of the offset is left with reduced flashing
+const REDUCED_FLASHING_SHAKE: f32 = 0.25;
+const TRAUMA_DECAY_PER_SECOND: f32 = 1.5;
+const PLAYER_HIT_TRAUMA: f32 = 0.4;
+const BOSS_DEFEATED_TRAUMA: f32 = 0.9;
+
+/// How shaken up the screen is, from 0 to 1
+#[derive(Resource, Default, Debug)]
+pub struct ScreenShake {
+    trauma: f32,
+}
+
+impl ScreenShake {
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).min(1.);
+    }
+}
+
+pub struct ScreenShakePlugin;
+
+impl Plugin for ScreenShakePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScreenShake>()
+            .add_systems(OnExit(AppState::Running), reset_screen_shake)
+            .add_systems(
+                Update,
+                (add_trauma, shake_camera).chain().run_if(gameplay_active),
+            );
+    }
+}
+
+fn add_trauma(
+    mut hit_events: EventReader<HitEvent>,
+    mut boss_defeated_events: EventReader<BossDefeatedEvent>,
+    mut shake: ResMut<ScreenShake>,
+) {
+    for _ in hit_events.read() {
+        shake.add_trauma(PLAYER_HIT_TRAUMA);
+    }
+    for _ in boss_defeated_events.read() {
+        shake.add_trauma(BOSS_DEFEATED_TRAUMA);
+    }
+}
+
+fn shake_camera(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut shake: ResMut<ScreenShake>,
+    mut cameras: Query<&mut Transform, With<Camera>>,
+) {
+    if shake.trauma == 0. {
+        return;
+    }
+    shake.trauma = (shake.trauma - TRAUMA_DECAY_PER_SECOND * time.delta_seconds()).max(0.);
+    let mut strength = shake.trauma * shake.trauma * settings.screen_shake * MAX_OFFSET;
+    if settings.reduced_flashing {
+        strength *= REDUCED_FLASHING_SHAKE;
+    }
+    let mut rng = rand::thread_rng();
+    let offset = Vec2::new(rng.gen_range(-1.0..1.), rng.gen_range(-1.0..1.)) * strength;
+    for mut transform in cameras.iter_mut() {
+        transform.translation = offset.extend(transform.translation.z);
+    }
+}
+
+/// Puts the camera back in place for the screens between runs
+fn reset_screen_shake(
+    mut shake: ResMut<ScreenShake>,
+    mut cameras: Query<&mut Transform, With<Camera>>,
+) {
+    *shake = ScreenShake::default();
+    for mut transform in cameras.iter_mut() {
+        transform.translation = Vec3::new(0., 0., transform.translation.z);
+    }
+}
@@ -0,0 +1,311 @@
+//! Boss scripts, with the `scripting` feature. A boss whose spec names a
+//! script has it run on every fixed tick instead of hovering and firing on its
+//! gun's cooldown. Scripts are Rhai files under `assets/scripts/`, and with the
+//! `dev` feature edits apply to the running fight.
+//!
+//! The script sees the boss as `boss`:
+//!
+//! - `boss.hp`, `boss.max_hp`
+//! - `boss.x`, `boss.y`, `boss.player_x`, `boss.player_y`, in pixels from the
+//!   center of the playfield, y going up
+//! - `boss.time`, seconds since the fight started, and `boss.phase_time`,
+//!   seconds since the phase last changed
+//! - `boss.phase`, which starts at 0 and keeps whatever the script sets it to
+//! - `boss.every(seconds)`, true on the one tick every that many seconds into
+//!   the phase
+//! - `boss.fire()` fires a single bullet, `boss.fire("fan")` a bullet pattern
+//! - `boss.move_to(x, y)` heads for a point, `boss.stop()` stays put
+//!
+//! Scripts run on the fixed timestep and see nothing random, so seeded runs
+//! stay reproducible. A script that fails to load or errors out leaves the
+//! boss standing where it is, back to firing on its gun's cooldown.
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext, LoadState};
+use bevy::log;
+use bevy::prelude::*;
+use bevy::utils::BoxedFuture;
+use rhai::{Engine, Scope, AST, FLOAT, INT};
+use thiserror::Error;
+
+use crate::audio::PlaySfx;
+use crate::beat::FireOnBeat;
+use crate::boss::BossPhaseEvent;
+use crate::bullet::Gun;
+use crate::collision::not_in_hitstop;
+use crate::config::GameConfig;
+use crate::difficulty::Difficulty;
+use crate::enemy::{fire_enemy_bullet, set_enemies_direction, Direction, HoverBehaviour};
+use crate::glow::GlowMaterial;
+use crate::palette::Palette;
+use crate::patterns::PatternBook;
+use crate::player::Player;
+use crate::rank::Rank;
+use crate::{GameSet, HitPoints, Playfield};
+
+/// Keeps a script stuck in a loop from freezing the game
+const MAX_OPERATIONS: u64 = 100_000;
+
+#[derive(Asset, TypePath)]
+pub struct BossScript {
+    ast: AST,
+}
+
+/// Runs a boss script on the boss it is attached to
+#[derive(Component)]
+pub struct BossBrain {
+    path: String,
+    script: Option<Handle<BossScript>>,
+    /// HP when the script took over
+    max_hp: u32,
+    phase: INT,
+    time: f32,
+    phase_time: f32,
+    failed: bool,
+}
+
+impl BossBrain {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            script: None,
+            max_hp: 0,
+            phase: 0,
+            time: 0.,
+            phase_time: 0.,
+            failed: false,
+        }
+    }
+
+    /// Hands the boss back to its gun's cooldown, standing where it is
+    fn give_up(&mut self, gun: &mut Gun, direction: &mut Direction) {
+        self.failed = true;
+        gun.cooldown_timer.unpause();
+        direction.0 = Vec3::ZERO;
+    }
+}
+
+/// What a script sees as `boss`, and what it asked for
+#[derive(Clone)]
+struct BossControl {
+    hp: INT,
+    max_hp: INT,
+    position: Vec2,
+    player: Vec2,
+    time: FLOAT,
+    phase_time: FLOAT,
+    /// `phase_time` on the tick before
+    last_phase_time: FLOAT,
+    phase: INT,
+    fires: Vec<Option<String>>,
+    /// `None` to stay put
+    target: Option<Vec2>,
+}
+
+#[derive(Resource)]
+struct ScriptEngine(Engine);
+
+impl Default for ScriptEngine {
+    fn default() -> Self {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine
+            .register_type_with_name::<BossControl>("Boss")
+            .register_get("hp", |boss: &mut BossControl| boss.hp)
+            .register_get("max_hp", |boss: &mut BossControl| boss.max_hp)
+            .register_get("x", |boss: &mut BossControl| boss.position.x as FLOAT)
+            .register_get("y", |boss: &mut BossControl| boss.position.y as FLOAT)
+            .register_get("player_x", |boss: &mut BossControl| boss.player.x as FLOAT)
+            .register_get("player_y", |boss: &mut BossControl| boss.player.y as FLOAT)
+            .register_get("time", |boss: &mut BossControl| boss.time)
+            .register_get("phase_time", |boss: &mut BossControl| boss.phase_time)
+            .register_get_set(
+                "phase",
+                |boss: &mut BossControl| boss.phase,
+                |boss: &mut BossControl, phase: INT| boss.phase = phase,
+            )
+            .register_fn("every", |boss: &mut BossControl, seconds: FLOAT| {
+                (boss.phase_time / seconds).floor() != (boss.last_phase_time / seconds).floor()
+            })
+            .register_fn("fire", |boss: &mut BossControl| boss.fires.push(None))
+            .register_fn("fire", |boss: &mut BossControl, pattern: &str| {
+                boss.fires.push(Some(pattern.to_string()))
+            })
+            .register_fn("move_to", |boss: &mut BossControl, x: FLOAT, y: FLOAT| {
+                boss.target = Some(Vec2::new(x as f32, y as f32))
+            })
+            .register_fn("stop", |boss: &mut BossControl| boss.target = None);
+        Self(engine)
+    }
+}
+
+#[derive(Default)]
+pub struct BossScriptLoader;
+
+#[derive(Debug, Error)]
+pub enum BossScriptLoaderError {
+    #[error("could not read the boss script: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not compile the boss script: {0}")]
+    Parse(#[from] rhai::ParseError),
+}
+
+impl AssetLoader for BossScriptLoader {
+    type Asset = BossScript;
+    type Settings = ();
+    type Error = BossScriptLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<BossScript, Self::Error>> {
+        Box::pin(async move {
+            let mut source = String::new();
+            reader.read_to_string(&mut source).await?;
+            let ast = Engine::new().compile(&source)?;
+            Ok(BossScript { ast })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["boss.rhai"]
+    }
+}
+
+pub struct ScriptingPlugin;
+
+impl Plugin for ScriptingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<BossScript>()
+            .init_asset_loader::<BossScriptLoader>()
+            .init_resource::<ScriptEngine>()
+            .add_systems(
+                FixedUpdate,
+                run_boss_scripts
+                    .after(set_enemies_direction)
+                    .run_if(not_in_hitstop)
+                    .in_set(GameSet::Movement),
+            );
+    }
+}
+
+fn run_boss_scripts(
+    mut commands: Commands,
+    time: Res<Time>,
+    engine: Res<ScriptEngine>,
+    asset_server: Res<AssetServer>,
+    scripts: Res<Assets<BossScript>>,
+    mut bosses: Query<(
+        Entity,
+        &Transform,
+        &HitPoints,
+        &mut Direction,
+        &mut Gun,
+        &mut BossBrain,
+    )>,
+    players: Query<&Transform, With<Player>>,
+    playfield: Res<Playfield>,
+    patterns: PatternBook,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<GlowMaterial>>,
+    palette: Res<Palette>,
+    difficulty: Res<Difficulty>,
+    rank: Res<Rank>,
+    config: Res<GameConfig>,
+    // Together, to stay within the parameters a system can take
+    (mut sfx_events, mut phase_events): (EventWriter<PlaySfx>, EventWriter<BossPhaseEvent>),
+) {
+    let center = playfield.rect.center();
+    let player = players.get_single().map_or(Vec2::ZERO, |transform| {
+        transform.translation.truncate() - center
+    });
+    for (entity, transform, hp, mut direction, mut gun, mut brain) in bosses.iter_mut() {
+        if brain.failed {
+            continue;
+        }
+        // The script takes over moving and firing
+        let Some(handle) = &brain.script else {
+            commands
+                .entity(entity)
+                .remove::<(HoverBehaviour, FireOnBeat)>();
+            gun.cooldown_timer.pause();
+            direction.0 = Vec3::ZERO;
+            brain.script = Some(asset_server.load(&brain.path));
+            brain.max_hp = hp.0;
+            continue;
+        };
+        let Some(script) = scripts.get(handle) else {
+            if asset_server.get_load_state(handle) == Some(LoadState::Failed) {
+                log::warn!("Could not load the boss script {}", brain.path);
+                brain.give_up(&mut gun, &mut direction);
+            }
+            continue;
+        };
+
+        let last_phase_time = brain.phase_time;
+        brain.time += time.delta_seconds();
+        brain.phase_time += time.delta_seconds();
+        let position = transform.translation.truncate() - center;
+        let control = BossControl {
+            hp: hp.0 as INT,
+            max_hp: brain.max_hp as INT,
+            position,
+            player,
+            time: brain.time as FLOAT,
+            phase_time: brain.phase_time as FLOAT,
+            last_phase_time: last_phase_time as FLOAT,
+            phase: brain.phase,
+            fires: Vec::new(),
+            target: None,
+        };
+        let mut scope = Scope::new();
+        scope.push("boss", control);
+        let control = engine
+            .0
+            .run_ast_with_scope(&mut scope, &script.ast)
+            .map_err(|error| error.to_string())
+            .and_then(|()| {
+                scope
+                    .get_value::<BossControl>("boss")
+                    .ok_or_else(|| "`boss` was assigned something else".to_string())
+            });
+        let control = match control {
+            Ok(control) => control,
+            Err(error) => {
+                log::warn!("The boss script {} failed: {error}", brain.path);
+                brain.give_up(&mut gun, &mut direction);
+                continue;
+            }
+        };
+
+        if control.phase != brain.phase {
+            brain.phase = control.phase;
+            brain.phase_time = 0.;
+            phase_events.send(BossPhaseEvent {
+                boss: entity,
+                phase: control.phase as i64,
+            });
+        }
+        // Full speed until right on the target, easing in over the last pixel
+        direction.0 = control.target.map_or(Vec3::ZERO, |target| {
+            (target - position).clamp_length_max(1.).extend(0.)
+        });
+        for pattern in &control.fires {
+            fire_enemy_bullet(
+                &mut commands,
+                &mut meshes,
+                &mut materials,
+                &palette,
+                &config,
+                &mut sfx_events,
+                entity,
+                transform,
+                &gun,
+                patterns.get(pattern.as_deref()),
+                difficulty.bullet_speed() * rank.bullet_speed(),
+            );
+        }
+    }
+}
@@ -0,0 +1,567 @@
+//! Settings screen, opened from the pause menu, writing into the `Settings`
+//! resource that gameplay systems read. Every row is a button that cycles
+//! through the option's values, but for the controls, which open their own
+//! screen, see `controls`.
+//!
+//! The settings are saved to `settings.ron` in the data directory, see
+//! `storage`, whenever they change, and restored from it on launch. Options
+//! added since the file was written start out on their defaults and ones
+//! since dropped are ignored, so the file only needs migrating when an option
+//! changes meaning. A file that can't be parsed is set aside as
+//! `settings.ron.bak`, and the game starts on the defaults.
+
+use bevy::log;
+use bevy::prelude::*;
+use bevy::ui::FocusPolicy;
+use fluent::fluent_args;
+use serde::{Deserialize, Serialize};
+
+use crate::bindings::{Action, ActionState, GamepadBindings, KeyBindings};
+use crate::difficulty::Difficulty;
+use crate::high_scores::not_entering_name;
+use crate::locale::{update_locale, Language, Locale};
+use crate::modal::no_modal_open;
+use crate::palette::{ColorblindMode, PaletteChoice};
+use crate::player::FireMode;
+use crate::pointer::PointerMovement;
+use crate::storage;
+use crate::theme::{wrapping_column, UiTheme};
+
+const ROW_WIDTH: f32 = 360.;
+const ROW_HEIGHT: f32 = 36.;
+const SETTINGS_FILE: &str = "settings.ron";
+/// Where a settings file that couldn't be parsed is kept
+const SETTINGS_BACKUP_FILE: &str = "settings.ron.bak";
+/// Version of the settings file, to be bumped whenever an option changes
+/// meaning. Version 2 replaced the control scheme with key bindings, and
+/// version 3 auto-fire with the fire mode.
+const SETTINGS_VERSION: u32 = 3;
+
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Master volume, from 0 to 1
+    pub volume: f32,
+    /// Music volume, from 0 to 1, under the master volume
+    pub music_volume: f32,
+    /// Sound effects volume, from 0 to 1, under the master volume
+    pub sfx_volume: f32,
+    /// Silences everything without touching the volumes. Toggled with M.
+    pub muted: bool,
+    /// Whether the fire action fires while held, toggles firing or fires a
+    /// shot per press
+    pub fire_mode: FireMode,
+    /// Screen shake intensity, from 0 (off) to 1
+    pub screen_shake: f32,
+    /// Gamepad rumble intensity, from 0 (off) to 1
+    pub rumble: f32,
+    /// Make bullets glow, at some cost in frame time
+    pub bloom: bool,
+    /// Dim the playfield and light it up around the player, bullets and
+    /// explosions. Only does anything in builds with the `lighting` feature.
+    pub lighting: bool,
+    /// Fade in a red vignette around the playfield while low on HP
+    pub low_hp_vignette: bool,
+    /// Colors to draw the game in, overriding the stages' own
+    pub palette: PaletteChoice,
+    /// Bullet colors and shapes for a kind of color blindness, on top of the
+    /// palette
+    pub colorblind: ColorblindMode,
+    /// How much bigger than normal text and buttons are drawn
+    pub text_scale: f32,
+    /// Draw buttons white on black and all text in pure white
+    pub high_contrast: bool,
+    /// Tone down screen flashes, strobing and heavy screen shake, for
+    /// photosensitive players
+    pub reduced_flashing: bool,
+    /// How fast runs go, from 0.5 to 1, scaling the score along with it
+    pub game_speed: f32,
+    /// Show the damage dealt by every hit as floating numbers
+    pub damage_numbers: bool,
+    /// Show the FPS counter in the corner of the HUD
+    pub show_fps: bool,
+    /// Keys for every action, those of a control scheme unless changed
+    pub bindings: KeyBindings,
+    /// Gamepad buttons for every action
+    pub gamepad_bindings: GamepadBindings,
+    /// Whether the mouse or touch screen moves the ship, and how
+    pub pointer_movement: PointerMovement,
+    /// Language of everything the game says
+    pub language: Language,
+    /// Picked last on the difficulty select screen, which runs start on until
+    /// another is
+    pub difficulty: Difficulty,
+    /// Save slot played on, from 0
+    pub save_slot: usize,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            volume: 0.8,
+            music_volume: 0.8,
+            sfx_volume: 1.,
+            muted: false,
+            fire_mode: FireMode::default(),
+            screen_shake: 1.,
+            rumble: 0.,
+            bloom: false,
+            lighting: cfg!(feature = "lighting"),
+            low_hp_vignette: true,
+            palette: PaletteChoice::default(),
+            colorblind: ColorblindMode::default(),
+            text_scale: 1.,
+            high_contrast: false,
+            reduced_flashing: false,
+            game_speed: 1.,
+            damage_numbers: true,
+            show_fps: false,
+            bindings: KeyBindings::default(),
+            gamepad_bindings: GamepadBindings::default(),
+            pointer_movement: PointerMovement::default(),
+            language: Language::default(),
+            difficulty: Difficulty::default(),
+            save_slot: 0,
+        }
+    }
+}
+
+impl Settings {
+    /// What the music plays at, master volume included
+    pub fn music_level(&self) -> f64 {
+        if self.muted {
+            return 0.;
+        }
+        (self.volume * self.music_volume) as f64
+    }
+
+    /// What sound effects play at, master volume included
+    pub fn sfx_level(&self) -> f64 {
+        if self.muted {
+            return 0.;
+        }
+        (self.volume * self.sfx_volume) as f64
+    }
+
+    /// Falls back to the defaults without a readable settings file
+    fn load() -> Self {
+        let contents = match storage::read(SETTINGS_FILE) {
+            Ok(contents) => contents,
+            Err(error) => {
+                if error.kind() != std::io::ErrorKind::NotFound {
+                    log::warn!("Could not read the settings, using the defaults: {error}");
+                }
+                return Self::default();
+            }
+        };
+        match ron::from_str::<SettingsFile>(&contents) {
+            Ok(mut file) => {
+                if file.version > SETTINGS_VERSION {
+                    log::warn!(
+                        "The settings were saved by a newer version of the game, keeping what \
+                         this one knows of"
+                    );
+                }
+                if file.version < SETTINGS_VERSION {
+                    let legacy = ron::from_str::<LegacySettingsFile>(&contents)
+                        .map(|legacy| legacy.settings)
+                        .unwrap_or_default();
+                    if file.version < 2 {
+                        file.settings.bindings = KeyBindings::for_scheme(legacy.controls);
+                    }
+                    // Auto-fire was always on unless toggled off mid-run
+                    if legacy.auto_fire {
+                        file.settings.fire_mode = FireMode::Toggle;
+                    }
+                }
+                file.settings
+            }
+            Err(error) => {
+                log::warn!("Could not parse the settings, using the defaults: {error}");
+                if let Err(error) = storage::write(SETTINGS_BACKUP_FILE, &contents) {
+                    log::warn!("Could not set the unparsable settings aside: {error}");
+                }
+                Self::default()
+            }
+        }
+    }
+
+    fn save(&self) {
+        let file = SettingsFile {
+            version: SETTINGS_VERSION,
+            settings: self.clone(),
+        };
+        let saved = ron::ser::to_string_pretty(&file, default())
+            .map_err(|error| error.to_string())
+            .and_then(|contents| {
+                storage::write(SETTINGS_FILE, &contents).map_err(|error| error.to_string())
+            });
+        if let Err(error) = saved {
+            log::warn!("Could not save the settings: {error}");
+        }
+    }
+}
+
+/// What the settings file holds
+#[derive(Serialize, Deserialize)]
+struct SettingsFile {
+    #[serde(default)]
+    version: u32,
+    #[serde(default)]
+    settings: Settings,
+}
+
+/// The settings file of older versions, for the options since replaced
+#[derive(Deserialize)]
+struct LegacySettingsFile {
+    settings: LegacySettings,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct LegacySettings {
+    /// Before version 2
+    controls: ControlScheme,
+    /// Before version 3
+    auto_fire: bool,
+}
+
+/// Presets for the key bindings, see `KeyBindings::for_scheme`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize)]
+pub enum ControlScheme {
+    /// Arrow keys or WASD to move, space to fire, B to bomb
+    #[default]
+    Standard,
+    /// Arrow keys to move, Z to fire, C to bomb
+    Classic,
+}
+
+impl ControlScheme {
+    pub const ALL: [ControlScheme; 2] = [ControlScheme::Standard, ControlScheme::Classic];
+
+    pub(crate) fn next(self) -> Self {
+        match self {
+            ControlScheme::Standard => ControlScheme::Classic,
+            ControlScheme::Classic => ControlScheme::Standard,
+        }
+    }
+
+    /// Message naming the scheme
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            ControlScheme::Standard => "controls-standard",
+            ControlScheme::Classic => "controls-classic",
+        }
+    }
+}
+
+/// Whether the settings screen is open. Separate from the pause state so the
+/// pause menu stays up underneath it.
+#[derive(States, Default, Debug, Clone, Hash, Eq, PartialEq)]
+pub enum SettingsMenuState {
+    #[default]
+    Closed,
+    Open,
+    /// The controls screen, in place of the settings screen it was opened from
+    Controls,
+    /// The accessibility screen, likewise
+    Accessibility,
+}
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Settings::load())
+            .add_state::<SettingsMenuState>()
+            .add_systems(OnEnter(SettingsMenuState::Open), spawn_settings_menu)
+            .add_systems(OnExit(SettingsMenuState::Open), despawn_settings_menu)
+            .add_systems(
+                Update,
+                (
+                    // M can be part of a name
+                    toggle_mute.run_if(not_entering_name),
+                    (
+                        settings_buttons,
+                        close_on_escape.run_if(no_modal_open),
+                        update_settings_labels.after(update_locale),
+                    )
+                        .run_if(in_state(SettingsMenuState::Open)),
+                )
+                    .chain(),
+            )
+            .add_systems(Last, save_settings);
+    }
+}
+
+#[derive(Component)]
+struct SettingsMenu;
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+enum SettingsButton {
+    Volume,
+    MusicVolume,
+    SfxVolume,
+    FireMode,
+    ScreenShake,
+    Rumble,
+    Bloom,
+    #[cfg(feature = "lighting")]
+    Lighting,
+    LowHpVignette,
+    Palette,
+    DamageNumbers,
+    FpsCounter,
+    /// Opens the controls screen
+    Controls,
+    /// Opens the accessibility screen
+    Accessibility,
+    Language,
+    Back,
+}
+
+impl SettingsButton {
+    fn label(self, settings: &Settings, locale: &Locale) -> String {
+        let percent = |value: f32| {
+            let percent = (value * 100.).round() as u32;
+            locale.format("settings-percent", &fluent_args!["percent" => percent])
+        };
+        let on_off = |on: bool| locale.get(if on { "settings-on" } else { "settings-off" });
+        let (name, value) = match self {
+            SettingsButton::Volume if settings.muted => (
+                "settings-volume",
+                locale.format(
+                    "settings-muted",
+                    &fluent_args!["volume" => percent(settings.volume)],
+                ),
+            ),
+            SettingsButton::Volume => ("settings-volume", percent(settings.volume)),
+            SettingsButton::MusicVolume => ("settings-music", percent(settings.music_volume)),
+            SettingsButton::SfxVolume => ("settings-sfx", percent(settings.sfx_volume)),
+            SettingsButton::FireMode => {
+                ("settings-fire-mode", locale.get(settings.fire_mode.label()))
+            }
+            SettingsButton::ScreenShake => {
+                ("settings-screen-shake", percent(settings.screen_shake))
+            }
+            SettingsButton::Rumble if settings.rumble == 0. => {
+                ("settings-rumble", locale.get("settings-off"))
+            }
+            SettingsButton::Rumble => ("settings-rumble", percent(settings.rumble)),
+            SettingsButton::Bloom => ("settings-bloom", on_off(settings.bloom)),
+            #[cfg(feature = "lighting")]
+            SettingsButton::Lighting => ("settings-lighting", on_off(settings.lighting)),
+            SettingsButton::LowHpVignette => {
+                ("settings-low-hp-vignette", on_off(settings.low_hp_vignette))
+            }
+            SettingsButton::Palette => ("settings-palette", locale.get(settings.palette.label())),
+            SettingsButton::DamageNumbers => {
+                ("settings-damage-numbers", on_off(settings.damage_numbers))
+            }
+            SettingsButton::FpsCounter => ("settings-fps-counter", on_off(settings.show_fps)),
+            SettingsButton::Controls => {
+                let scheme = settings.bindings.scheme();
+                let label = scheme.map_or("controls-custom", ControlScheme::label);
+                ("settings-controls", locale.get(label))
+            }
+            SettingsButton::Language => ("settings-language", settings.language.name().to_string()),
+            SettingsButton::Accessibility => return locale.get("accessibility"),
+            SettingsButton::Back => return locale.get("button-back"),
+        };
+        locale.format(
+            "settings-row",
+            &fluent_args!["name" => locale.get(name), "value" => value],
+        )
+    }
+
+    /// Steps the option to its next value, wrapping around at the end
+    fn cycle(self, settings: &mut Settings) {
+        // Steps are in tenths, so keep them in integers to avoid drift
+        let next_step = |value: f32, step: u32| {
+            let tenths = (value * 10.).round() as u32 + step;
+            if tenths > 10 {
+                0.
+            } else {
+                tenths as f32 / 10.
+            }
+        };
+        match self {
+            SettingsButton::Volume => settings.volume = next_step(settings.volume, 1),
+            SettingsButton::MusicVolume => {
+                settings.music_volume = next_step(settings.music_volume, 1)
+            }
+            SettingsButton::SfxVolume => settings.sfx_volume = next_step(settings.sfx_volume, 1),
+            SettingsButton::FireMode => settings.fire_mode = settings.fire_mode.next(),
+            SettingsButton::ScreenShake => {
+                settings.screen_shake = next_step(settings.screen_shake, 5)
+            }
+            SettingsButton::Rumble => settings.rumble = next_step(settings.rumble, 5),
+            SettingsButton::Bloom => settings.bloom = !settings.bloom,
+            #[cfg(feature = "lighting")]
+            SettingsButton::Lighting => settings.lighting = !settings.lighting,
+            SettingsButton::LowHpVignette => settings.low_hp_vignette = !settings.low_hp_vignette,
+            SettingsButton::Palette => settings.palette = settings.palette.next(),
+            SettingsButton::DamageNumbers => settings.damage_numbers = !settings.damage_numbers,
+            SettingsButton::FpsCounter => settings.show_fps = !settings.show_fps,
+            SettingsButton::Language => settings.language = settings.language.next(),
+            SettingsButton::Controls | SettingsButton::Accessibility | SettingsButton::Back => {}
+        }
+    }
+}
+
+/// Text showing the current value of a settings row
+#[derive(Component)]
+struct SettingsLabel(SettingsButton);
+
+fn spawn_settings_menu(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    theme: Res<UiTheme>,
+    locale: Res<Locale>,
+) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(4.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.9).into(),
+                // Keeps the mouse off the menu underneath
+                focus_policy: FocusPolicy::Block,
+                // Above the pause or main menu it was opened from
+                z_index: ZIndex::Global(1),
+                ..default()
+            },
+            SettingsMenu,
+        ))
+        .with_children(|parent| {
+            parent.spawn(locale.text("settings", theme.title.clone()));
+            parent
+                .spawn(NodeBundle {
+                    style: wrapping_column(4.),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for button in [
+                        SettingsButton::Volume,
+                        SettingsButton::MusicVolume,
+                        SettingsButton::SfxVolume,
+                        SettingsButton::FireMode,
+                        SettingsButton::ScreenShake,
+                        SettingsButton::Rumble,
+                        SettingsButton::Bloom,
+                        #[cfg(feature = "lighting")]
+                        SettingsButton::Lighting,
+                        SettingsButton::LowHpVignette,
+                        SettingsButton::Palette,
+                        SettingsButton::DamageNumbers,
+                        SettingsButton::FpsCounter,
+                        SettingsButton::Controls,
+                        SettingsButton::Accessibility,
+                        SettingsButton::Language,
+                        SettingsButton::Back,
+                    ] {
+                        spawn_settings_row(parent, &theme, &locale, button, &settings);
+                    }
+                });
+        });
+}
+
+fn spawn_settings_row(
+    parent: &mut ChildBuilder,
+    theme: &UiTheme,
+    locale: &Locale,
+    button: SettingsButton,
+    settings: &Settings,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    min_width: Val::Px(ROW_WIDTH),
+                    height: Val::Px(ROW_HEIGHT),
+                    border: UiRect::all(Val::Px(3.)),
+                    padding: UiRect::horizontal(Val::Px(12.)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                border_color: BorderColor(Color::BLACK),
+                background_color: Color::WHITE.into(),
+                ..default()
+            },
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(button.label(settings, locale), theme.label.clone()),
+                SettingsLabel(button),
+            ));
+        });
+}
+
+fn despawn_settings_menu(mut commands: Commands, query: Query<Entity, With<SettingsMenu>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn settings_buttons(
+    interaction_query: Query<(&Interaction, &SettingsButton), Changed<Interaction>>,
+    mut settings: ResMut<Settings>,
+    mut next_state: ResMut<NextState<SettingsMenuState>>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            SettingsButton::Controls => next_state.set(SettingsMenuState::Controls),
+            SettingsButton::Accessibility => next_state.set(SettingsMenuState::Accessibility),
+            SettingsButton::Back => next_state.set(SettingsMenuState::Closed),
+            _ => button.cycle(&mut settings),
+        }
+    }
+}
+
+fn close_on_escape(
+    actions: Res<ActionState>,
+    mut next_state: ResMut<NextState<SettingsMenuState>>,
+) {
+    if actions.just_pressed(Action::MenuBack) {
+        next_state.set(SettingsMenuState::Closed);
+    }
+}
+
+fn toggle_mute(actions: Res<ActionState>, mut settings: ResMut<Settings>) {
+    if actions.just_pressed(Action::ToggleMute) {
+        settings.muted = !settings.muted;
+    }
+}
+
+fn save_settings(settings: Res<Settings>) {
+    // They were only just loaded
+    if settings.is_changed() && !settings.is_added() {
+        settings.save();
+    }
+}
+
+fn update_settings_labels(
+    settings: Res<Settings>,
+    locale: Res<Locale>,
+    mut query: Query<(&SettingsLabel, &mut Text)>,
+) {
+    if !settings.is_changed() && !locale.is_changed() {
+        return;
+    }
+    for (label, mut text) in query.iter_mut() {
+        text.sections[0].value = label.0.label(&settings, &locale);
+    }
+}
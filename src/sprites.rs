@@ -0,0 +1,197 @@
+//! Sprite art. Ships, bosses and bullets are drawn as colored shapes unless a
+//! sprite sheet is found at `SPRITE_SHEET_PATH`, in which case they are drawn
+//! from it instead, stretched over their collider. The sheet is a grid of
+//! square cells with a row per `SpriteId`, in order, holding its animation
+//! frames from left to right.
+//!
+//! `SpriteAnimation` flips through frames of the sheet. Sprites with more than
+//! one frame get one out of the box, like the player's thruster flames or the
+//! boss idling.
+
+use bevy::log;
+use bevy::prelude::*;
+use bevy::sprite::Mesh2dHandle;
+use serde::Deserialize;
+
+use crate::collision::{ColliderShape, HitFlash};
+use crate::state::{gameplay_active, AppState};
+
+const SPRITE_SHEET_PATH: &str = "textures/sprites.png";
+const CELL_SIZE: f32 = 64.;
+/// Columns of the sheet, the most frames a sprite can have
+const MAX_FRAMES: usize = 4;
+
+/// A cell of the sprite sheet
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum SpriteId {
+    Player,
+    Enemy,
+    /// Bosses and mid-bosses
+    Boss,
+    PlayerBullet,
+    EnemyBullet,
+}
+
+impl SpriteId {
+    const COUNT: usize = 5;
+
+    /// First frame of the sprite
+    fn index(self) -> usize {
+        self as usize * MAX_FRAMES
+    }
+
+    /// Frames of the sprite's animation, and how fast it plays. A single frame
+    /// stays still.
+    fn animation(self) -> (usize, f32) {
+        match self {
+            // Thruster flames
+            SpriteId::Player => (4, 12.),
+            SpriteId::Enemy => (2, 8.),
+            // Idling
+            SpriteId::Boss => (4, 6.),
+            SpriteId::PlayerBullet | SpriteId::EnemyBullet => (1, 0.),
+        }
+    }
+}
+
+/// Draws the entity from the sprite sheet, when there is one
+#[derive(Component, Clone, Copy, Debug)]
+pub struct SpriteArt(pub SpriteId);
+
+/// Flips the sprite through `frames` frames of the sheet, starting at `first`
+#[derive(Component, Clone, Debug)]
+pub struct SpriteAnimation {
+    pub first: usize,
+    pub frames: usize,
+    pub fps: f32,
+    /// Starts over after the last frame, instead of holding it
+    pub looping: bool,
+    elapsed: f32,
+}
+
+impl SpriteAnimation {
+    /// There is always at least the first frame, even when `frames` is 0
+    pub fn new(first: usize, frames: usize, fps: f32, looping: bool) -> Self {
+        Self {
+            first,
+            frames: frames.max(1),
+            fps,
+            looping,
+            elapsed: 0.,
+        }
+    }
+}
+
+/// The sprite sheet, and the atlas over it once it has loaded
+#[derive(Resource)]
+pub struct SpriteSheet {
+    pub(crate) image: Handle<Image>,
+    atlas: Option<Handle<TextureAtlas>>,
+}
+
+impl FromWorld for SpriteSheet {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        Self {
+            image: asset_server.load(SPRITE_SHEET_PATH),
+            atlas: None,
+        }
+    }
+}
+
+pub struct SpritesPlugin;
+
+impl Plugin for SpritesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpriteSheet>()
+            .add_systems(OnExit(AppState::Loading), build_sprite_atlas)
+            .add_systems(
+                Update,
+                (
+                    apply_sprite_art,
+                    tint_hit_flash_sprites,
+                    animate_sprites.run_if(gameplay_active),
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn build_sprite_atlas(
+    asset_server: Res<AssetServer>,
+    mut sheet: ResMut<SpriteSheet>,
+    mut atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    if asset_server.get_load_state(&sheet.image) != Some(bevy::asset::LoadState::Loaded) {
+        log::info!("No sprite sheet at {SPRITE_SHEET_PATH}, drawing plain shapes");
+        return;
+    }
+    let atlas = TextureAtlas::from_grid(
+        sheet.image.clone(),
+        Vec2::splat(CELL_SIZE),
+        MAX_FRAMES,
+        SpriteId::COUNT,
+        None,
+        None,
+    );
+    sheet.atlas = Some(atlases.add(atlas));
+}
+
+/// Swaps the shape of newly spawned entities for their sprite
+fn apply_sprite_art(
+    mut commands: Commands,
+    sheet: Res<SpriteSheet>,
+    query: Query<(Entity, &SpriteArt, &ColliderShape), Added<SpriteArt>>,
+) {
+    let Some(atlas) = &sheet.atlas else {
+        return;
+    };
+    for (entity, art, shape) in query.iter() {
+        // The material stays, some systems still read its color
+        let mut entity = commands.entity(entity);
+        entity.remove::<Mesh2dHandle>().insert((
+            atlas.clone(),
+            TextureAtlasSprite {
+                index: art.0.index(),
+                custom_size: Some(shape.half_extents() * 2.),
+                ..default()
+            },
+        ));
+        let (frames, fps) = art.0.animation();
+        if frames > 1 {
+            entity.insert(SpriteAnimation::new(art.0.index(), frames, fps, true));
+        }
+    }
+}
+
+fn animate_sprites(
+    time: Res<Time>,
+    mut query: Query<(&mut SpriteAnimation, &mut TextureAtlasSprite)>,
+) {
+    for (mut animation, mut sprite) in query.iter_mut() {
+        animation.elapsed += time.delta_seconds();
+        let frame = (animation.elapsed * animation.fps) as usize;
+        let frame = if animation.looping {
+            frame % animation.frames
+        } else {
+            frame.min(animation.frames - 1)
+        };
+        let index = animation.first + frame;
+        if sprite.index != index {
+            sprite.index = index;
+        }
+    }
+}
+
+/// Carries the hit flash, which tints the material, over to the sprite, for
+/// the player and enemies alike
+fn tint_hit_flash_sprites(
+    materials: Res<Assets<ColorMaterial>>,
+    mut query: Query<(&Handle<ColorMaterial>, &mut TextureAtlasSprite), With<HitFlash>>,
+) {
+    for (material, mut sprite) in query.iter_mut() {
+        if let Some(material) = materials.get(material) {
+            sprite.color = material.color;
+        }
+    }
+}
@@ -0,0 +1,362 @@
+//! Stages, played in stage mode. A run goes through the stages in `Stages` one
+//! after the other, and `StageProgress` keeps track of where it is. Each stage
+//! brings its own background, music, mid-boss and the boss that clears it,
+//! while its timeline asset decides when waves, the mid-boss and the boss show
+//! up. Clearing the last stage loops back to the first one.
+
+use bevy::log;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::audio::MusicController;
+use crate::background::{
+    BackgroundLayer, BackgroundLayers, BackgroundScroll, CLOUDS, DISTANT_STARS, GROUND, NEAR_STARS,
+};
+use crate::banner::StageBanner;
+use crate::boss::{
+    advance_boss_encounter, spawn_boss, BossEncounter, StartBossEvent, MID_BOSS_KIND,
+};
+use crate::collision::not_in_hitstop;
+use crate::config::GameConfig;
+use crate::difficulty::Difficulty;
+use crate::enemies::GRUNT;
+use crate::enemy::{EnemyKind, EnemyOverrides, SpawnEnemy};
+use crate::locale::Locale;
+use crate::mode::{practice_mode, stage_mode, GameMode};
+use crate::palette::Palette;
+use crate::practice::PracticeStart;
+use crate::score::{reset_score, Score};
+use crate::state::{AppState, PauseState};
+use crate::timeline::{report_changes, StageTimeline, TimelineAction, TimelineReloadedEvent};
+use crate::{GameSet, Playfield};
+
+#[derive(Clone, Debug)]
+pub struct BossSpec {
+    pub size: Vec2,
+    pub hp: u32,
+    pub color: Color,
+    /// Boss script under `assets/scripts/` driving it, with the `scripting`
+    /// feature. Without it the boss hovers and fires on its own.
+    pub script: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Stage {
+    pub name: String,
+    pub background: Color,
+    /// Colors of the ships, bullets and HUD, unless the settings pick others
+    pub palette: Palette,
+    /// Scrolling layers in front of the background color, farthest first
+    pub layers: Vec<BackgroundLayer>,
+    /// Looping track played for the whole stage
+    pub music: Option<String>,
+    pub timeline: Handle<StageTimeline>,
+    pub mid_boss: Option<BossSpec>,
+    pub boss: BossSpec,
+}
+
+/// Every stage of a run, in order
+#[derive(Resource, Clone, Debug)]
+pub struct Stages(pub Vec<Stage>);
+
+impl FromWorld for Stages {
+    fn from_world(world: &mut World) -> Self {
+        let classic_palette = world.resource::<GameConfig>().classic_palette;
+        let asset_server = world.resource::<AssetServer>();
+        let music = Some("audio/flying.ogg".to_string());
+        Self(vec![
+            Stage {
+                name: "Skirmish".to_string(),
+                background: Color::rgb(0.4, 0.4, 0.4),
+                palette: classic_palette,
+                layers: vec![DISTANT_STARS, NEAR_STARS],
+                music: music.clone(),
+                timeline: asset_server.load("stages/skirmish.timeline.ron"),
+                mid_boss: None,
+                boss: BossSpec {
+                    size: Vec2::new(160., 80.),
+                    hp: 300,
+                    color: Color::MAROON,
+                    script: None,
+                },
+            },
+            Stage {
+                name: "Crossfire".to_string(),
+                background: Color::rgb(0.2, 0.25, 0.4),
+                palette: Palette::NEON,
+                layers: vec![DISTANT_STARS, NEAR_STARS, CLOUDS],
+                music: music.clone(),
+                timeline: asset_server.load("stages/crossfire.timeline.ron"),
+                mid_boss: Some(BossSpec {
+                    size: Vec2::new(100., 50.),
+                    hp: 120,
+                    color: Color::PURPLE,
+                    script: None,
+                }),
+                boss: BossSpec {
+                    size: Vec2::new(180., 90.),
+                    hp: 450,
+                    color: Color::MIDNIGHT_BLUE,
+                    script: None,
+                },
+            },
+            Stage {
+                name: "Onslaught".to_string(),
+                background: Color::rgb(0.35, 0.15, 0.15),
+                palette: Palette::EMBER,
+                layers: vec![GROUND, CLOUDS],
+                music,
+                timeline: asset_server.load("stages/onslaught.timeline.ron"),
+                mid_boss: Some(BossSpec {
+                    size: Vec2::new(120., 60.),
+                    hp: 200,
+                    color: Color::PURPLE,
+                    script: None,
+                }),
+                boss: BossSpec {
+                    size: Vec2::new(200., 100.),
+                    hp: 600,
+                    color: Color::CRIMSON,
+                    script: Some("scripts/onslaught.boss.rhai".to_string()),
+                },
+            },
+        ])
+    }
+}
+
+/// Where the run is at: which stage, and how far into it
+#[derive(Resource, Default, Debug)]
+pub struct StageProgress {
+    /// Index into `Stages`
+    pub stage: usize,
+    /// Seconds since the stage started, not counting pauses
+    pub elapsed: f32,
+}
+
+impl StageProgress {
+    pub fn stage<'a>(&self, stages: &'a Stages) -> &'a Stage {
+        &stages.0[self.stage % stages.0.len()]
+    }
+}
+
+/// The run as it was at a checkpoint
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StageSnapshot {
+    /// Index into `Stages`
+    pub stage: usize,
+    /// When the checkpoint is on the stage timeline
+    pub elapsed: f32,
+    pub score: u32,
+}
+
+/// The last checkpoint reached, which a game over can continue from
+#[derive(Resource, Default, Debug)]
+pub struct StageCheckpoint {
+    pub last: Option<StageSnapshot>,
+    /// Set to have the next run pick up from `last` instead of starting over
+    pub resume: bool,
+}
+
+pub struct StagePlugin;
+
+impl Plugin for StagePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Stages>()
+            .init_resource::<StageProgress>()
+            .init_resource::<StageCheckpoint>()
+            .add_systems(
+                OnEnter(AppState::Running),
+                (reset_stage_progress, start_stage)
+                    .chain()
+                    // Continuing overrides the score `reset_score` resets
+                    .after(reset_score)
+                    .run_if(stage_mode),
+            )
+            .add_systems(
+                OnExit(PauseState::StageClear),
+                (advance_stage_progress, start_stage)
+                    .chain()
+                    .run_if(not(practice_mode)),
+            )
+            .add_systems(
+                FixedUpdate,
+                run_stage_timeline
+                    .run_if(stage_mode.and_then(not_in_hitstop))
+                    // Fixed ticks can skip frames, so the boss has to hear
+                    // about its start within the same tick
+                    .before(advance_boss_encounter)
+                    .in_set(GameSet::Movement),
+            )
+            .add_systems(
+                Update,
+                follow_timeline_reloads.run_if(stage_mode.and_then(in_state(AppState::Running))),
+            );
+    }
+}
+
+fn reset_stage_progress(
+    mut commands: Commands,
+    mode: Res<GameMode>,
+    practice: Res<PracticeStart>,
+    mut checkpoint: ResMut<StageCheckpoint>,
+    stages: Res<Stages>,
+    timelines: Res<Assets<StageTimeline>>,
+    mut progress: ResMut<StageProgress>,
+) {
+    let resume = std::mem::take(&mut checkpoint.resume);
+    *progress = match (&checkpoint.last, *mode) {
+        (Some(snapshot), _) if resume => {
+            log::info!("Continuing from {snapshot:?}");
+            commands.insert_resource(Score(snapshot.score));
+            StageProgress {
+                stage: snapshot.stage,
+                elapsed: snapshot.elapsed,
+            }
+        }
+        (_, GameMode::Practice) => practice.progress(&stages, &timelines),
+        _ => StageProgress::default(),
+    };
+}
+
+fn advance_stage_progress(mut progress: ResMut<StageProgress>) {
+    *progress = StageProgress {
+        stage: progress.stage + 1,
+        ..default()
+    };
+}
+
+fn start_stage(
+    mut commands: Commands,
+    stages: Res<Stages>,
+    timelines: Res<Assets<StageTimeline>>,
+    progress: Res<StageProgress>,
+    mut music: ResMut<MusicController>,
+    mut stage_banner: ResMut<StageBanner>,
+    locale: Res<Locale>,
+) {
+    let stage = progress.stage(&stages);
+    log::info!("Starting stage {}: {}", progress.stage + 1, stage.name);
+    *stage_banner = StageBanner::for_stage(&locale, progress.stage as u32 + 1, &stage.name);
+    commands.insert_resource(ClearColor(stage.background));
+    commands.insert_resource(BackgroundLayers(stage.layers.clone()));
+    commands.insert_resource(BossEncounter::Approaching);
+    // Starting mid-stage skips the events before, so catch up on the scrolling
+    let speed = timelines
+        .get(&stage.timeline)
+        .and_then(|timeline| timeline.scroll_speed_at(progress.elapsed))
+        .unwrap_or(1.);
+    commands.insert_resource(BackgroundScroll { speed });
+    music.play(stage.music.as_deref());
+}
+
+/// Carries on with the current stage on its edited timeline
+fn follow_timeline_reloads(
+    mut events: EventReader<TimelineReloadedEvent>,
+    stages: Res<Stages>,
+    timelines: Res<Assets<StageTimeline>>,
+    progress: Res<StageProgress>,
+    mut scroll: ResMut<BackgroundScroll>,
+) {
+    let stage = progress.stage(&stages);
+    for event in events.read() {
+        if event.id != stage.timeline.id() {
+            continue;
+        }
+        let Some(timeline) = timelines.get(event.id) else {
+            continue;
+        };
+        let name = format!("the timeline of {}", stage.name);
+        report_changes(&name, &event.previous, timeline, progress.elapsed);
+        scroll.speed = timeline.scroll_speed_at(progress.elapsed).unwrap_or(1.);
+    }
+}
+
+/// Spawns a row of `enemy` spread evenly across the top of the playfield,
+/// firing `pattern` when given instead of the one in their definition
+pub(crate) fn spawn_wave(
+    spawn_events: &mut EventWriter<SpawnEnemy>,
+    playfield: &Playfield,
+    enemy: &str,
+    enemies: u32,
+    pattern: Option<&str>,
+) {
+    let overrides = EnemyOverrides {
+        pattern: pattern.map(str::to_string),
+        ..default()
+    };
+    // Keep the row off the very edges of the playfield
+    let width = playfield.rect.width() * 0.8;
+    let spacing = width / enemies as f32;
+    for i in 0..enemies {
+        let x = playfield.rect.center().x - width / 2. + spacing * (i as f32 + 0.5);
+        let position = Vec2::new(x, playfield.rect.max.y);
+        spawn_events.send(SpawnEnemy::new(enemy, position).with_overrides(overrides.clone()));
+    }
+}
+
+/// Carries out the timeline events of the current stage as their time comes
+fn run_stage_timeline(
+    mut commands: Commands,
+    time: Res<Time>,
+    stages: Res<Stages>,
+    timelines: Res<Assets<StageTimeline>>,
+    mut progress: ResMut<StageProgress>,
+    playfield: Res<Playfield>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut spawn_events: EventWriter<SpawnEnemy>,
+    mut scroll: ResMut<BackgroundScroll>,
+    mut checkpoint: ResMut<StageCheckpoint>,
+    score: Res<Score>,
+    difficulty: Res<Difficulty>,
+    mut start_boss_events: EventWriter<StartBossEvent>,
+) {
+    let from = progress.elapsed;
+    progress.elapsed += time.delta_seconds();
+    let stage = progress.stage(&stages);
+    let Some(timeline) = timelines.get(&stage.timeline) else {
+        return;
+    };
+
+    for event in timeline.events_between(from, progress.elapsed) {
+        match &event.action {
+            TimelineAction::SpawnWave {
+                enemies: count,
+                enemy,
+                pattern,
+            } => spawn_wave(
+                &mut spawn_events,
+                &playfield,
+                enemy.as_deref().unwrap_or(GRUNT),
+                *count,
+                pattern.as_deref(),
+            ),
+            TimelineAction::StartMidBoss => match &stage.mid_boss {
+                Some(spec) => {
+                    log::info!("Mid-boss incoming");
+                    let entity = spawn_boss(
+                        &mut commands,
+                        &mut meshes,
+                        &mut materials,
+                        &playfield,
+                        *difficulty,
+                        spec,
+                    );
+                    commands
+                        .entity(entity)
+                        .insert(EnemyKind(MID_BOSS_KIND.to_string()));
+                }
+                None => log::warn!("Stage {} has no mid-boss to start", stage.name),
+            },
+            TimelineAction::SetScrollSpeed(speed) => scroll.speed = *speed,
+            TimelineAction::StartBoss => start_boss_events.send_default(),
+            TimelineAction::Checkpoint => {
+                checkpoint.last = Some(StageSnapshot {
+                    stage: progress.stage,
+                    elapsed: event.at,
+                    score: score.0,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! The states the app goes through, from loading to the menus and runs, and
+//! moving between them.
+
+use bevy::log;
+use bevy::prelude::*;
+
+use crate::bindings::{Action, ActionState};
+use crate::modal::no_modal_open;
+use crate::settings::SettingsMenuState;
+
+/// Where the app is at. The transitions are:
+///
+/// - `Loading` → `MainMenu`, or `LoadingFailed` → `MainMenu` when some assets
+///   failed to load
+/// - `MainMenu` → `Credits`, `HighScores`, `SlotSelect`, `PracticeSelect` or
+///   `ModeSelect`, and back
+/// - `ModeSelect` → `DifficultySelect` → `Running`, or straight to `Running`
+///   for the modes with a fixed difficulty
+/// - `PracticeSelect` → `DifficultySelect` → `Running`
+/// - `Running` → `GameOver`, or `MainMenu` when quitting from the pause menu
+/// - `GameOver` → `Restarting` → `Running`, or `MainMenu`
+///
+/// While `Running`, `PauseState` tracks whether play is paused.
+#[derive(States, Default, Debug, Clone, Hash, Eq, PartialEq)]
+pub enum AppState {
+    /// Waiting on the assets
+    #[default]
+    Loading,
+    /// Listing the assets that failed to load
+    LoadingFailed,
+    MainMenu,
+    Credits,
+    HighScores,
+    Statistics,
+    SlotSelect,
+    /// Listing the saved replays, see `replay`
+    Replays,
+    ModeSelect,
+    DifficultySelect,
+    PracticeSelect,
+    /// Passed through for a single frame so `Running` is entered afresh
+    Restarting,
+    Running,
+    GameOver,
+}
+
+/// Sub-state of `AppState::Running`, and `Unpaused` in every other state.
+/// Kept separate from `AppState` so pausing doesn't run the `Running` exit
+/// systems and tear the whole run down.
+#[derive(States, Default, Debug, Clone, Hash, Eq, PartialEq)]
+pub enum PauseState {
+    #[default]
+    Unpaused,
+    Paused,
+    /// Frozen on the results of a cleared stage
+    StageClear,
+}
+
+/// The run is over, with the player out of lives or time
+#[derive(Event, Default, Clone, Copy, Debug)]
+pub struct GameOverEvent;
+
+pub struct StatePlugin;
+
+impl Plugin for StatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<AppState>()
+            .add_state::<PauseState>()
+            .add_event::<GameOverEvent>()
+            .add_systems(
+                Update,
+                (
+                    game_over.run_if(gameplay_active),
+                    toggle_pause.run_if(
+                        in_state(AppState::Running)
+                            .and_then(in_state(SettingsMenuState::Closed))
+                            .and_then(no_modal_open),
+                    ),
+                ),
+            )
+            .add_systems(OnEnter(AppState::Restarting), restart)
+            .add_systems(OnExit(AppState::Running), (teardown, unpause));
+    }
+}
+
+pub(crate) fn gameplay_active(
+    app_state: Res<State<AppState>>,
+    pause_state: Res<State<PauseState>>,
+) -> bool {
+    *app_state.get() == AppState::Running && *pause_state.get() == PauseState::Unpaused
+}
+
+fn game_over(mut events: EventReader<GameOverEvent>, mut next_state: ResMut<NextState<AppState>>) {
+    if events.read().count() > 0 {
+        log::info!("Game over");
+        next_state.set(AppState::GameOver);
+    }
+}
+
+fn toggle_pause(
+    actions: Res<ActionState>,
+    pause_state: Res<State<PauseState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+) {
+    if actions.just_pressed(Action::Pause) {
+        next_pause_state.set(match pause_state.get() {
+            PauseState::Unpaused => PauseState::Paused,
+            PauseState::Paused => PauseState::Unpaused,
+            // The results screen has to be dismissed with its own button
+            PauseState::StageClear => return,
+        });
+    }
+}
+
+fn unpause(mut next_pause_state: ResMut<NextState<PauseState>>) {
+    next_pause_state.set(PauseState::Unpaused);
+}
+
+fn restart(mut next_state: ResMut<NextState<AppState>>) {
+    *next_state = NextState(Some(AppState::Running));
+}
+
+/// Despawns everything from the run. The score and stats are kept around for
+/// the game over screen and reset when the next run starts.
+fn teardown(
+    mut commands: Commands,
+    entities: Query<Entity, (Without<bevy::window::PrimaryWindow>, Without<Camera>)>,
+) {
+    for entity in entities.iter() {
+        commands.entity(entity).despawn();
+    }
+}
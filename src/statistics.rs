@@ -0,0 +1,282 @@
+//! Lifetime statistics: the stats of every run played, added up. They are
+//! kept in the data directory, see `storage`, and shown next to the last
+//! run's on their own screen off the main menu.
+//!
+//! Every run counts as it ends, whatever the mode, practice included, so the
+//! time played is all of it. Watching replays doesn't count.
+
+use bevy::app::AppExit;
+use bevy::log;
+use bevy::prelude::*;
+use fluent::fluent_args;
+use serde::{Deserialize, Serialize};
+
+use crate::bindings::{Action, ActionState};
+use crate::locale::Locale;
+use crate::replay::watching_replay;
+use crate::score::RunStats;
+use crate::state::AppState;
+use crate::storage;
+use crate::theme::UiTheme;
+
+const STATISTICS_FILE: &str = "statistics.ron";
+/// Widths of the name, last run and lifetime columns
+const COLUMN_WIDTHS: [f32; 3] = [260., 150., 150.];
+
+/// What every run played so far adds up to
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct LifetimeStats {
+    pub(crate) runs: u32,
+    pub(crate) totals: RunStats,
+}
+
+impl LifetimeStats {
+    /// Starts out at zero when there is no readable file yet
+    fn load() -> Self {
+        let contents = match storage::read(STATISTICS_FILE) {
+            Ok(contents) => contents,
+            Err(error) => {
+                if error.kind() != std::io::ErrorKind::NotFound {
+                    log::warn!("Could not read the statistics: {error}");
+                }
+                return Self::default();
+            }
+        };
+        ron::from_str(&contents).unwrap_or_else(|error| {
+            log::warn!("Could not parse the statistics: {error}");
+            Self::default()
+        })
+    }
+
+    fn save(&self) {
+        let saved = ron::ser::to_string_pretty(self, default())
+            .map_err(|error| error.to_string())
+            .and_then(|contents| {
+                storage::write(STATISTICS_FILE, &contents).map_err(|error| error.to_string())
+            });
+        if let Err(error) = saved {
+            log::warn!("Could not save the statistics: {error}");
+        }
+    }
+}
+
+/// Whether a run was played since the game started, so the last run's stats
+/// are worth showing
+#[derive(Resource, Default)]
+struct RunPlayed(bool);
+
+#[derive(Component)]
+struct StatisticsScreen;
+
+#[derive(Component)]
+struct BackButton;
+
+pub struct StatisticsPlugin;
+
+impl Plugin for StatisticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LifetimeStats::load())
+            .init_resource::<RunPlayed>()
+            .add_systems(
+                OnExit(AppState::Running),
+                record_run.run_if(not(watching_replay)),
+            )
+            // Quitting the app never leaves `Running`, so record on the way out too
+            .add_systems(
+                Last,
+                record_run
+                    .run_if(in_state(AppState::Running).and_then(not(watching_replay)))
+                    .run_if(on_event::<AppExit>()),
+            )
+            .add_systems(OnEnter(AppState::Statistics), spawn_statistics_screen)
+            .add_systems(OnExit(AppState::Statistics), despawn_statistics_screen)
+            .add_systems(
+                Update,
+                statistics_buttons.run_if(in_state(AppState::Statistics)),
+            );
+    }
+}
+
+fn record_run(
+    mut lifetime: ResMut<LifetimeStats>,
+    mut run_played: ResMut<RunPlayed>,
+    run_stats: Res<RunStats>,
+) {
+    lifetime.runs += 1;
+    lifetime.totals.add(&run_stats);
+    lifetime.save();
+    run_played.0 = true;
+}
+
+/// Hours only show up once there are any
+fn format_duration(seconds: u64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
+
+/// The cells of every row: what is counted, then its count in `last` and
+/// `lifetime`
+fn rows(locale: &Locale, last: Option<&RunStats>, lifetime: &LifetimeStats) -> Vec<[String; 3]> {
+    let totals = &lifetime.totals;
+    let row = |id: &str, count: &dyn Fn(&RunStats) -> String| {
+        [
+            locale.get(id),
+            last.map_or("-".to_string(), count),
+            count(totals),
+        ]
+    };
+    let accuracy = |stats: &RunStats| {
+        stats.accuracy().map_or("-".to_string(), |accuracy| {
+            format!("{:.0}%", accuracy * 100.)
+        })
+    };
+    let mut rows = vec![
+        [
+            locale.get("statistics-runs"),
+            "-".to_string(),
+            lifetime.runs.to_string(),
+        ],
+        row("statistics-time", &|stats| {
+            format_duration(stats.time_survived.as_secs())
+        }),
+        row("statistics-shots", &|stats| stats.shots_fired.to_string()),
+        row("statistics-accuracy", &accuracy),
+        row("statistics-kills", &|stats| {
+            stats.enemies_killed.to_string()
+        }),
+    ];
+    for kind in totals.kills_by_kind.keys() {
+        let kills = |stats: &RunStats| {
+            stats
+                .kills_by_kind
+                .get(kind)
+                .copied()
+                .unwrap_or_default()
+                .to_string()
+        };
+        rows.push([
+            locale.format(
+                "statistics-kills-kind",
+                &fluent_args!["kind" => kind.as_str()],
+            ),
+            last.map_or("-".to_string(), kills),
+            kills(totals),
+        ]);
+    }
+    rows.push(row("statistics-grazes", &|stats| stats.grazes.to_string()));
+    rows.push(row("statistics-bombs", &|stats| {
+        stats.bombs_used.to_string()
+    }));
+    rows
+}
+
+fn spawn_statistics_screen(
+    mut commands: Commands,
+    theme: Res<UiTheme>,
+    locale: Res<Locale>,
+    run_stats: Res<RunStats>,
+    run_played: Res<RunPlayed>,
+    lifetime: Res<LifetimeStats>,
+) {
+    let last = run_played.0.then_some(&*run_stats);
+    let header = [
+        String::new(),
+        locale.get("statistics-last-run"),
+        locale.get("statistics-lifetime"),
+    ];
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(16.),
+                    ..default()
+                },
+                ..default()
+            },
+            StatisticsScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(locale.text("statistics", theme.title.clone()));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(4.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for cells in [header].into_iter().chain(rows(&locale, last, &lifetime)) {
+                        parent.spawn(NodeBundle::default()).with_children(|parent| {
+                            for (cell, width) in cells.into_iter().zip(COLUMN_WIDTHS) {
+                                parent
+                                    .spawn(NodeBundle {
+                                        style: Style {
+                                            width: Val::Px(width),
+                                            ..default()
+                                        },
+                                        ..default()
+                                    })
+                                    .with_children(|parent| {
+                                        parent.spawn(TextBundle::from_section(
+                                            cell,
+                                            theme.hud.clone(),
+                                        ));
+                                    });
+                            }
+                        });
+                    }
+                });
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            min_width: Val::Px(240.),
+                            height: Val::Px(50.),
+                            border: UiRect::all(Val::Px(5.)),
+                            padding: UiRect::horizontal(Val::Px(16.)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        border_color: BorderColor(Color::BLACK),
+                        background_color: Color::WHITE.into(),
+                        ..default()
+                    },
+                    BackButton,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(locale.text("button-back", theme.label.clone()));
+                });
+        });
+}
+
+fn despawn_statistics_screen(mut commands: Commands, query: Query<Entity, With<StatisticsScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn statistics_buttons(
+    actions: Res<ActionState>,
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<BackButton>)>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let pressed = interaction_query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed);
+    if pressed || actions.just_pressed(Action::MenuBack) {
+        next_state.set(AppState::MainMenu);
+    }
+}
@@ -0,0 +1,77 @@
+//! Where the game keeps what it remembers between launches: the platform's
+//! data directory, e.g. `~/.local/share/bevygame` on Linux,
+//! `~/Library/Application Support/me.nikl.bevygame` on macOS or
+//! `%APPDATA%\nikl\bevygame\data` on Windows. Platforms without one use the
+//! working directory instead. The web build keeps every file in the browser's
+//! local storage, under `bevygame/` and the file name.
+//!
+//! Everything saved goes through here, so files are named the same on every
+//! platform.
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use self::files::{read, write};
+#[cfg(target_arch = "wasm32")]
+pub(crate) use self::local_storage::{read, write};
+
+#[cfg(not(target_arch = "wasm32"))]
+mod files {
+    use std::io;
+    use std::path::PathBuf;
+
+    /// Where `file` is kept
+    fn data_path(file: &str) -> PathBuf {
+        match directories::ProjectDirs::from("me", "nikl", "bevygame") {
+            Some(dirs) => dirs.data_dir().join(file),
+            None => PathBuf::from(file),
+        }
+    }
+
+    pub(crate) fn read(file: &str) -> io::Result<String> {
+        std::fs::read_to_string(data_path(file))
+    }
+
+    /// Writes `file`, creating the data directory on the first save
+    pub(crate) fn write(file: &str, contents: &str) -> io::Result<()> {
+        let path = data_path(file);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, contents)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod local_storage {
+    use std::io;
+
+    use web_sys::wasm_bindgen::JsValue;
+    use web_sys::Storage;
+
+    fn key(file: &str) -> String {
+        format!("bevygame/{file}")
+    }
+
+    /// Fails in browsers with storage disabled, e.g. for some private windows
+    fn storage() -> io::Result<Storage> {
+        web_sys::window()
+            .ok_or_else(|| io::Error::other("there is no window"))?
+            .local_storage()
+            .map_err(js_error)?
+            .ok_or_else(|| io::Error::other("local storage is disabled"))
+    }
+
+    fn js_error(error: JsValue) -> io::Error {
+        io::Error::other(format!("{error:?}"))
+    }
+
+    pub(crate) fn read(file: &str) -> io::Result<String> {
+        storage()?
+            .get_item(&key(file))
+            .map_err(js_error)?
+            .ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+
+    pub(crate) fn write(file: &str, contents: &str) -> io::Result<()> {
+        storage()?.set_item(&key(file), contents).map_err(js_error)
+    }
+}
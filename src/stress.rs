@@ -0,0 +1,252 @@
+//! Stress-test harness: keeps the playfield saturated with bullets and enemies
+//! and records how long the movement and collision systems take each frame.
+//!
+//! Time is advanced by exactly one fixed timestep per frame, so every frame runs
+//! one simulation tick no matter how fast the machine is.
+//!
+//! Run it headless with `cargo run --release --example stress`.
+
+use std::time::{Duration, Instant};
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use rand::random;
+
+use crate::bullet::{create_bullet, Bullet};
+use crate::config::GameConfig;
+use crate::difficulty::Difficulty;
+use crate::enemies::{EnemyBook, GRUNT};
+use crate::enemy::{spawn_enemy, Enemy};
+use crate::glow::GlowMaterial;
+use crate::palette::Palette;
+use crate::player::Player;
+use crate::state::AppState;
+use crate::{GameRng, GameSet, HitPoints, Playfield};
+
+/// Frames at the start of a run that are left out of the report, so the
+/// initial mass spawn doesn't skew the numbers.
+const WARMUP_FRAMES: usize = 30;
+
+pub struct StressTestPlugin {
+    pub bullets: usize,
+    pub enemies: usize,
+    pub frames: usize,
+}
+
+impl Default for StressTestPlugin {
+    fn default() -> Self {
+        Self {
+            bullets: 10_000,
+            enemies: 200,
+            frames: 600,
+        }
+    }
+}
+
+impl Plugin for StressTestPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(StressConfig {
+            bullets: self.bullets,
+            enemies: self.enemies,
+            frames: self.frames,
+        })
+        .insert_resource(TimeUpdateStrategy::ManualDuration(
+            Time::<Fixed>::default().timestep(),
+        ))
+        .init_resource::<StressTimings>()
+        .add_systems(First, start_frame)
+        .add_systems(OnEnter(AppState::LoadingFailed), skip_missing_assets)
+        .add_systems(OnEnter(AppState::MainMenu), skip_main_menu)
+        .add_systems(
+            Update,
+            ((make_player_invulnerable, top_up_population).run_if(in_state(AppState::Running)),),
+        )
+        .add_systems(
+            FixedUpdate,
+            (
+                start_movement.before(GameSet::Movement),
+                end_movement.after(GameSet::Movement),
+                start_collision.before(GameSet::Collision),
+                end_collision.after(GameSet::Collision),
+            ),
+        )
+        .add_systems(Last, end_frame);
+    }
+}
+
+#[derive(Resource)]
+struct StressConfig {
+    bullets: usize,
+    enemies: usize,
+    frames: usize,
+}
+
+#[derive(Resource, Default)]
+struct StressTimings {
+    frame: usize,
+    frame_start: Option<Instant>,
+    movement_start: Option<Instant>,
+    collision_start: Option<Instant>,
+    frames: Vec<Duration>,
+    movement: Vec<Duration>,
+    collision: Vec<Duration>,
+}
+
+impl StressTimings {
+    fn recording(&self) -> bool {
+        self.frame >= WARMUP_FRAMES
+    }
+}
+
+/// Missing assets don't stop a benchmark, nor does anyone have to confirm it
+fn skip_missing_assets(mut next_state: ResMut<NextState<AppState>>) {
+    next_state.set(AppState::MainMenu);
+}
+
+/// Measurements start right away, without anyone pressing "Play"
+fn skip_main_menu(mut next_state: ResMut<NextState<AppState>>) {
+    next_state.set(AppState::Running);
+}
+
+fn make_player_invulnerable(mut query: Query<&mut HitPoints, Added<Player>>) {
+    for mut hp in query.iter_mut() {
+        hp.0 = u32::MAX;
+    }
+}
+
+fn top_up_population(
+    mut commands: Commands,
+    config: Res<StressConfig>,
+    bullet_query: Query<(), With<Bullet>>,
+    enemy_query: Query<(), With<Enemy>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut glow_materials: ResMut<Assets<GlowMaterial>>,
+    palette: Res<Palette>,
+    enemies: EnemyBook,
+    mut rng: ResMut<GameRng>,
+    playfield: Res<Playfield>,
+    difficulty: Res<Difficulty>,
+    game_config: Res<GameConfig>,
+) {
+    let center = playfield.rect.center();
+    let half_screen = playfield.rect.half_size();
+
+    for _ in bullet_query.iter().len()..config.bullets {
+        let position = Vec3::new(
+            center.x + (random::<f32>() * 2. - 1.) * half_screen.x,
+            center.y + (random::<f32>() * 2. - 1.) * half_screen.y,
+            0.,
+        );
+        let is_hostile = random::<bool>();
+        let velocity = if is_hostile {
+            Vec2::new(0., -game_config.enemy_bullet_speed)
+        } else {
+            Vec2::new(0., game_config.player.shot_speed)
+        };
+        commands.spawn(create_bullet(
+            position,
+            &mut meshes,
+            &mut glow_materials,
+            &palette,
+            game_config.bullet_radius,
+            velocity,
+            10,
+            is_hostile,
+        ));
+    }
+
+    for _ in enemy_query.iter().len()..config.enemies {
+        let position = Vec3::new(
+            center.x + (random::<f32>() * 2. - 1.) * half_screen.x * 0.8,
+            center.y + random::<f32>() * half_screen.y,
+            0.,
+        );
+        spawn_enemy(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            &palette,
+            enemies.get(GRUNT),
+            &mut rng,
+            &playfield,
+            *difficulty,
+            position,
+        );
+    }
+}
+
+fn start_frame(mut timings: ResMut<StressTimings>) {
+    timings.frame_start = Some(Instant::now());
+}
+
+fn end_frame(
+    config: Res<StressConfig>,
+    mut timings: ResMut<StressTimings>,
+    mut exit: EventWriter<AppExit>,
+    bullet_query: Query<(), With<Bullet>>,
+    enemy_query: Query<(), With<Enemy>>,
+) {
+    if let Some(start) = timings.frame_start.take() {
+        if timings.recording() {
+            timings.frames.push(start.elapsed());
+        }
+    }
+    timings.frame += 1;
+    if timings.frame >= config.frames + WARMUP_FRAMES {
+        println!(
+            "Stress test finished: {} frames with {} bullets and {} enemies alive at the end",
+            config.frames,
+            bullet_query.iter().len(),
+            enemy_query.iter().len(),
+        );
+        report("frame", &timings.frames);
+        report("movement", &timings.movement);
+        report("collision", &timings.collision);
+        exit.send(AppExit);
+    }
+}
+
+fn start_movement(mut timings: ResMut<StressTimings>) {
+    timings.movement_start = Some(Instant::now());
+}
+
+fn end_movement(mut timings: ResMut<StressTimings>) {
+    if let Some(start) = timings.movement_start.take() {
+        if timings.recording() {
+            timings.movement.push(start.elapsed());
+        }
+    }
+}
+
+fn start_collision(mut timings: ResMut<StressTimings>) {
+    timings.collision_start = Some(Instant::now());
+}
+
+fn end_collision(mut timings: ResMut<StressTimings>) {
+    if let Some(start) = timings.collision_start.take() {
+        if timings.recording() {
+            timings.collision.push(start.elapsed());
+        }
+    }
+}
+
+fn report(label: &str, samples: &[Duration]) {
+    let mut samples = samples.to_vec();
+    if samples.is_empty() {
+        println!("{label:>10}: no samples");
+        return;
+    }
+    samples.sort();
+    let average = samples.iter().sum::<Duration>() / samples.len() as u32;
+    let p95 = samples[(samples.len() * 95 / 100).min(samples.len() - 1)];
+    let max = samples[samples.len() - 1];
+    println!(
+        "{label:>10}: avg {:>8.3}ms  p95 {:>8.3}ms  max {:>8.3}ms  ({} samples)",
+        average.as_secs_f64() * 1000.,
+        p95.as_secs_f64() * 1000.,
+        max.as_secs_f64() * 1000.,
+        samples.len(),
+    );
+}
@@ -0,0 +1,185 @@
+//! Bundled UI font, and the text styles and button colors shared by the HUD
+//! and menus. The accessibility settings go through here: the text scale
+//! makes every text and button bigger, and high contrast draws buttons white
+//! on black and all text in pure white. Anything on screen is restyled when
+//! they or the palette change, and buttons are styled as they are spawned,
+//! so screens only need to build from the theme.
+
+use bevy::prelude::*;
+use bevy::ui::UiSystem;
+
+use crate::palette::{choose_palette, Palette};
+use crate::settings::Settings;
+
+pub(crate) const FONT_PATH: &str = "fonts/FiraSans-Bold.ttf";
+
+/// Text styles for every kind of UI text. All of them use the bundled font,
+/// which is loaded while in `AppState::Loading`.
+#[derive(Resource, Clone, Debug)]
+pub struct UiTheme {
+    pub font: Handle<Font>,
+    /// Screen titles like "Paused" or "Game over"
+    pub title: TextStyle,
+    /// The live score
+    pub score: TextStyle,
+    /// Smaller HUD readouts
+    pub hud: TextStyle,
+    /// Text on menu buttons
+    pub button: TextStyle,
+    /// Text on the narrower rows of option lists
+    pub label: TextStyle,
+    /// Fill of every button
+    pub button_fill: Color,
+    /// Border of every button but the focused one
+    pub button_border: Color,
+    /// Border of the button the keyboard and gamepad are on
+    pub focus_border: Color,
+    /// How much bigger than normal text and buttons are, see
+    /// `Settings::text_scale`
+    pub text_scale: f32,
+    pub high_contrast: bool,
+}
+
+impl UiTheme {
+    /// The theme the settings ask for, with titles and HUD text in `text`
+    /// unless in high contrast
+    pub fn new(font: Handle<Font>, settings: &Settings, text: Color) -> Self {
+        let scale = settings.text_scale;
+        let style = |font_size, color| TextStyle {
+            font: font.clone(),
+            font_size: font_size * scale,
+            color,
+        };
+        let (text, button_text, button_fill, button_border, focus_border) =
+            if settings.high_contrast {
+                (
+                    Color::WHITE,
+                    Color::WHITE,
+                    Color::BLACK,
+                    Color::WHITE,
+                    Color::YELLOW,
+                )
+            } else {
+                (text, Color::BLACK, Color::WHITE, Color::BLACK, Color::GOLD)
+            };
+        Self {
+            title: style(80., text),
+            score: style(40., text),
+            hud: style(20., text),
+            button: style(40., button_text),
+            label: style(28., button_text),
+            font,
+            button_fill,
+            button_border,
+            focus_border,
+            text_scale: scale,
+            high_contrast: settings.high_contrast,
+        }
+    }
+}
+
+pub struct ThemePlugin;
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            restyle_ui
+                .after(choose_palette)
+                .run_if(resource_exists::<UiTheme>()),
+        )
+        // Before the layout, so buttons spawned this frame never show up
+        // in the wrong style
+        .add_systems(
+            PostUpdate,
+            style_new_buttons
+                .before(UiSystem::Layout)
+                .run_if(resource_exists::<UiTheme>()),
+        );
+    }
+}
+
+/// A column for a screen's buttons that wraps into more columns rather than
+/// run off the screen, like it would with bigger text
+pub(crate) fn wrapping_column(row_gap: f32) -> Style {
+    Style {
+        flex_direction: FlexDirection::Column,
+        flex_wrap: FlexWrap::Wrap,
+        max_height: Val::Percent(80.),
+        align_items: AlignItems::Center,
+        align_content: AlignContent::Center,
+        row_gap: Val::Px(row_gap),
+        column_gap: Val::Px(16.),
+        ..default()
+    }
+}
+
+/// Multiplies a size in pixels, leaving relative ones alone
+fn scale_val(val: &mut Val, by: f32) {
+    if let Val::Px(px) = val {
+        *px *= by;
+    }
+}
+
+/// Screens size their buttons for normal text, so they grow along with it
+fn scale_button(style: &mut Style, by: f32) {
+    scale_val(&mut style.height, by);
+    scale_val(&mut style.min_width, by);
+    scale_val(&mut style.min_height, by);
+}
+
+fn style_new_buttons(
+    theme: Res<UiTheme>,
+    mut buttons: Query<(&mut Style, &mut BackgroundColor), Added<Button>>,
+) {
+    for (mut style, mut fill) in buttons.iter_mut() {
+        if theme.text_scale != 1. {
+            scale_button(&mut style, theme.text_scale);
+        }
+        fill.0 = theme.button_fill;
+    }
+}
+
+/// Switches to the theme the settings and palette ask for, once they ask for
+/// another. Text in the old colors takes the new ones and everything is
+/// resized by as much as the text scale changed.
+fn restyle_ui(
+    settings: Res<Settings>,
+    palette: Res<Palette>,
+    mut theme: ResMut<UiTheme>,
+    mut texts: Query<(&mut Text, Option<&Parent>)>,
+    mut buttons: Query<(&mut Style, &mut BackgroundColor), With<Button>>,
+) {
+    if !settings.is_changed() && !palette.is_changed() {
+        return;
+    }
+    let wanted = UiTheme::new(theme.font.clone(), &settings, palette.text);
+    if wanted.text_scale == theme.text_scale
+        && wanted.high_contrast == theme.high_contrast
+        && wanted.hud.color == theme.hud.color
+    {
+        return;
+    }
+    let ratio = wanted.text_scale / theme.text_scale;
+    for (mut text, parent) in texts.iter_mut() {
+        let on_button = parent.is_some_and(|parent| buttons.contains(parent.get()));
+        let (old, new) = if on_button {
+            (theme.button.color, wanted.button.color)
+        } else {
+            (theme.hud.color, wanted.hud.color)
+        };
+        for section in text.sections.iter_mut() {
+            section.style.font_size *= ratio;
+            if section.style.color == old {
+                section.style.color = new;
+            }
+        }
+    }
+    for (mut style, mut fill) in buttons.iter_mut() {
+        if ratio != 1. {
+            scale_button(&mut style, ratio);
+        }
+        fill.0 = wanted.button_fill;
+    }
+    *theme = wanted;
+}
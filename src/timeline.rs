@@ -0,0 +1,349 @@
+//! Stage timelines: what happens when during a stage, as RON assets under
+//! `assets/stages/`. With the `dev` feature the files are watched, so edits
+//! apply to the running game without recompiling or restarting the run.
+//! Events already in the past stay in the past, so tweaking a timeline
+//! mid-stage only affects what is yet to come, and the changes to it are
+//! logged. The scroll speed is the exception, and switches to whatever the
+//! new timeline has it at right away.
+//!
+//! Timelines serialize back to the same format with `StageTimeline::to_ron`,
+//! so editors can write files the game loads. Every file says which version
+//! of the format it is in, and the game won't load one newer than it knows.
+//! Waves naming an enemy or bullet pattern that doesn't exist are reported
+//! once loading is done, and again whenever the file is reloaded.
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::log;
+use bevy::prelude::*;
+use bevy::utils::{BoxedFuture, HashMap};
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::enemies::EnemyDefinitions;
+use crate::patterns::BulletPatterns;
+use crate::state::AppState;
+
+/// Version of the timeline format written by this build, and the newest one
+/// it can read. Files that leave it out are taken to be version 1.
+pub const TIMELINE_VERSION: u32 = 1;
+
+#[derive(Asset, TypePath, Serialize, Deserialize, Clone, Debug)]
+pub struct StageTimeline {
+    #[serde(default = "StageTimeline::first_version")]
+    pub version: u32,
+    pub events: Vec<TimelineEvent>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TimelineEvent {
+    /// Seconds from the start of the stage
+    pub at: f32,
+    pub action: TimelineAction,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum TimelineAction {
+    /// A row of enemies spread evenly across the top of the playfield
+    SpawnWave {
+        enemies: u32,
+        /// Name of the kind of enemy in the row, grunts when left out
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        enemy: Option<String>,
+        /// Bullet pattern the whole row fires instead of their own
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pattern: Option<String>,
+    },
+    StartMidBoss,
+    /// Sets how fast the background scrolls, 1 being the normal speed
+    SetScrollSpeed(f32),
+    /// Starts the boss warning, with the boss arriving right after it
+    StartBoss,
+    /// Marks the start of a section. After a game over the run can continue
+    /// from the last checkpoint reached
+    Checkpoint,
+}
+
+impl StageTimeline {
+    fn first_version() -> u32 {
+        1
+    }
+
+    /// Parses a timeline file, with its events in order
+    pub fn from_ron(bytes: &[u8]) -> Result<Self, StageTimelineLoaderError> {
+        let mut timeline: StageTimeline = ron::de::from_bytes(bytes)?;
+        if timeline.version > TIMELINE_VERSION {
+            return Err(StageTimelineLoaderError::NewerVersion(timeline.version));
+        }
+        timeline.events.sort_by(|a, b| a.at.total_cmp(&b.at));
+        Ok(timeline)
+    }
+
+    /// The timeline as a file the game can load, in the current version of
+    /// the format
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        let timeline = StageTimeline {
+            version: TIMELINE_VERSION,
+            events: self.events.clone(),
+        };
+        // One line per event
+        ron::ser::to_string_pretty(&timeline, PrettyConfig::new().depth_limit(2))
+    }
+
+    /// Everything wrong with the timeline, given which enemies and bullet
+    /// patterns exist
+    pub fn problems(
+        &self,
+        enemy_exists: impl Fn(&str) -> bool,
+        pattern_exists: impl Fn(&str) -> bool,
+    ) -> Vec<String> {
+        let mut problems = Vec::new();
+        for event in &self.events {
+            let at = event.at;
+            if !(at >= 0. && at.is_finite()) {
+                problems.push(format!("an event is at {at}, which is not a time"));
+            }
+            if let TimelineAction::SpawnWave {
+                enemies,
+                enemy,
+                pattern,
+            } = &event.action
+            {
+                if *enemies == 0 {
+                    problems.push(format!("the wave at {at}s has no enemies"));
+                }
+                if let Some(enemy) = enemy.as_deref().filter(|enemy| !enemy_exists(enemy)) {
+                    problems.push(format!("the wave at {at}s has an unknown enemy {enemy:?}"));
+                }
+                if let Some(pattern) = pattern
+                    .as_deref()
+                    .filter(|pattern| !pattern_exists(pattern))
+                {
+                    problems.push(format!(
+                        "the wave at {at}s has an unknown bullet pattern {pattern:?}"
+                    ));
+                }
+            }
+        }
+        problems
+    }
+
+    /// Events falling within `from..to`, in order
+    pub fn events_between(&self, from: f32, to: f32) -> impl Iterator<Item = &TimelineEvent> {
+        self.events
+            .iter()
+            .filter(move |event| event.at >= from && event.at < to)
+    }
+
+    /// The scroll speed the timeline has set by `at` seconds, if it has
+    pub fn scroll_speed_at(&self, at: f32) -> Option<f32> {
+        self.events_between(0., at)
+            .filter_map(|event| match event.action {
+                TimelineAction::SetScrollSpeed(speed) => Some(speed),
+                _ => None,
+            })
+            .last()
+    }
+
+    /// What changed from `previous` to this timeline, for events from `at`
+    /// seconds on
+    pub fn changes_after<'a>(
+        &'a self,
+        previous: &'a StageTimeline,
+        at: f32,
+    ) -> TimelineChanges<'a> {
+        let (upcoming, past): (Vec<_>, Vec<_>) =
+            self.events.iter().partition(|event| event.at >= at);
+        let previously_upcoming = previous.events.iter().filter(|event| event.at >= at);
+        TimelineChanges {
+            added: upcoming
+                .into_iter()
+                .filter(|event| !previous.events.contains(event))
+                .collect(),
+            removed: previously_upcoming
+                .filter(|event| !self.events.contains(event))
+                .collect(),
+            missed: past
+                .into_iter()
+                .filter(|event| !previous.events.contains(event))
+                .collect(),
+        }
+    }
+
+    /// When the boss starts, if it ever does
+    pub fn boss_time(&self) -> Option<f32> {
+        self.events
+            .iter()
+            .find(|event| event.action == TimelineAction::StartBoss)
+            .map(|event| event.at)
+    }
+}
+
+#[derive(Default)]
+pub struct StageTimelineLoader;
+
+#[derive(Debug, Error)]
+pub enum StageTimelineLoaderError {
+    #[error("could not read the timeline: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse the timeline: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("the timeline is version {0}, newer than this game's version {TIMELINE_VERSION}")]
+    NewerVersion(u32),
+}
+
+impl AssetLoader for StageTimelineLoader {
+    type Asset = StageTimeline;
+    type Settings = ();
+    type Error = StageTimelineLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<StageTimeline, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            StageTimeline::from_ron(&bytes)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["timeline.ron"]
+    }
+}
+
+/// How a timeline changed past some point in time
+#[derive(Debug, Default)]
+pub struct TimelineChanges<'a> {
+    /// In the new timeline only
+    pub added: Vec<&'a TimelineEvent>,
+    /// In the old timeline only
+    pub removed: Vec<&'a TimelineEvent>,
+    /// New events from before that point, which won't happen
+    pub missed: Vec<&'a TimelineEvent>,
+}
+
+impl TimelineChanges<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.missed.is_empty()
+    }
+}
+
+/// A timeline was reloaded, and was `previous` before
+#[derive(Event)]
+pub(crate) struct TimelineReloadedEvent {
+    pub(crate) id: AssetId<StageTimeline>,
+    pub(crate) previous: StageTimeline,
+}
+
+/// The last version seen of every timeline, to tell what a reload changed
+#[derive(Resource, Default)]
+struct TimelineHistory(HashMap<AssetId<StageTimeline>, StageTimeline>);
+
+pub struct TimelinePlugin;
+
+impl Plugin for TimelinePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<StageTimeline>()
+            .init_asset_loader::<StageTimelineLoader>()
+            .init_resource::<TimelineHistory>()
+            .add_event::<TimelineReloadedEvent>()
+            .add_systems(OnExit(AppState::Loading), check_timelines)
+            .add_systems(Update, log_timeline_reloads);
+    }
+}
+
+/// Logs what a reload changed from `elapsed` seconds into the timeline on,
+/// for the timeline being played
+pub(crate) fn report_changes(
+    name: &str,
+    previous: &StageTimeline,
+    timeline: &StageTimeline,
+    elapsed: f32,
+) {
+    let changes = timeline.changes_after(previous, elapsed);
+    if changes.is_empty() {
+        log::info!("No upcoming events changed in {name}");
+        return;
+    }
+    for event in changes.removed {
+        log::info!("Removed from {name}: {event:?}");
+    }
+    for event in changes.added {
+        log::info!("Added to {name}: {event:?}");
+    }
+    for event in changes.missed {
+        log::warn!("Added to {name} at {elapsed:.1}s, too late for {event:?}");
+    }
+}
+
+/// Warns about everything wrong with `timeline`
+fn report_problems(
+    path: &str,
+    timeline: &StageTimeline,
+    enemies: &EnemyDefinitions,
+    patterns: &BulletPatterns,
+) {
+    let problems = timeline.problems(
+        |enemy| enemies.0.contains_key(enemy),
+        |pattern| patterns.0.contains_key(pattern),
+    );
+    for problem in problems {
+        log::warn!("In the stage timeline {path}, {problem}");
+    }
+}
+
+fn check_timelines(
+    timelines: Res<Assets<StageTimeline>>,
+    enemies: Res<EnemyDefinitions>,
+    patterns: Res<BulletPatterns>,
+    asset_server: Res<AssetServer>,
+) {
+    for (id, timeline) in timelines.iter() {
+        let path = asset_server
+            .get_path(id)
+            .map_or_else(|| format!("{id:?}"), |path| path.to_string());
+        report_problems(&path, timeline, &enemies, &patterns);
+    }
+}
+
+fn log_timeline_reloads(
+    mut events: EventReader<AssetEvent<StageTimeline>>,
+    timelines: Res<Assets<StageTimeline>>,
+    mut history: ResMut<TimelineHistory>,
+    mut reloaded_events: EventWriter<TimelineReloadedEvent>,
+    enemies: Res<EnemyDefinitions>,
+    patterns: Res<BulletPatterns>,
+    asset_server: Res<AssetServer>,
+) {
+    for event in events.read() {
+        match *event {
+            AssetEvent::Added { id } => {
+                if let Some(timeline) = timelines.get(id) {
+                    history.0.insert(id, timeline.clone());
+                }
+            }
+            AssetEvent::Modified { id } => {
+                let path = asset_server.get_path(id);
+                log::info!("Reloaded stage timeline {path:?}");
+                let Some(timeline) = timelines.get(id) else {
+                    continue;
+                };
+                if let Some(path) = path {
+                    report_problems(&path.to_string(), timeline, &enemies, &patterns);
+                }
+                if let Some(previous) = history.0.insert(id, timeline.clone()) {
+                    reloaded_events.send(TimelineReloadedEvent { id, previous });
+                }
+            }
+            AssetEvent::Removed { id } => {
+                history.0.remove(&id);
+            }
+            _ => {}
+        }
+    }
+}
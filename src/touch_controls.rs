@@ -0,0 +1,285 @@
+//! On-screen controls for touch screens: a virtual joystick in the bottom left
+//! corner, and fire and bomb buttons in the bottom right one. They show up
+//! during runs once the screen is touched, and go away again as soon as a key
+//! or gamepad button is pressed. They stay hidden while the ship follows or is
+//! dragged by the finger instead, see `pointer`.
+//!
+//! They are drawn on the UI layer, but read every finger on the screen rather
+//! than the UI's single pointer, so the ship can move and fire at once. What
+//! they are pushed or held down by is added to `ActionState`, and the rest of
+//! the game takes it like any other input.
+
+use bevy::input::touch::Touch;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::ui::UiSystem;
+
+use crate::bindings::{update_actions, Action, ActionState};
+use crate::locale::Locale;
+use crate::pointer::PointerMovement;
+use crate::settings::Settings;
+use crate::state::{gameplay_active, AppState, PauseState};
+use crate::theme::UiTheme;
+
+const JOYSTICK_SIZE: f32 = 180.;
+const KNOB_SIZE: f32 = 70.;
+const BUTTON_SIZE: f32 = 110.;
+const BORDER_WIDTH: f32 = 3.;
+const MARGIN: f32 = 40.;
+/// Clear of the HUD text along the bottom
+const BOTTOM_MARGIN: f32 = 60.;
+/// How far out the joystick has to be pushed to move the ship, as a fraction
+/// of its radius
+const DEAD_ZONE: f32 = 0.15;
+const FILL: Color = Color::rgba(1., 1., 1., 0.15);
+const HELD_FILL: Color = Color::rgba(1., 1., 1., 0.35);
+const BORDER: Color = Color::rgba(1., 1., 1., 0.5);
+
+/// Whether a touch screen is in use, and the finger on the joystick
+#[derive(Resource, Default, Debug)]
+pub struct TouchControls {
+    /// Set by the first touch, and cleared by keys and gamepad buttons
+    pub detected: bool,
+    joystick_finger: Option<u64>,
+}
+
+#[derive(Component)]
+struct TouchControlsRoot;
+
+#[derive(Component)]
+struct Joystick;
+
+#[derive(Component)]
+struct JoystickKnob;
+
+/// Holds its action down while touched
+#[derive(Component)]
+struct TouchButton(Action);
+
+pub struct TouchControlsPlugin;
+
+impl Plugin for TouchControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TouchControls>()
+            .add_systems(OnEnter(AppState::Running), spawn_touch_controls)
+            // Adding to the actions once they are read from the keys and
+            // buttons, ahead of the fixed ticks and menus reading them
+            .add_systems(
+                PreUpdate,
+                (
+                    detect_touch_screen,
+                    show_touch_controls,
+                    // Headless runs have no UI to touch
+                    read_touch_controls.run_if(
+                        resource_exists::<UiScale>()
+                            .and_then(touch_controls_shown)
+                            .and_then(gameplay_active),
+                    ),
+                )
+                    .chain()
+                    .after(InputSystem)
+                    .after(update_actions)
+                    .before(UiSystem::Focus),
+            );
+    }
+}
+
+fn touch_controls_shown(controls: Res<TouchControls>, settings: Res<Settings>) -> bool {
+    controls.detected && settings.pointer_movement == PointerMovement::Off
+}
+
+fn spawn_touch_controls(mut commands: Commands, theme: Res<UiTheme>, locale: Res<Locale>) {
+    let node = |size: f32| Style {
+        width: Val::Px(size),
+        height: Val::Px(size),
+        border: UiRect::all(Val::Px(BORDER_WIDTH)),
+        ..default()
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    justify_content: JustifyContent::SpaceBetween,
+                    align_items: AlignItems::FlexEnd,
+                    padding: UiRect {
+                        left: Val::Px(MARGIN),
+                        right: Val::Px(MARGIN),
+                        bottom: Val::Px(BOTTOM_MARGIN),
+                        ..default()
+                    },
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            TouchControlsRoot,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: node(JOYSTICK_SIZE),
+                        background_color: FILL.into(),
+                        border_color: BORDER.into(),
+                        ..default()
+                    },
+                    Joystick,
+                ))
+                .with_children(|parent| {
+                    let mut style = Style {
+                        position_type: PositionType::Absolute,
+                        ..node(KNOB_SIZE)
+                    };
+                    place_knob(&mut style, Vec2::ZERO);
+                    parent.spawn((
+                        NodeBundle {
+                            style,
+                            background_color: HELD_FILL.into(),
+                            border_color: BORDER.into(),
+                            ..default()
+                        },
+                        JoystickKnob,
+                    ));
+                });
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(MARGIN / 2.),
+                        align_items: AlignItems::FlexEnd,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    // Bomb up and to the left of fire, where the thumb
+                    // reaches it without leaving fire for long
+                    for (action, bottom) in [(Action::Bomb, BUTTON_SIZE / 2.), (Action::Fire, 0.)] {
+                        parent
+                            .spawn((
+                                NodeBundle {
+                                    style: Style {
+                                        justify_content: JustifyContent::Center,
+                                        align_items: AlignItems::Center,
+                                        margin: UiRect::bottom(Val::Px(bottom)),
+                                        ..node(BUTTON_SIZE)
+                                    },
+                                    background_color: FILL.into(),
+                                    border_color: BORDER.into(),
+                                    ..default()
+                                },
+                                TouchButton(action),
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(locale.text(action.label(), theme.hud.clone()));
+                            });
+                    }
+                });
+        });
+}
+
+fn detect_touch_screen(
+    touches: Res<Touches>,
+    keyboard: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    mut controls: ResMut<TouchControls>,
+) {
+    if touches.any_just_pressed() {
+        controls.detected = true;
+    } else if keyboard.get_just_pressed().next().is_some()
+        || gamepad_buttons.get_just_pressed().next().is_some()
+    {
+        controls.detected = false;
+    }
+}
+
+/// Hidden outside of play too, so they don't sit over the pause menu. Letting
+/// go of the joystick when hiding it keeps the ship from running off once it
+/// shows up again.
+fn show_touch_controls(
+    mut controls: ResMut<TouchControls>,
+    settings: Res<Settings>,
+    app_state: Res<State<AppState>>,
+    pause_state: Res<State<PauseState>>,
+    mut roots: Query<&mut Visibility, With<TouchControlsRoot>>,
+    mut knobs: Query<&mut Style, With<JoystickKnob>>,
+) {
+    let shown = controls.detected
+        && settings.pointer_movement == PointerMovement::Off
+        && gameplay_active(app_state, pause_state);
+    for mut visibility in roots.iter_mut() {
+        visibility.set_if_neq(if shown {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+    if !shown && controls.joystick_finger.take().is_some() {
+        for mut style in knobs.iter_mut() {
+            place_knob(&mut style, Vec2::ZERO);
+        }
+    }
+}
+
+/// Puts the knob as far out from the center of the joystick as it is pushed
+fn place_knob(style: &mut Style, push: Vec2) {
+    // Inside the border, which absolute positions start from
+    let radius = JOYSTICK_SIZE / 2. - BORDER_WIDTH;
+    let corner = radius - KNOB_SIZE / 2.;
+    style.left = Val::Px(corner + push.x * radius);
+    // UI coordinates grow downwards
+    style.top = Val::Px(corner - push.y * radius);
+}
+
+fn read_touch_controls(
+    mut controls: ResMut<TouchControls>,
+    touches: Res<Touches>,
+    ui_scale: Res<UiScale>,
+    joysticks: Query<(&Node, &GlobalTransform), With<Joystick>>,
+    mut knobs: Query<&mut Style, With<JoystickKnob>>,
+    mut buttons: Query<(&TouchButton, &Node, &GlobalTransform, &mut BackgroundColor)>,
+    mut actions: ResMut<ActionState>,
+) {
+    // Touches are in window coordinates, and the UI is scaled from those
+    let position = |touch: &Touch| touch.position() / ui_scale.0 as f32;
+
+    if let Ok((node, transform)) = joysticks.get_single() {
+        let rect = node.logical_rect(transform);
+        // The finger that went down on it keeps steering it, even past its
+        // edges, until lifted
+        let held = controls
+            .joystick_finger
+            .is_some_and(|finger| touches.get_pressed(finger).is_some());
+        if !held {
+            controls.joystick_finger = touches
+                .iter_just_pressed()
+                .find(|touch| rect.contains(position(touch)))
+                .map(Touch::id);
+        }
+        let push = controls
+            .joystick_finger
+            .and_then(|finger| touches.get_pressed(finger))
+            .map_or(Vec2::ZERO, |touch| {
+                let offset = (position(touch) - rect.center()) / (rect.width() / 2.);
+                Vec2::new(offset.x, -offset.y).clamp_length_max(1.)
+            });
+        if push.length() > DEAD_ZONE {
+            actions.push_stick(push);
+        }
+        for mut style in knobs.iter_mut() {
+            place_knob(&mut style, push);
+        }
+    }
+
+    for (button, node, transform, mut fill) in buttons.iter_mut() {
+        let rect = node.logical_rect(transform);
+        let on_button = |touch: &Touch| rect.contains(position(touch));
+        let held = touches.iter().any(on_button);
+        if held {
+            actions.press(button.0, touches.iter_just_pressed().any(on_button));
+        }
+        fill.0 = if held { HELD_FILL } else { FILL };
+    }
+}
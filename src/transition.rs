@@ -0,0 +1,215 @@
+//! Transition between stages. Once a boss goes down, the remaining hostile
+//! bullets vanish, the ship flies itself to the bottom of the playfield and
+//! the screen fades to black behind the results. Continuing fades back in on
+//! the next stage, with its background and banner already in place.
+
+use bevy::prelude::*;
+
+use crate::boss::StageClearedEvent;
+use crate::bullet::{Bullet, Hostility};
+use crate::mode::practice_mode;
+use crate::player::{Player, PlayerControllerSet, TickInput};
+use crate::state::{gameplay_active, AppState, PauseState};
+use crate::{GameSet, Playfield};
+
+const FLY_SPEED: f32 = 400.;
+/// Gives up on reaching the spot, in case something is in the way
+const FLY_TIMEOUT_SECONDS: f32 = 2.;
+/// Distance from the bottom of the playfield the ship parks at
+const PARKING_HEIGHT: f32 = 100.;
+const FADE_SECONDS: f32 = 0.6;
+
+/// The steps of a stage transition, `Idle` outside of one. They are timed on
+/// the fixed ticks, so a replay goes through them on the same ticks as the run.
+#[derive(Resource, Default, Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub enum StageTransition {
+    #[default]
+    Idle,
+    /// The ship flies to center-bottom on its own
+    FlyOut,
+    FadeOut,
+    /// Black screen behind `PauseState::StageClear`
+    Results,
+    /// The next stage fades in
+    FadeIn,
+}
+
+#[derive(Resource)]
+struct TransitionTimer(Timer);
+
+/// Full-screen black layer, under the UI but over the playfield
+#[derive(Component)]
+struct FadeOverlay;
+
+pub struct StageTransitionPlugin;
+
+impl Plugin for StageTransitionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StageTransition>()
+            .insert_resource(TransitionTimer(Timer::from_seconds(0., TimerMode::Once)))
+            .add_systems(
+                FixedUpdate,
+                (
+                    fly_player_out
+                        .after(PlayerControllerSet)
+                        .in_set(GameSet::Input)
+                        .run_if(resource_equals(StageTransition::FlyOut)),
+                    (
+                        begin_transition.run_if(resource_equals(StageTransition::Idle)),
+                        advance_fade,
+                    )
+                        .in_set(GameSet::Resolution),
+                ),
+            )
+            .add_systems(Update, show_fade.run_if(gameplay_active))
+            .add_systems(
+                OnExit(PauseState::StageClear),
+                // Practice leaves the run after the results instead
+                start_fade_in.run_if(not(practice_mode)),
+            )
+            .add_systems(OnExit(AppState::Running), reset_transition);
+    }
+}
+
+/// Run condition keeping the simulation still once the fade-out is over. The
+/// results only pause the game on the next frame, and the ticks left in this
+/// one must not run, since how many there are depends on the frame rate.
+pub(crate) fn simulation_running(transition: Res<StageTransition>) -> bool {
+    *transition != StageTransition::Results
+}
+
+fn begin_transition(
+    mut commands: Commands,
+    mut events: EventReader<StageClearedEvent>,
+    bullets: Query<(Entity, &Hostility), With<Bullet>>,
+    mut transition: ResMut<StageTransition>,
+    mut timer: ResMut<TransitionTimer>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+    for (entity, hostility) in bullets.iter() {
+        if let Hostility::Hostile = hostility {
+            commands.entity(entity).despawn();
+        }
+    }
+    *transition = StageTransition::FlyOut;
+    timer.0 = Timer::from_seconds(FLY_TIMEOUT_SECONDS, TimerMode::Once);
+}
+
+/// Takes the controls away from the player and steers the ship instead
+fn fly_player_out(
+    mut commands: Commands,
+    time: Res<Time>,
+    playfield: Res<Playfield>,
+    mut transition: ResMut<StageTransition>,
+    mut timer: ResMut<TransitionTimer>,
+    mut tick_input: ResMut<TickInput>,
+    mut players: Query<&mut Transform, With<Player>>,
+) {
+    *tick_input = TickInput::default();
+    let target = Vec2::new(
+        playfield.rect.center().x,
+        playfield.rect.min.y + PARKING_HEIGHT,
+    );
+    let mut arrived = true;
+    for mut transform in players.iter_mut() {
+        let position = transform.translation.truncate();
+        let step = FLY_SPEED * time.delta_seconds();
+        let offset = target - position;
+        let position = if offset.length() <= step {
+            target
+        } else {
+            arrived = false;
+            position + offset.normalize() * step
+        };
+        transform.translation = position.extend(transform.translation.z);
+    }
+    if arrived || timer.0.tick(time.delta()).finished() {
+        *transition = StageTransition::FadeOut;
+        timer.0 = Timer::from_seconds(FADE_SECONDS, TimerMode::Once);
+        spawn_fade_overlay(&mut commands);
+    }
+}
+
+fn spawn_fade_overlay(commands: &mut Commands) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                ..default()
+            },
+            background_color: Color::rgba(0., 0., 0., 0.).into(),
+            // Below the HUD and the results screen
+            z_index: ZIndex::Global(-1),
+            ..default()
+        },
+        FadeOverlay,
+    ));
+}
+
+/// Ends the fades on the tick their time is up
+fn advance_fade(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut transition: ResMut<StageTransition>,
+    mut timer: ResMut<TransitionTimer>,
+    overlays: Query<Entity, With<FadeOverlay>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+) {
+    let fading = matches!(
+        *transition,
+        StageTransition::FadeOut | StageTransition::FadeIn
+    );
+    if !fading || !timer.0.tick(time.delta()).finished() {
+        return;
+    }
+    if *transition == StageTransition::FadeOut {
+        *transition = StageTransition::Results;
+        next_pause_state.set(PauseState::StageClear);
+    } else {
+        *transition = StageTransition::Idle;
+        despawn_fade_overlays(&mut commands, &overlays);
+    }
+}
+
+fn show_fade(
+    transition: Res<StageTransition>,
+    timer: Res<TransitionTimer>,
+    mut overlays: Query<&mut BackgroundColor, With<FadeOverlay>>,
+) {
+    let alpha = match *transition {
+        StageTransition::FadeOut => timer.0.percent(),
+        StageTransition::FadeIn => timer.0.percent_left(),
+        StageTransition::Results => 1.,
+        _ => return,
+    };
+    for mut color in overlays.iter_mut() {
+        color.0.set_a(alpha);
+    }
+}
+
+fn start_fade_in(mut transition: ResMut<StageTransition>, mut timer: ResMut<TransitionTimer>) {
+    if *transition == StageTransition::Results {
+        *transition = StageTransition::FadeIn;
+        timer.0 = Timer::from_seconds(FADE_SECONDS, TimerMode::Once);
+    }
+}
+
+fn despawn_fade_overlays(commands: &mut Commands, overlays: &Query<Entity, With<FadeOverlay>>) {
+    for entity in overlays.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Leaving the run mid-transition, or at the end of practice
+fn reset_transition(
+    mut commands: Commands,
+    mut transition: ResMut<StageTransition>,
+    overlays: Query<Entity, With<FadeOverlay>>,
+) {
+    *transition = StageTransition::Idle;
+    despawn_fade_overlays(&mut commands, &overlays);
+}
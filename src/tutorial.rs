@@ -0,0 +1,202 @@
+//! First-run tutorial. Until the player has tried every control once, runs
+//! start with a prompt per control, and each prompt goes away as soon as its
+//! input is used. Finishing the tutorial is remembered across launches.
+
+use bevy::log;
+use bevy::prelude::*;
+
+use fluent::fluent_args;
+
+use crate::bindings::{Action, KeyBindings};
+use crate::locale::Locale;
+use crate::player::TickInput;
+use crate::replay::watching_replay;
+use crate::settings::{ControlScheme, Settings};
+use crate::state::{gameplay_active, AppState};
+use crate::storage;
+use crate::theme::UiTheme;
+
+const TUTORIAL_FILE: &str = "tutorial.txt";
+const COMPLETED: &str = "completed";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TutorialStep {
+    Move,
+    Shoot,
+    Focus,
+    Bomb,
+}
+
+impl TutorialStep {
+    const ALL: [TutorialStep; 4] = [
+        TutorialStep::Move,
+        TutorialStep::Shoot,
+        TutorialStep::Focus,
+        TutorialStep::Bomb,
+    ];
+
+    /// Message prompting for the step
+    fn prompt(self, scheme: ControlScheme) -> &'static str {
+        match (self, scheme) {
+            (TutorialStep::Move, ControlScheme::Standard) => "tutorial-move-standard",
+            (TutorialStep::Move, ControlScheme::Classic) => "tutorial-move-classic",
+            (TutorialStep::Shoot, ControlScheme::Standard) => "tutorial-shoot-standard",
+            (TutorialStep::Shoot, ControlScheme::Classic) => "tutorial-shoot-classic",
+            (TutorialStep::Focus, _) => "tutorial-focus",
+            (TutorialStep::Bomb, ControlScheme::Standard) => "tutorial-bomb-standard",
+            (TutorialStep::Bomb, ControlScheme::Classic) => "tutorial-bomb-classic",
+        }
+    }
+
+    /// Prompt naming the keys bound to the step's actions, for bindings that
+    /// follow no control scheme
+    fn custom_prompt(self, bindings: &KeyBindings, locale: &Locale) -> String {
+        let (id, actions): (_, &[Action]) = match self {
+            TutorialStep::Move => (
+                "tutorial-move-custom",
+                &[
+                    Action::MoveUp,
+                    Action::MoveDown,
+                    Action::MoveLeft,
+                    Action::MoveRight,
+                ],
+            ),
+            TutorialStep::Shoot => ("tutorial-shoot-custom", &[Action::Fire]),
+            TutorialStep::Focus => ("tutorial-focus-custom", &[Action::Focus]),
+            TutorialStep::Bomb => ("tutorial-bomb-custom", &[Action::Bomb]),
+        };
+        let keys = actions
+            .iter()
+            .flat_map(|action| bindings.inputs(*action))
+            .map(|key| format!("{key:?}"))
+            .collect::<Vec<_>>()
+            .join("/");
+        locale.format(id, &fluent_args!["keys" => keys])
+    }
+
+    fn done_by(self, input: &TickInput) -> bool {
+        match self {
+            TutorialStep::Move => input.movement != Vec2::ZERO,
+            TutorialStep::Shoot => input.fire,
+            TutorialStep::Focus => input.focus,
+            TutorialStep::Bomb => input.bomb,
+        }
+    }
+}
+
+/// Steps the player still has to go through
+#[derive(Resource, Debug)]
+struct TutorialProgress {
+    remaining: Vec<TutorialStep>,
+}
+
+impl TutorialProgress {
+    /// Starts from scratch unless a previous launch finished the tutorial
+    fn load() -> Self {
+        let completed =
+            storage::read(TUTORIAL_FILE).is_ok_and(|contents| contents.trim() == COMPLETED);
+        Self {
+            remaining: if completed {
+                Vec::new()
+            } else {
+                TutorialStep::ALL.to_vec()
+            },
+        }
+    }
+
+    fn save_completed() {
+        if let Err(error) = storage::write(TUTORIAL_FILE, COMPLETED) {
+            log::warn!("Could not save the tutorial progress: {error}");
+        }
+    }
+}
+
+#[derive(Component)]
+struct TutorialPrompts;
+
+#[derive(Component)]
+struct TutorialPrompt(TutorialStep);
+
+pub struct TutorialPlugin;
+
+impl Plugin for TutorialPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TutorialProgress::load())
+            // Replays are watched without the prompts
+            .add_systems(
+                OnEnter(AppState::Running),
+                spawn_tutorial_prompts.run_if(not(watching_replay)),
+            )
+            .add_systems(
+                Update,
+                advance_tutorial.run_if(gameplay_active.and_then(not(watching_replay))),
+            );
+    }
+}
+
+fn spawn_tutorial_prompts(
+    mut commands: Commands,
+    progress: Res<TutorialProgress>,
+    settings: Res<Settings>,
+    theme: Res<UiTheme>,
+    locale: Res<Locale>,
+) {
+    if progress.remaining.is_empty() {
+        return;
+    }
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    bottom: Val::Percent(20.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(8.),
+                    ..default()
+                },
+                ..default()
+            },
+            TutorialPrompts,
+        ))
+        .with_children(|parent| {
+            for step in progress.remaining.iter() {
+                let mut prompt = parent.spawn(TutorialPrompt(*step));
+                match settings.bindings.scheme() {
+                    Some(scheme) => {
+                        prompt.insert(locale.text(step.prompt(scheme), theme.hud.clone()))
+                    }
+                    None => prompt.insert(TextBundle::from_section(
+                        step.custom_prompt(&settings.bindings, &locale),
+                        theme.hud.clone(),
+                    )),
+                };
+            }
+        });
+}
+
+fn advance_tutorial(
+    mut commands: Commands,
+    tick_input: Res<TickInput>,
+    mut progress: ResMut<TutorialProgress>,
+    prompts: Query<(Entity, &TutorialPrompt)>,
+    roots: Query<Entity, With<TutorialPrompts>>,
+) {
+    if progress.remaining.is_empty() {
+        return;
+    }
+    progress.remaining.retain(|step| !step.done_by(&tick_input));
+    for (entity, prompt) in prompts.iter() {
+        if !progress.remaining.contains(&prompt.0) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    if progress.remaining.is_empty() {
+        log::info!("Tutorial completed");
+        TutorialProgress::save_completed();
+        for entity in roots.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,677 @@
+//! The HUD shown during runs, and the main menu, pause menu and game over
+//! screen with the buttons on them.
+
+use std::marker::PhantomData;
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use fluent::fluent_args;
+
+use crate::config::GameConfig;
+use crate::high_scores::{HighScores, NameEntry, NameEntryText};
+use crate::hud::{HudAnchor, HudRegion};
+use crate::locale::Locale;
+use crate::modal::{spawn_confirm_dialog, ConfirmDialog};
+use crate::mode::{GameMode, TimeLimit};
+use crate::palette::Palette;
+use crate::player::{spawn_player, Bombs, Lives, Player};
+use crate::replay::{ReplayPlayback, ReplayRecorder, SaveReplayEvent};
+use crate::score::{Combo, HighScore, RunStats, Score};
+use crate::settings::{Settings, SettingsMenuState};
+use crate::stage::StageCheckpoint;
+use crate::state::{AppState, PauseState};
+use crate::theme::{wrapping_column, UiTheme, FONT_PATH};
+use crate::{HitPoints, MaxHitPoints};
+
+const HUD_ICON_SIZE: f32 = 14.;
+
+#[derive(Component)]
+struct MainMenuScreen;
+
+#[derive(Component)]
+struct PauseMenu;
+
+#[derive(Component)]
+struct GameOverScreen;
+
+#[derive(Component, Clone, Copy, Debug)]
+enum MenuButton {
+    Play,
+    Practice,
+    HighScores,
+    Statistics,
+    SaveSlots,
+    /// Picks the run back up from the last checkpoint after a game over
+    Continue,
+    Credits,
+    /// Closes the game, unlike `Quit` which only leaves the run
+    Exit,
+    Retry,
+    SaveReplay,
+    MainMenu,
+    Resume,
+    Settings,
+    Quit,
+}
+
+#[derive(Component)]
+pub(crate) struct HpBarFill;
+
+#[derive(Component)]
+struct HpText;
+
+#[derive(Component)]
+struct ComboText;
+
+/// Resources the HUD can display as a line of text
+pub(crate) trait HudValue: Resource {
+    fn hud_text(&self, locale: &Locale) -> String;
+}
+
+impl HudValue for Score {
+    fn hud_text(&self, _locale: &Locale) -> String {
+        self.0.to_string()
+    }
+}
+
+impl HudValue for HighScore {
+    fn hud_text(&self, locale: &Locale) -> String {
+        locale.format("hud-high-score", &fluent_args!["score" => self.0])
+    }
+}
+
+/// Resources the HUD can display as a row of icons, one per unit
+trait HudIcons: Resource {
+    fn icon_color(palette: &Palette) -> Color;
+
+    fn icon_count(&self) -> u32;
+}
+
+impl HudIcons for Lives {
+    fn icon_color(palette: &Palette) -> Color {
+        palette.life_icon
+    }
+
+    fn icon_count(&self) -> u32 {
+        self.0
+    }
+}
+
+impl HudIcons for Bombs {
+    fn icon_color(palette: &Palette) -> Color {
+        palette.bomb_icon
+    }
+
+    fn icon_count(&self) -> u32 {
+        self.0
+    }
+}
+
+/// Marks a row node whose children are rebuilt as one icon per unit of `R`
+/// whenever `R` or the palette changes, or the row was just spawned.
+#[derive(Component)]
+struct HudIconRow<R: HudIcons>(PhantomData<R>);
+
+impl<R: HudIcons> Default for HudIconRow<R> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+/// Marks a text entity whose first section mirrors the resource `R`. The text
+/// is only rewritten when `R` or the language changes, or the binding was just
+/// spawned.
+#[derive(Component)]
+pub(crate) struct HudBinding<R: HudValue>(PhantomData<R>);
+
+impl<R: HudValue> Default for HudBinding<R> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+pub struct UiPlugin;
+
+impl Plugin for UiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load_ui_assets)
+            .add_systems(OnEnter(AppState::Running), spawn_hud.after(spawn_player))
+            .add_systems(
+                Update,
+                (
+                    menu_buttons.run_if(any_with_component::<MenuButton>()),
+                    (
+                        update_hud_binding::<Score>,
+                        update_hud_binding::<HighScore>,
+                        update_hud_icon_row::<Lives>,
+                        update_hud_icon_row::<Bombs>,
+                        update_hp_bar,
+                        update_combo_text.run_if(
+                            resource_changed::<Combo>().or_else(resource_changed::<Locale>()),
+                        ),
+                    )
+                        .run_if(in_state(AppState::Running)),
+                ),
+            )
+            .add_systems(OnEnter(AppState::MainMenu), spawn_main_menu)
+            .add_systems(OnExit(AppState::MainMenu), despawn_main_menu)
+            .add_systems(OnEnter(AppState::GameOver), spawn_game_over_screen)
+            .add_systems(OnExit(AppState::GameOver), despawn_game_over_screen)
+            .add_systems(OnEnter(PauseState::Paused), spawn_pause_menu)
+            .add_systems(OnExit(PauseState::Paused), despawn_pause_menu);
+    }
+}
+
+fn load_ui_assets(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    palette: Res<Palette>,
+) {
+    let font = asset_server.load(FONT_PATH);
+    commands.insert_resource(UiTheme::new(font, &settings, palette.text));
+}
+
+fn spawn_hud(
+    mut commands: Commands,
+    theme: Res<UiTheme>,
+    palette: Res<Palette>,
+    config: Res<GameConfig>,
+    locale: Res<Locale>,
+) {
+    commands.spawn((
+        TextBundle::from_section("0", theme.score.clone()),
+        HudBinding::<Score>::default(),
+        HudAnchor(HudRegion::TopLeft),
+    ));
+    commands.spawn((
+        TextBundle::from_section("", theme.hud.clone()),
+        ComboText,
+        HudAnchor(HudRegion::TopLeft),
+    ));
+    commands.spawn((
+        TextBundle::from_section("", theme.hud.clone()),
+        HudBinding::<HighScore>::default(),
+        HudAnchor(HudRegion::TopRight),
+    ));
+    let hp_text = hp_text(&locale, config.player.max_hp, config.player.max_hp);
+    spawn_status_hud(&mut commands, &theme, &palette, hp_text);
+}
+
+fn hp_text(locale: &Locale, hp: u32, max_hp: u32) -> String {
+    locale.format("hud-hp", &fluent_args!["hp" => hp, "max" => max_hp])
+}
+
+/// Lives, bombs and the HP bar, stacked in the bottom-left corner
+fn spawn_status_hud(commands: &mut Commands, theme: &UiTheme, palette: &Palette, hp_text: String) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4.),
+                    ..default()
+                },
+                ..default()
+            },
+            HudAnchor(HudRegion::BottomLeft),
+        ))
+        .with_children(|parent| {
+            spawn_hud_icon_row::<Lives>(parent);
+            spawn_hud_icon_row::<Bombs>(parent);
+            parent.spawn((TextBundle::from_section(hp_text, theme.hud.clone()), HpText));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(200.),
+                        height: Val::Px(16.),
+                        border: UiRect::all(Val::Px(2.)),
+                        ..default()
+                    },
+                    border_color: BorderColor(Color::BLACK),
+                    background_color: Color::DARK_GRAY.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(100.),
+                                height: Val::Percent(100.),
+                                ..default()
+                            },
+                            background_color: palette.hp_bar.into(),
+                            ..default()
+                        },
+                        HpBarFill,
+                    ));
+                });
+        });
+}
+
+fn spawn_hud_icon_row<R: HudIcons>(parent: &mut ChildBuilder) {
+    parent.spawn((
+        NodeBundle {
+            style: Style {
+                height: Val::Px(HUD_ICON_SIZE),
+                column_gap: Val::Px(4.),
+                ..default()
+            },
+            ..default()
+        },
+        HudIconRow::<R>::default(),
+    ));
+}
+
+/// Reacts to any HP change, whether from being hit or from healing
+fn update_hp_bar(
+    player_query: Query<(Ref<HitPoints>, &MaxHitPoints), With<Player>>,
+    mut fill_query: Query<&mut Style, With<HpBarFill>>,
+    mut text_query: Query<&mut Text, With<HpText>>,
+    locale: Res<Locale>,
+) {
+    for (hp, max_hp) in player_query.iter() {
+        if !hp.is_changed() && !locale.is_changed() {
+            continue;
+        }
+        let fraction = (hp.0 as f32 / max_hp.0 as f32).clamp(0., 1.);
+        for mut style in fill_query.iter_mut() {
+            style.width = Val::Percent(fraction * 100.);
+        }
+        for mut text in text_query.iter_mut() {
+            text.sections[0].value = hp_text(&locale, hp.0, max_hp.0);
+        }
+    }
+}
+
+pub(crate) fn update_hud_binding<R: HudValue>(
+    value: Res<R>,
+    locale: Res<Locale>,
+    mut query: Query<(Ref<HudBinding<R>>, &mut Text)>,
+) {
+    for (binding, mut text) in query.iter_mut() {
+        if value.is_changed() || locale.is_changed() || binding.is_added() {
+            text.sections[0].value = value.hud_text(&locale);
+        }
+    }
+}
+
+fn update_hud_icon_row<R: HudIcons>(
+    mut commands: Commands,
+    value: Res<R>,
+    palette: Res<Palette>,
+    query: Query<(Entity, Ref<HudIconRow<R>>)>,
+) {
+    for (entity, row) in query.iter() {
+        if value.is_changed() || palette.is_changed() || row.is_added() {
+            commands
+                .entity(entity)
+                .despawn_descendants()
+                .with_children(|parent| {
+                    for _ in 0..value.icon_count() {
+                        parent.spawn(NodeBundle {
+                            style: Style {
+                                width: Val::Px(HUD_ICON_SIZE),
+                                height: Val::Px(HUD_ICON_SIZE),
+                                ..default()
+                            },
+                            background_color: R::icon_color(&palette).into(),
+                            ..default()
+                        });
+                    }
+                });
+        }
+    }
+}
+
+/// Hidden below two kills, then grows and heats up from white to red as the
+/// combo climbs
+fn update_combo_text(
+    combo: Res<Combo>,
+    locale: Res<Locale>,
+    mut query: Query<&mut Text, With<ComboText>>,
+) {
+    let heat = (combo.kills as f32 / 30.).min(1.);
+    for mut text in query.iter_mut() {
+        let section = &mut text.sections[0];
+        section.value = if combo.kills >= 2 {
+            locale.format(
+                "hud-combo",
+                &fluent_args![
+                    "kills" => combo.kills,
+                    "multiplier" => combo.score_multiplier(),
+                ],
+            )
+        } else {
+            String::new()
+        };
+        section.style.font_size = 20. + 20. * heat;
+        section.style.color = if heat < 0.5 {
+            lerp_color(Color::WHITE, Color::YELLOW, heat * 2.)
+        } else {
+            lerp_color(Color::YELLOW, Color::RED, heat * 2. - 1.)
+        };
+    }
+}
+
+fn lerp_color(from: Color, to: Color, t: f32) -> Color {
+    Color::from(Vec4::from(from).lerp(Vec4::from(to), t))
+}
+
+fn spawn_main_menu(mut commands: Commands, theme: Res<UiTheme>, locale: Res<Locale>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.),
+                    ..default()
+                },
+                ..default()
+            },
+            MainMenuScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(locale.text("game-title", theme.title.clone()));
+            parent
+                .spawn(NodeBundle {
+                    style: wrapping_column(20.),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    spawn_menu_button(parent, &theme, &locale, "menu-play", MenuButton::Play);
+                    spawn_menu_button(
+                        parent,
+                        &theme,
+                        &locale,
+                        "menu-practice",
+                        MenuButton::Practice,
+                    );
+                    spawn_menu_button(
+                        parent,
+                        &theme,
+                        &locale,
+                        "button-settings",
+                        MenuButton::Settings,
+                    );
+                    spawn_menu_button(
+                        parent,
+                        &theme,
+                        &locale,
+                        "menu-high-scores",
+                        MenuButton::HighScores,
+                    );
+                    spawn_menu_button(
+                        parent,
+                        &theme,
+                        &locale,
+                        "menu-statistics",
+                        MenuButton::Statistics,
+                    );
+                    spawn_menu_button(
+                        parent,
+                        &theme,
+                        &locale,
+                        "menu-save-slots",
+                        MenuButton::SaveSlots,
+                    );
+                    spawn_menu_button(parent, &theme, &locale, "menu-credits", MenuButton::Credits);
+                    spawn_menu_button(parent, &theme, &locale, "button-quit", MenuButton::Exit);
+                });
+        });
+}
+
+fn despawn_main_menu(mut commands: Commands, query: Query<Entity, With<MainMenuScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn spawn_pause_menu(mut commands: Commands, theme: Res<UiTheme>, locale: Res<Locale>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.6).into(),
+                ..default()
+            },
+            PauseMenu,
+        ))
+        .with_children(|parent| {
+            parent.spawn(locale.text("paused", theme.title.clone()));
+            spawn_menu_button(parent, &theme, &locale, "pause-resume", MenuButton::Resume);
+            spawn_menu_button(
+                parent,
+                &theme,
+                &locale,
+                "button-settings",
+                MenuButton::Settings,
+            );
+            spawn_menu_button(parent, &theme, &locale, "button-quit", MenuButton::Quit);
+        });
+}
+
+fn despawn_pause_menu(mut commands: Commands, query: Query<Entity, With<PauseMenu>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn spawn_game_over_screen(
+    mut commands: Commands,
+    theme: Res<UiTheme>,
+    score: Res<Score>,
+    high_score: Res<HighScore>,
+    run_stats: Res<RunStats>,
+    mode: Res<GameMode>,
+    checkpoint: Res<StageCheckpoint>,
+    time_limit: Res<TimeLimit>,
+    name_entry: Res<NameEntry>,
+    high_scores: Res<HighScores>,
+    locale: Res<Locale>,
+    recorder: Res<ReplayRecorder>,
+    playback: Res<ReplayPlayback>,
+) {
+    let title =
+        if matches!(*mode, GameMode::TimeAttack | GameMode::Caravan) && time_limit.0.finished() {
+            "time-up"
+        } else {
+            "game-over"
+        };
+    let seconds = run_stats.time_survived.as_secs();
+    let time = format!("{}:{:02}", seconds / 60, seconds % 60);
+    let stats = [
+        locale.format("game-over-score", &fluent_args!["score" => score.0]),
+        locale.format(
+            if score.0 > 0 && score.0 == high_score.0 {
+                "game-over-new-record"
+            } else {
+                "game-over-high-score"
+            },
+            &fluent_args!["score" => high_score.0],
+        ),
+        locale.format(
+            "game-over-kills",
+            &fluent_args!["kills" => run_stats.enemies_killed],
+        ),
+        locale.format("game-over-time", &fluent_args!["time" => time]),
+    ];
+    let name_prompt = name_entry.prompt(&high_scores, &locale);
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.),
+                    ..default()
+                },
+                ..default()
+            },
+            GameOverScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(locale.text(title, theme.title.clone()));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        row_gap: Val::Px(4.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for line in stats {
+                        parent.spawn(TextBundle::from_section(line, theme.hud.clone()));
+                    }
+                });
+            if let Some(prompt) = name_prompt {
+                parent.spawn((
+                    TextBundle::from_section(prompt, theme.hud.clone()),
+                    NameEntryText,
+                ));
+            }
+            // Time attack allows no continues, and replays play back as they were
+            if matches!(*mode, GameMode::Stages | GameMode::Practice)
+                && checkpoint.last.is_some()
+                && playback.seed().is_none()
+            {
+                spawn_menu_button(
+                    parent,
+                    &theme,
+                    &locale,
+                    "button-continue",
+                    MenuButton::Continue,
+                );
+            }
+            spawn_menu_button(
+                parent,
+                &theme,
+                &locale,
+                "game-over-retry",
+                MenuButton::Retry,
+            );
+            if recorder.can_save() {
+                spawn_menu_button(
+                    parent,
+                    &theme,
+                    &locale,
+                    "game-over-save-replay",
+                    MenuButton::SaveReplay,
+                );
+            }
+            spawn_menu_button(
+                parent,
+                &theme,
+                &locale,
+                "game-over-main-menu",
+                MenuButton::MainMenu,
+            );
+        });
+}
+
+fn despawn_game_over_screen(mut commands: Commands, query: Query<Entity, With<GameOverScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn spawn_menu_button(
+    parent: &mut ChildBuilder,
+    theme: &UiTheme,
+    locale: &Locale,
+    label: &'static str,
+    button: MenuButton,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    min_width: Val::Px(240.),
+                    height: Val::Px(65.),
+                    border: UiRect::all(Val::Px(5.)),
+                    padding: UiRect::horizontal(Val::Px(16.)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                border_color: BorderColor(Color::BLACK),
+                background_color: Color::WHITE.into(),
+                ..default()
+            },
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn(locale.text(label, theme.button.clone()));
+        });
+}
+
+fn menu_buttons(
+    mut commands: Commands,
+    theme: Res<UiTheme>,
+    locale: Res<Locale>,
+    interaction_query: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+    mut next_settings_state: ResMut<NextState<SettingsMenuState>>,
+    mut checkpoint: ResMut<StageCheckpoint>,
+    mut exit_events: EventWriter<AppExit>,
+    mut save_replay_events: EventWriter<SaveReplayEvent>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            MenuButton::Resume => next_pause_state.set(PauseState::Unpaused),
+            MenuButton::Settings => next_settings_state.set(SettingsMenuState::Open),
+            MenuButton::Play => next_state.set(AppState::ModeSelect),
+            MenuButton::Practice => next_state.set(AppState::PracticeSelect),
+            MenuButton::HighScores => next_state.set(AppState::HighScores),
+            MenuButton::Statistics => next_state.set(AppState::Statistics),
+            MenuButton::SaveSlots => next_state.set(AppState::SlotSelect),
+            MenuButton::Credits => next_state.set(AppState::Credits),
+            MenuButton::Exit => exit_events.send(AppExit),
+            MenuButton::Continue => {
+                checkpoint.resume = true;
+                next_state.set(AppState::Restarting);
+            }
+            MenuButton::Retry => next_state.set(AppState::Restarting),
+            MenuButton::SaveReplay => save_replay_events.send_default(),
+            MenuButton::MainMenu => next_state.set(AppState::MainMenu),
+            MenuButton::Quit => spawn_confirm_dialog(
+                &mut commands,
+                &theme,
+                ConfirmDialog::new(
+                    &locale,
+                    locale.get("quit-title"),
+                    locale.get("quit-message"),
+                ),
+                |world| {
+                    world
+                        .resource_mut::<NextState<AppState>>()
+                        .set(AppState::MainMenu)
+                },
+            ),
+        }
+    }
+}